@@ -0,0 +1,137 @@
+use server_info_packets::server_info_packet::ServerInfo;
+use std::fmt::Write;
+
+/// Nagios plugin exit codes, ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok = 0,
+    Warning = 1,
+    Critical = 2,
+    Unknown = 3,
+}
+
+impl Status {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Warning => "WARNING",
+            Status::Critical => "CRITICAL",
+            Status::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Warning and critical levels in percent, a check passes while the value stays below both
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub warn: Option<f32>,
+    pub crit: Option<f32>,
+}
+
+impl Thresholds {
+    pub fn is_set(&self) -> bool {
+        self.warn.is_some() || self.crit.is_some()
+    }
+
+    fn status_of(&self, value: f32) -> Status {
+        if self.crit.map(|crit| value >= crit).unwrap_or(false) {
+            Status::Critical
+        } else if self.warn.map(|warn| value >= warn).unwrap_or(false) {
+            Status::Warning
+        } else {
+            Status::Ok
+        }
+    }
+
+    /// Nagios performance data, e.g. "cpu=12.50%;80;95"
+    fn perf_data(&self, name: &str, value: f32) -> String {
+        let level = |level: Option<f32>| level.map(|l| l.to_string()).unwrap_or_default();
+        format!(
+            "{name}={value:.2}%;{};{}",
+            level(self.warn),
+            level(self.crit)
+        )
+    }
+}
+
+/// Checks a packet against the cpu and memory thresholds, returning the worst status and a plugin output line
+pub fn check(info: &ServerInfo, cpu: Thresholds, memory: Thresholds) -> (Status, String) {
+    let memory_usage = if info.total_ram == 0 {
+        0.0
+    } else {
+        info.used_memory as f32 / info.total_ram as f32 * 100.0
+    };
+
+    let mut status = Status::Ok;
+    let mut problems: Vec<String> = vec![];
+    let mut perf_data: Vec<String> = vec![];
+
+    for (name, value, thresholds) in [
+        ("cpu", info.avg_cpu_usage, cpu),
+        ("memory", memory_usage, memory),
+    ] {
+        let value_status = thresholds.status_of(value);
+        if value_status != Status::Ok {
+            problems.push(format!("{name} {value:.2} %"));
+        }
+        status = status.max(value_status);
+        perf_data.push(thresholds.perf_data(name, value));
+    }
+
+    let mut line = String::new();
+    let _ = write!(line, "{} - {}", status.label(), info.host_name);
+    if !problems.is_empty() {
+        let _ = write!(line, " {}", problems.join(", "));
+    }
+    let _ = write!(line, " | {}", perf_data.join(" "));
+
+    (status, line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(cpu: f32, used_memory: u64) -> ServerInfo {
+        ServerInfo {
+            host_name: "box".to_string(),
+            avg_cpu_usage: cpu,
+            total_ram: 100,
+            used_memory,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn worst_threshold_decides_the_status() {
+        let cpu = Thresholds {
+            warn: Some(80.0),
+            crit: Some(95.0),
+        };
+        let memory = Thresholds {
+            warn: Some(90.0),
+            crit: None,
+        };
+
+        let (status, line) = check(&info(10.0, 50), cpu, memory);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(line, "OK - box | cpu=10.00%;80;95 memory=50.00%;90;");
+
+        assert_eq!(check(&info(85.0, 50), cpu, memory).0, Status::Warning);
+        assert_eq!(check(&info(10.0, 95), cpu, memory).0, Status::Warning);
+
+        let (status, line) = check(&info(99.0, 95), cpu, memory);
+        assert_eq!(status, Status::Critical);
+        assert!(line.starts_with("CRITICAL - box cpu 99.00 %, memory 95.00 %"));
+    }
+
+    #[test]
+    fn missing_thresholds_never_alert() {
+        let (status, _) = check(
+            &info(100.0, 100),
+            Thresholds::default(),
+            Thresholds::default(),
+        );
+        assert_eq!(status, Status::Ok);
+    }
+}
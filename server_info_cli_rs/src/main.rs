@@ -0,0 +1,264 @@
+use crate::check::{Status, Thresholds};
+use clap::{Parser, ValueEnum};
+use server_info_client::{
+    discover, Client, ClientError, ClientOptions, Compression, Encoding, ExportFormat, TlsOptions,
+};
+use server_info_packets::discovery::DISCOVERY_PORT;
+use server_info_packets::server_info_packet::ServerInfo;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+mod check;
+
+/// Prints the info of a server_info_server_rs, once or continuously.
+///
+/// Exits with 0 (OK), 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN) like a Nagios plugin when thresholds are given.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Address of the server
+    #[arg(default_value = "localhost:8111")]
+    address: String,
+
+    /// Output format of the server info
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Keep printing the server info instead of exiting after the first one
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Seconds between two prints when watching
+    #[arg(short, long, default_value = "2", value_parser = parse_interval)]
+    interval: Duration,
+
+    /// Seconds to wait for the server before giving up
+    #[arg(short, long, default_value_t = 5)]
+    timeout: u64,
+
+    /// Token to send when the server asks for one
+    #[arg(long)]
+    token: Option<String>,
+
+    /// Connect with tls, e.g. to a tls terminating proxy in front of the server
+    #[arg(long)]
+    tls: bool,
+
+    /// Pem file with the certificates to trust for tls, instead of the usual web roots
+    #[arg(long, requires = "tls")]
+    tls_ca: Option<PathBuf>,
+
+    /// Encoding to ask the server for: json, msgpack, cbor or bincode
+    #[arg(long, default_value_t = Encoding::Json)]
+    encoding: Encoding,
+
+    /// Compression to ask the server for, for slow or metered links: none, deflate or zstd
+    #[arg(long, default_value_t = Compression::None)]
+    compression: Compression,
+
+    /// List the servers answering on the local network and exit
+    #[arg(long)]
+    discover: bool,
+
+    /// List the servers a server_info_hub_rs collects from and exit
+    #[arg(long)]
+    hosts: bool,
+
+    /// Print the info of this host of a server_info_hub_rs, instead of its first one
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Print the hardware of the server and exit
+    #[arg(long)]
+    inventory: bool,
+
+    /// Print the infos the server kept as csv or ndjson and exit
+    #[arg(long)]
+    export: Option<ExportFormat>,
+
+    /// Exported infos are dated from this unix time in seconds on
+    #[arg(long, default_value_t = 0)]
+    from: i64,
+
+    /// Exported infos are dated up to this unix time in seconds
+    #[arg(long, default_value_t = i64::MAX)]
+    to: i64,
+
+    /// Columns to export, e.g. date,cpu,disk./,net.eth0.rx, every column when not given
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Average cpu usage in percent that results in a WARNING
+    #[arg(long)]
+    warn_cpu: Option<f32>,
+
+    /// Average cpu usage in percent that results in a CRITICAL
+    #[arg(long)]
+    crit_cpu: Option<f32>,
+
+    /// Memory usage in percent that results in a WARNING
+    #[arg(long)]
+    warn_mem: Option<f32>,
+
+    /// Memory usage in percent that results in a CRITICAL
+    #[arg(long)]
+    crit_mem: Option<f32>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// The same text the ServerInfo Display impl produces
+    Text,
+    Json,
+    Yaml,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if args.discover {
+        return match discover(DISCOVERY_PORT, Duration::from_secs(args.timeout.min(2))) {
+            Ok(found) => {
+                for server in found {
+                    println!("{}: {}", server.address, server.announcement);
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => unknown(&format!("Unable to look for servers: {e}")),
+        };
+    }
+
+    let cpu = Thresholds {
+        warn: args.warn_cpu,
+        crit: args.crit_cpu,
+    };
+    let memory = Thresholds {
+        warn: args.warn_mem,
+        crit: args.crit_mem,
+    };
+
+    let options = ClientOptions {
+        timeout: Duration::from_secs(args.timeout),
+        auth_token: args.token,
+        tls: args.tls.then_some(TlsOptions {
+            server_name: None,
+            ca_file: args.tls_ca,
+        }),
+        encodings: vec![args.encoding],
+        compressions: vec![args.compression],
+        // a single check has nothing to save the round trip for
+        deltas: args.watch,
+    };
+
+    let mut client = match Client::connect(&args.address, &options) {
+        Ok(client) => client,
+        Err(e) => return unknown(&format!("Unable to connect to {}: {e}", args.address)),
+    };
+
+    if args.hosts {
+        return match client.hosts() {
+            Ok(hosts) => {
+                for host in hosts {
+                    println!("{host}");
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => unknown(&format!(
+                "Unable to list the hosts of {}: {e}",
+                args.address
+            )),
+        };
+    }
+    if let Some(host) = &args.host {
+        if let Err(e) = client.select_host(host) {
+            return unknown(&format!("Unable to select {host} on {}: {e}", args.address));
+        }
+    }
+
+    if args.inventory {
+        return match client.inventory() {
+            Ok(inventory) => {
+                print!("{inventory}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => unknown(&format!(
+                "Unable to take the inventory of {}: {e}",
+                args.address
+            )),
+        };
+    }
+
+    if let Some(format) = args.export {
+        return match client.export(args.from, args.to, format, &args.columns) {
+            Ok(text) => {
+                print!("{text}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => unknown(&format!("Unable to export from {}: {e}", args.address)),
+        };
+    }
+
+    loop {
+        let info = match client.next_info() {
+            Ok(info) => info,
+            Err(ClientError::ServerStopped) => {
+                return unknown(&format!("Server {} stopped.", args.address))
+            }
+            Err(e) => return unknown(&format!("Unable to read from {}: {e}", args.address)),
+        };
+
+        let status = if cpu.is_set() || memory.is_set() {
+            let (status, line) = check::check(&info, cpu, memory);
+            println!("{line}");
+            status
+        } else {
+            match format_info(&info, args.format) {
+                Ok(formatted) => println!("{formatted}"),
+                Err(e) => return unknown(&e),
+            }
+            Status::Ok
+        };
+
+        if !args.watch {
+            return ExitCode::from(status as u8);
+        }
+        thread::sleep(args.interval);
+    }
+}
+
+fn format_info(info: &ServerInfo, format: Format) -> Result<String, String> {
+    match format {
+        Format::Text => Ok(info.to_string()),
+        Format::Json => serde_json::to_string_pretty(info).map_err(|e| e.to_string()),
+        Format::Yaml => serde_yaml::to_string(info).map_err(|e| e.to_string()),
+    }
+}
+
+fn unknown(message: &str) -> ExitCode {
+    println!("{} - {message}", Status::Unknown.label());
+    ExitCode::from(Status::Unknown as u8)
+}
+
+/// Seconds as a duration, a value Duration can not hold or that would never wait is rejected by clap
+fn parse_interval(seconds: &str) -> Result<Duration, String> {
+    let seconds: f32 = seconds.parse().map_err(|e| format!("{e}"))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err("expected a number of seconds greater than 0".to_string());
+    }
+    Duration::try_from_secs_f32(seconds).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_intervals_that_can_not_be_slept() {
+        assert_eq!(parse_interval("0.5"), Ok(Duration::from_millis(500)));
+        for interval in ["-1", "0", "nan", "inf", "1e30", "soon"] {
+            assert!(parse_interval(interval).is_err(), "{interval}");
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use crate::error::ClientError;
+use crate::options::ClientOptions;
+use crate::update::{
+    ack_reply, answer, auth_reply, hello_reply, read_timeout_after, wants_hello, Greeting,
+    Progress, Update,
+};
+use futures_util::Stream;
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::delta::DeltaDecoder;
+use server_info_packets::export::ExportFormat;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::inventory::Inventory;
+use server_info_packets::protocol::{
+    write_message_async, ClientRequest, FrameReader, Sections, ServerMessage, HEARTBEAT_INTERVAL,
+};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncStream for T {}
+
+/// The tokio counterpart of Client, with the same methods.
+pub struct AsyncClient {
+    stream: Box<dyn AsyncStream>,
+    timeout: Duration,
+    /// longer than timeout while subscribed with an interval
+    read_timeout: Duration,
+    greeting: Option<ServerInfo>,
+    codec: Codec,
+    reader: FrameReader,
+    deltas: DeltaDecoder,
+    push: bool,
+    last_sent: Instant,
+}
+
+impl AsyncClient {
+    /// Connects and completes the handshake, authenticating when the server asks for a token
+    pub async fn connect(address: &str, options: &ClientOptions) -> Result<Self, ClientError> {
+        let tcp = with_timeout(options.timeout, async {
+            Ok(TcpStream::connect(address).await?)
+        })
+        .await?;
+
+        let stream: Box<dyn AsyncStream> = match &options.tls {
+            None => Box::new(tcp),
+            #[cfg(feature = "tls")]
+            Some(tls) => {
+                let connector = tokio_rustls::TlsConnector::from(crate::tls::client_config(tls)?);
+                let server_name = crate::tls::server_name(tls, address)?;
+                let stream = with_timeout(options.timeout, async {
+                    connector
+                        .connect(server_name, tcp)
+                        .await
+                        .map_err(|e| ClientError::Tls(e.to_string()))
+                })
+                .await?;
+                Box::new(stream)
+            }
+            #[cfg(not(feature = "tls"))]
+            Some(_) => {
+                return Err(ClientError::Tls(
+                    "server_info_client was built without the tls feature.".to_string(),
+                ))
+            }
+        };
+
+        let mut client = Self {
+            stream,
+            timeout: options.timeout,
+            read_timeout: options.timeout,
+            greeting: None,
+            codec: Codec::default(),
+            reader: FrameReader::default(),
+            deltas: DeltaDecoder::default(),
+            push: false,
+            last_sent: Instant::now(),
+        };
+
+        let first = client.read().await?;
+        let info = match Greeting::from_message(first, options.auth_token.as_deref())? {
+            Greeting::Info(info) => *info,
+            Greeting::AuthRequired(token) => {
+                client.send(&ClientRequest::Auth { token }).await?;
+                auth_reply(client.read().await?)?
+            }
+        };
+        client.greeting = Some(info);
+
+        if wants_hello(options) {
+            let hello = ClientRequest::Hello {
+                encodings: options.encodings.clone(),
+                compressions: options.compressions.clone(),
+                deltas: options.deltas,
+            };
+            client.send(&hello).await?;
+            client.codec = hello_reply(client.read().await?)?;
+            // the info answering the hello replaces the greeting
+            client.greeting = None;
+            client.greeting = Some(client.receive().await?.info);
+        }
+        Ok(client)
+    }
+
+    /// Sends a request without waiting for the answer, which the next receive returns
+    pub async fn send(&mut self, request: &ClientRequest) -> Result<(), ClientError> {
+        if let Some(read_timeout) = read_timeout_after(request, self.timeout) {
+            self.read_timeout = read_timeout;
+        }
+        if let ClientRequest::Subscribe { push, .. } = request {
+            self.push = *push;
+        }
+        let stream = &mut self.stream;
+        let codec = self.codec;
+        with_timeout(self.timeout, async {
+            Ok(write_message_async(stream, codec, request).await?)
+        })
+        .await?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Waits for the answer to the last request, or the next pushed update,
+    /// or returns the greeting if nothing was requested yet
+    pub async fn receive(&mut self) -> Result<Update, ClientError> {
+        if let Some(info) = self.greeting.take() {
+            return Ok(Update {
+                info,
+                ..Default::default()
+            });
+        }
+
+        let mut update = Update::default();
+        loop {
+            if self.push && self.last_sent.elapsed() >= HEARTBEAT_INTERVAL {
+                self.send(&ClientRequest::Heartbeat).await?;
+            }
+            match update.add(self.read().await?, &mut self.deltas)? {
+                Progress::Pending => {}
+                Progress::Complete => return Ok(update),
+                Progress::Gap => self.send(&ClientRequest::Resync).await?,
+            }
+        }
+    }
+
+    /// Sends a request and waits for its answer
+    pub async fn request(&mut self, request: &ClientRequest) -> Result<Update, ClientError> {
+        self.greeting = None;
+        self.send(request).await?;
+        self.receive().await
+    }
+
+    /// Returns the next ServerInfo, ignoring log lines and errors
+    pub async fn next_info(&mut self) -> Result<ServerInfo, ClientError> {
+        match self.greeting.take() {
+            Some(info) => Ok(info),
+            None => Ok(self.request(&ClientRequest::Next).await?.info),
+        }
+    }
+
+    /// Lists the servers a hub collects from, a server that is not a hub lists only itself
+    pub async fn hosts(&mut self) -> Result<Vec<HostStatus>, ClientError> {
+        answer(self.request(&ClientRequest::ListHosts).await?, |update| {
+            update.hosts.take()
+        })
+    }
+
+    /// Makes a hub send the infos of the host called name, returning its latest one
+    pub async fn select_host(&mut self, name: &str) -> Result<ServerInfo, ClientError> {
+        let request = ClientRequest::SelectHost {
+            name: name.to_string(),
+        };
+        let mut update = self.request(&request).await?;
+        update.refused()?;
+        Ok(update.info)
+    }
+
+    /// The infos the server kept of the selected host dated from..=to (unix seconds), oldest first
+    pub async fn history(&mut self, from: i64, to: i64) -> Result<Vec<ServerInfo>, ClientError> {
+        answer(
+            self.request(&ClientRequest::History { from, to }).await?,
+            |update| update.history.take(),
+        )
+    }
+
+    /// The infos the server kept of the selected host dated from..=to (unix seconds) as csv or ndjson,
+    /// with only the named columns or all of them when empty
+    pub async fn export(
+        &mut self,
+        from: i64,
+        to: i64,
+        format: ExportFormat,
+        columns: &[String],
+    ) -> Result<String, ClientError> {
+        let request = ClientRequest::Export {
+            from,
+            to,
+            format,
+            columns: columns.to_vec(),
+        };
+        answer(self.request(&request).await?, |update| update.export.take())
+    }
+
+    /// The hardware of the server, hubs do not take inventories of their hosts
+    pub async fn inventory(&mut self) -> Result<Inventory, ClientError> {
+        answer(self.request(&ClientRequest::Inventory).await?, |update| {
+            update.inventory.take()
+        })
+    }
+
+    /// Turns the connection into an agent reporting the infos of the host called name to a hub,
+    /// token is the hubs agent token
+    pub async fn register_agent(&mut self, name: &str, token: &str) -> Result<(), ClientError> {
+        self.greeting = None;
+        let request = ClientRequest::Agent {
+            name: name.to_string(),
+            token: token.to_string(),
+        };
+        self.send(&request).await?;
+        self.wait_for_ack().await
+    }
+
+    /// Sends infos to the hub the agent registered with, at most MAX_REPORT_LEN, returning once the hub stored them
+    pub async fn report(&mut self, infos: Vec<ServerInfo>) -> Result<(), ClientError> {
+        self.send(&ClientRequest::Report(infos)).await?;
+        self.wait_for_ack().await
+    }
+
+    /// The encoding the server agreed to
+    pub fn encoding(&self) -> Encoding {
+        self.codec.encoding
+    }
+
+    /// The compression the server agreed to
+    pub fn compression(&self) -> Compression {
+        self.codec.compression
+    }
+
+    /// Turns the client into a stream over every section of the ServerInfos the server pushes every interval,
+    /// ending after the first error
+    pub fn subscribe(
+        self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<ServerInfo, ClientError>> {
+        let subscribe = ClientRequest::Subscribe {
+            sections: Sections::default(),
+            interval_ms: interval.as_millis() as u64,
+            push: true,
+        };
+
+        futures_util::stream::unfold(Some((self, Some(subscribe))), |state| async move {
+            let (mut client, mut subscribe) = state?;
+            let info = match client.greeting.take() {
+                Some(info) => Ok(info),
+                None => client.next_pushed(subscribe.take()).await,
+            };
+            match info {
+                Ok(info) => Some((Ok(info), Some((client, subscribe)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Sends the subscription the first time, then waits for the next pushed info
+    async fn next_pushed(
+        &mut self,
+        subscribe: Option<ClientRequest>,
+    ) -> Result<ServerInfo, ClientError> {
+        if let Some(subscribe) = subscribe {
+            self.send(&subscribe).await?;
+        }
+        Ok(self.receive().await?.info)
+    }
+
+    async fn wait_for_ack(&mut self) -> Result<(), ClientError> {
+        loop {
+            if let Some(result) = ack_reply(self.read().await?) {
+                return result;
+            }
+        }
+    }
+
+    /// Cancel safe, a frame cut off by the timeout is finished by the next read
+    async fn read(&mut self) -> Result<ServerMessage, ClientError> {
+        let reader = &mut self.reader;
+        let stream = &mut self.stream;
+        let codec = self.codec;
+        with_timeout(self.read_timeout, async {
+            Ok(reader.read_async(stream, codec).await?)
+        })
+        .await
+    }
+}
+
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl Future<Output = Result<T, ClientError>>,
+) -> Result<T, ClientError> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| ClientError::Timeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use server_info_packets::protocol::{read_message, write_message};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[tokio::test]
+    async fn subscribe_streams_infos() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            for total_cpus in 1..=3 {
+                let info = ServerInfo {
+                    total_cpus,
+                    ..Default::default()
+                };
+                write_message(
+                    &mut stream,
+                    Encoding::Json,
+                    &ServerMessage::Info(Box::new(info)),
+                )
+                .unwrap();
+                // the subscription follows the greeting, every info after it is pushed
+                if total_cpus == 1 {
+                    let request: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+                    assert!(matches!(
+                        request,
+                        ClientRequest::Subscribe { push: true, .. }
+                    ));
+                }
+            }
+            // keep the connection open until the client is done reading
+            let _ = read_message::<ClientRequest>(&mut stream, Encoding::Json);
+        });
+
+        let options = ClientOptions {
+            deltas: false,
+            ..Default::default()
+        };
+        let client = AsyncClient::connect(&address, &options).await.unwrap();
+        let cpus: Vec<usize> = client
+            .subscribe(Duration::from_millis(1))
+            .take(3)
+            .map(|info| info.unwrap().total_cpus)
+            .collect()
+            .await;
+        assert_eq!(cpus, vec![1, 2, 3]);
+    }
+}
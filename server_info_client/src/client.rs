@@ -0,0 +1,555 @@
+use crate::error::ClientError;
+use crate::options::ClientOptions;
+use crate::update::{
+    ack_reply, answer, auth_reply, hello_reply, read_timeout_after, wants_hello, Greeting,
+    Progress, Update,
+};
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::delta::DeltaDecoder;
+use server_info_packets::export::ExportFormat;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::inventory::Inventory;
+use server_info_packets::protocol::{
+    write_message, ClientRequest, FrameReader, Sections, ServerMessage, HEARTBEAT_INTERVAL,
+};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+/// A blocking connection to a server.
+///
+/// The server answers every request with any log lines or errors, followed by a ServerInfo.
+/// Use request for a round trip, or send and receive to ask for the next update while doing something else.
+///
+/// After sending a ClientRequest::Subscribe with push set, the server sends updates on its own instead,
+/// which receive waits for and try_receive picks up without blocking, and requests are no longer answered.
+pub struct Client {
+    stream: Box<dyn Stream>,
+    /// kept to shut the connection down, the stream may be wrapped in tls
+    tcp: TcpStream,
+    timeout: Duration,
+    /// the info the server greets with, handed out by the first receive
+    greeting: Option<ServerInfo>,
+    codec: Codec,
+    reader: FrameReader,
+    deltas: DeltaDecoder,
+    /// the update try_receive is still reading the messages of
+    partial: Update,
+    /// whether the server pushes updates, which needs heartbeats
+    push: bool,
+    last_sent: Instant,
+}
+
+impl Client {
+    /// Connects and completes the handshake, authenticating when the server asks for a token
+    pub fn connect(address: &str, options: &ClientOptions) -> Result<Self, ClientError> {
+        let tcp = connect_tcp(address, options.timeout)?;
+        tcp.set_read_timeout(Some(options.timeout))?;
+        tcp.set_write_timeout(Some(options.timeout))?;
+
+        let stream: Box<dyn Stream> = match &options.tls {
+            None => Box::new(tcp.try_clone()?),
+            #[cfg(feature = "tls")]
+            Some(tls) => Box::new(crate::tls::wrap(tcp.try_clone()?, address, tls)?),
+            #[cfg(not(feature = "tls"))]
+            Some(_) => {
+                return Err(ClientError::Tls(
+                    "server_info_client was built without the tls feature.".to_string(),
+                ))
+            }
+        };
+
+        let mut client = Self {
+            stream,
+            tcp,
+            timeout: options.timeout,
+            greeting: None,
+            codec: Codec::default(),
+            reader: FrameReader::default(),
+            deltas: DeltaDecoder::default(),
+            partial: Update::default(),
+            push: false,
+            last_sent: Instant::now(),
+        };
+
+        let first = client.read()?;
+        let info = match Greeting::from_message(first, options.auth_token.as_deref())? {
+            Greeting::Info(info) => *info,
+            Greeting::AuthRequired(token) => {
+                client.send(&ClientRequest::Auth { token })?;
+                auth_reply(client.read()?)?
+            }
+        };
+        client.greeting = Some(info);
+
+        if wants_hello(options) {
+            let hello = ClientRequest::Hello {
+                encodings: options.encodings.clone(),
+                compressions: options.compressions.clone(),
+                deltas: options.deltas,
+            };
+            client.send(&hello)?;
+            client.codec = hello_reply(client.read()?)?;
+            // the info answering the hello replaces the greeting
+            client.greeting = None;
+            client.greeting = Some(client.receive()?.info);
+        }
+        Ok(client)
+    }
+
+    /// Sends a request without waiting for the answer, which the next receive returns
+    pub fn send(&mut self, request: &ClientRequest) -> Result<(), ClientError> {
+        if let Some(read_timeout) = read_timeout_after(request, self.timeout) {
+            self.tcp.set_read_timeout(Some(read_timeout))?;
+        }
+        if let ClientRequest::Subscribe { push, .. } = request {
+            self.push = *push;
+        }
+        write_message(&mut self.stream, self.codec, request)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Waits for the answer to the last request, or the next pushed update,
+    /// or returns the greeting if nothing was requested yet
+    pub fn receive(&mut self) -> Result<Update, ClientError> {
+        if let Some(update) = self.take_greeting() {
+            return Ok(update);
+        }
+
+        loop {
+            self.heartbeat_if_due()?;
+            let message = self.read()?;
+            if let Some(update) = self.add(message)? {
+                return Ok(update);
+            }
+        }
+    }
+
+    /// Returns the next update if it arrived completely, without blocking
+    pub fn try_receive(&mut self) -> Result<Option<Update>, ClientError> {
+        if let Some(update) = self.take_greeting() {
+            return Ok(Some(update));
+        }
+
+        self.heartbeat_if_due()?;
+        loop {
+            self.tcp.set_nonblocking(true)?;
+            let message = self.reader.read(&mut self.stream, self.codec);
+            self.tcp.set_nonblocking(false)?;
+
+            let message = match message {
+                Ok(message) => message,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(update) = self.add(message)? {
+                return Ok(Some(update));
+            }
+        }
+    }
+
+    /// Sends a request and waits for its answer
+    pub fn request(&mut self, request: &ClientRequest) -> Result<Update, ClientError> {
+        // the greeting is older than the answer we are about to get
+        self.greeting = None;
+        self.send(request)?;
+        self.receive()
+    }
+
+    /// Returns the next ServerInfo, ignoring log lines and errors
+    pub fn next_info(&mut self) -> Result<ServerInfo, ClientError> {
+        match self.greeting.take() {
+            Some(info) => Ok(info),
+            None => Ok(self.request(&ClientRequest::Next)?.info),
+        }
+    }
+
+    /// Lists the servers a hub collects from, a server that is not a hub lists only itself
+    pub fn hosts(&mut self) -> Result<Vec<HostStatus>, ClientError> {
+        answer(self.request(&ClientRequest::ListHosts)?, |update| {
+            update.hosts.take()
+        })
+    }
+
+    /// Makes a hub send the infos of the host called name, returning its latest one
+    pub fn select_host(&mut self, name: &str) -> Result<ServerInfo, ClientError> {
+        let request = ClientRequest::SelectHost {
+            name: name.to_string(),
+        };
+        let mut update = self.request(&request)?;
+        update.refused()?;
+        Ok(update.info)
+    }
+
+    /// The infos the server kept of the selected host dated from..=to (unix seconds), oldest first
+    pub fn history(&mut self, from: i64, to: i64) -> Result<Vec<ServerInfo>, ClientError> {
+        answer(
+            self.request(&ClientRequest::History { from, to })?,
+            |update| update.history.take(),
+        )
+    }
+
+    /// The infos the server kept of the selected host dated from..=to (unix seconds) as csv or ndjson,
+    /// with only the named columns or all of them when empty
+    pub fn export(
+        &mut self,
+        from: i64,
+        to: i64,
+        format: ExportFormat,
+        columns: &[String],
+    ) -> Result<String, ClientError> {
+        let request = ClientRequest::Export {
+            from,
+            to,
+            format,
+            columns: columns.to_vec(),
+        };
+        answer(self.request(&request)?, |update| update.export.take())
+    }
+
+    /// The hardware of the server, hubs do not take inventories of their hosts
+    pub fn inventory(&mut self) -> Result<Inventory, ClientError> {
+        answer(self.request(&ClientRequest::Inventory)?, |update| {
+            update.inventory.take()
+        })
+    }
+
+    /// Turns the connection into an agent reporting the infos of the host called name to a hub,
+    /// token is the hubs agent token
+    pub fn register_agent(&mut self, name: &str, token: &str) -> Result<(), ClientError> {
+        self.greeting = None;
+        let request = ClientRequest::Agent {
+            name: name.to_string(),
+            token: token.to_string(),
+        };
+        self.send(&request)?;
+        self.wait_for_ack()
+    }
+
+    /// Sends infos to the hub the agent registered with, at most MAX_REPORT_LEN, returning once the hub stored them
+    pub fn report(&mut self, infos: Vec<ServerInfo>) -> Result<(), ClientError> {
+        self.send(&ClientRequest::Report(infos))?;
+        self.wait_for_ack()
+    }
+
+    /// The encoding the server agreed to
+    pub fn encoding(&self) -> Encoding {
+        self.codec.encoding
+    }
+
+    /// The compression the server agreed to
+    pub fn compression(&self) -> Compression {
+        self.codec.compression
+    }
+
+    /// Turns the client into an iterator over every section of the ServerInfos the server pushes every interval
+    pub fn subscribe(self, interval: Duration) -> Subscription {
+        Subscription {
+            client: self,
+            interval,
+            subscribed: false,
+            failed: false,
+        }
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.tcp.shutdown(Shutdown::Both);
+    }
+
+    fn read(&mut self) -> Result<ServerMessage, ClientError> {
+        Ok(self.reader.read(&mut self.stream, self.codec)?)
+    }
+
+    fn wait_for_ack(&mut self) -> Result<(), ClientError> {
+        loop {
+            if let Some(result) = ack_reply(self.read()?) {
+                return result;
+            }
+        }
+    }
+
+    fn take_greeting(&mut self) -> Option<Update> {
+        self.greeting.take().map(|info| Update {
+            info,
+            ..Default::default()
+        })
+    }
+
+    /// Adds a message to the update being read, returning it once complete
+    fn add(&mut self, message: ServerMessage) -> Result<Option<Update>, ClientError> {
+        match self.partial.add(message, &mut self.deltas)? {
+            Progress::Pending => Ok(None),
+            Progress::Complete => Ok(Some(mem::take(&mut self.partial))),
+            Progress::Gap => {
+                self.send(&ClientRequest::Resync)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Tells a pushing server the client is still there
+    fn heartbeat_if_due(&mut self) -> Result<(), ClientError> {
+        if self.push && self.last_sent.elapsed() >= HEARTBEAT_INTERVAL {
+            self.send(&ClientRequest::Heartbeat)?;
+        }
+        Ok(())
+    }
+}
+
+fn connect_tcp(address: &str, timeout: Duration) -> Result<TcpStream, ClientError> {
+    let mut last_error = ClientError::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{address} did not resolve."),
+    ));
+
+    for socket_address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e.into(),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Iterator over the ServerInfos of a server, ends after the first error.
+pub struct Subscription {
+    client: Client,
+    interval: Duration,
+    subscribed: bool,
+    failed: bool,
+}
+
+impl Iterator for Subscription {
+    type Item = Result<ServerInfo, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        if let Some(update) = self.client.take_greeting() {
+            return Some(Ok(update.info));
+        }
+
+        let info = self.subscribe().and_then(|_| self.client.receive());
+        self.failed = info.is_err();
+        Some(info.map(|update| update.info))
+    }
+}
+
+impl Subscription {
+    /// Asks the server to start pushing, once
+    fn subscribe(&mut self) -> Result<(), ClientError> {
+        if !self.subscribed {
+            self.subscribed = true;
+            self.client.send(&ClientRequest::Subscribe {
+                sections: Sections::default(),
+                interval_ms: self.interval.as_millis() as u64,
+                push: true,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use server_info_packets::delta::InfoChange;
+    use server_info_packets::protocol::{encode_frame, read_message};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn info(host_name: &str) -> ServerMessage {
+        ServerMessage::Info(Box::new(ServerInfo {
+            host_name: host_name.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    /// Serves a fake server on a random port, running script with the accepted stream
+    fn fake_server(script: impl FnOnce(TcpStream) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || script(listener.accept().unwrap().0));
+        address
+    }
+
+    #[test]
+    fn authenticates_and_collects_updates() {
+        let address = fake_server(|mut stream| {
+            write_message(&mut stream, Encoding::Json, &ServerMessage::AuthRequired).unwrap();
+            let request: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            assert_eq!(
+                request,
+                ClientRequest::Auth {
+                    token: "secret".to_string()
+                }
+            );
+            write_message(&mut stream, Encoding::Json, &info("first")).unwrap();
+
+            let _: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            let lines = ServerMessage::LogLines {
+                file: "app.log".to_string(),
+                lines: vec!["started".to_string()],
+            };
+            write_message(&mut stream, Encoding::Json, &lines).unwrap();
+            write_message(&mut stream, Encoding::Json, &info("second")).unwrap();
+        });
+
+        let options = ClientOptions {
+            auth_token: Some("secret".to_string()),
+            deltas: false,
+            ..Default::default()
+        };
+        let mut client = Client::connect(&address, &options).unwrap();
+        assert_eq!(client.receive().unwrap().info.host_name, "first");
+
+        let update = client.request(&ClientRequest::Next).unwrap();
+        assert_eq!(update.info.host_name, "second");
+        assert_eq!(
+            update.log_lines,
+            vec![("app.log".to_string(), vec!["started".to_string()])]
+        );
+    }
+
+    #[test]
+    fn resyncs_after_a_missing_delta() {
+        let address = fake_server(|mut stream| {
+            write_message(&mut stream, Encoding::Json, &info("first")).unwrap();
+            let request: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            assert!(matches!(request, ClientRequest::Hello { deltas: true, .. }));
+            write_message(
+                &mut stream,
+                Encoding::Json,
+                &ServerMessage::Hello {
+                    encoding: Encoding::Json,
+                    compression: Compression::None,
+                },
+            )
+            .unwrap();
+            let snapshot = ServerMessage::Snapshot {
+                seq: 1,
+                info: Box::new(ServerInfo {
+                    host_name: "second".to_string(),
+                    ..Default::default()
+                }),
+            };
+            write_message(&mut stream, Encoding::Json, &snapshot).unwrap();
+
+            let _: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            let delta = ServerMessage::Delta {
+                seq: 3,
+                changes: vec![InfoChange::TotalCpus(8)],
+            };
+            write_message(&mut stream, Encoding::Json, &delta).unwrap();
+            let request: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            assert_eq!(request, ClientRequest::Resync);
+            let snapshot = ServerMessage::Snapshot {
+                seq: 4,
+                info: Box::new(ServerInfo {
+                    host_name: "third".to_string(),
+                    total_cpus: 8,
+                    ..Default::default()
+                }),
+            };
+            write_message(&mut stream, Encoding::Json, &snapshot).unwrap();
+        });
+
+        let mut client = Client::connect(&address, &ClientOptions::default()).unwrap();
+        assert_eq!(client.next_info().unwrap().host_name, "second");
+        let info = client.next_info().unwrap();
+        assert_eq!(info.host_name, "third");
+        assert_eq!(info.total_cpus, 8);
+    }
+
+    #[test]
+    fn try_receive_picks_up_pushed_updates() {
+        let (pushed, push) = mpsc::channel::<()>();
+        let address = fake_server(move |mut stream| {
+            write_message(&mut stream, Encoding::Json, &info("first")).unwrap();
+            let request: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            assert!(matches!(
+                request,
+                ClientRequest::Subscribe { push: true, .. }
+            ));
+            // split over two writes, so the client sees half a frame first
+            let frame = encode_frame(Encoding::Json, &info("pushed")).unwrap();
+            stream.write_all(&frame[..10]).unwrap();
+            push.recv().unwrap();
+            stream.write_all(&frame[10..]).unwrap();
+            push.recv().unwrap();
+        });
+
+        let options = ClientOptions {
+            deltas: false,
+            ..Default::default()
+        };
+        let mut client = Client::connect(&address, &options).unwrap();
+        assert_eq!(
+            client.try_receive().unwrap().unwrap().info.host_name,
+            "first"
+        );
+        client
+            .send(&ClientRequest::Subscribe {
+                sections: Sections::default(),
+                interval_ms: 100,
+                push: true,
+            })
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(client.try_receive().unwrap().is_none());
+
+        pushed.send(()).unwrap();
+        let update = loop {
+            if let Some(update) = client.try_receive().unwrap() {
+                break update;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(update.info.host_name, "pushed");
+        pushed.send(()).unwrap();
+    }
+
+    #[test]
+    fn missing_token_is_an_auth_error() {
+        let address = fake_server(|mut stream| {
+            write_message(&mut stream, Encoding::Json, &ServerMessage::AuthRequired).unwrap();
+        });
+
+        let error = Client::connect(&address, &ClientOptions::default())
+            .err()
+            .unwrap();
+        assert!(matches!(error, ClientError::Auth(_)));
+    }
+
+    #[test]
+    fn subscription_ends_after_the_server_closes() {
+        let address = fake_server(|mut stream| {
+            write_message(&mut stream, Encoding::Json, &info("first")).unwrap();
+            let _: ClientRequest = read_message(&mut stream, Encoding::Json).unwrap();
+            write_message(&mut stream, Encoding::Json, &info("second")).unwrap();
+        });
+
+        let options = ClientOptions {
+            deltas: false,
+            ..Default::default()
+        };
+        let client = Client::connect(&address, &options).unwrap();
+        let results: Vec<_> = client.subscribe(Duration::ZERO).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].as_ref().unwrap().host_name, "second");
+        assert!(matches!(results[2], Err(ClientError::Closed)));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::ClientError;
+use server_info_packets::discovery::{Announcement, DiscoveryMessage};
+use std::io;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// A server that answered a discovery probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    /// what Client::connect takes, the address the answer came from with the announced port
+    pub address: String,
+    pub announcement: Announcement,
+}
+
+/// Broadcasts a probe to the udp port on the local network and to localhost, and collects the answers arriving within timeout.
+///
+/// Servers are usually found on DISCOVERY_PORT, one answering both probes is listed once.
+pub fn discover(port: u16, timeout: Duration) -> Result<Vec<Discovered>, ClientError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    let probe = DiscoveryMessage::probe();
+    // broadcasting fails without a network to broadcast on, a server on this machine is still found through localhost
+    let _ = socket.send_to(&probe, (Ipv4Addr::BROADCAST, port));
+    socket.send_to(&probe, (Ipv4Addr::LOCALHOST, port))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found: Vec<Discovered> = vec![];
+    let mut datagram = [0; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (length, sender) = match socket.recv_from(&mut datagram) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(DiscoveryMessage::Announcement(announcement)) =
+            DiscoveryMessage::decode(&datagram[..length])
+        else {
+            continue;
+        };
+        let known = found.iter().any(|discovered| {
+            discovered.announcement.host_name == announcement.host_name
+                && discovered.announcement.port == announcement.port
+        });
+        if !known {
+            found.push(Discovered {
+                address: format!("{}:{}", sender.ip(), announcement.port),
+                announcement,
+            });
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn finds_servers_answering_probes() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let announcement = Announcement {
+            host_name: "box".to_string(),
+            port: 9000,
+            ..Default::default()
+        };
+        {
+            let answer = DiscoveryMessage::Announcement(announcement.clone()).encode();
+            thread::spawn(move || {
+                let mut datagram = [0; 2048];
+                loop {
+                    let (length, sender) = server.recv_from(&mut datagram).unwrap();
+                    if DiscoveryMessage::decode(&datagram[..length])
+                        == Some(DiscoveryMessage::Probe)
+                    {
+                        // answered twice, like a server receiving both probes
+                        server.send_to(&answer, sender).unwrap();
+                        server.send_to(&answer, sender).unwrap();
+                    }
+                }
+            });
+        }
+
+        let found = discover(port, Duration::from_millis(500)).unwrap();
+        assert_eq!(
+            found,
+            vec![Discovered {
+                address: "127.0.0.1:9000".to_string(),
+                announcement,
+            }]
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// Everything that can go wrong talking to a server.
+#[derive(Debug)]
+pub enum ClientError {
+    /// resolving, connecting, reading or writing failed
+    Io(io::Error),
+    /// the server did not answer within ClientOptions::timeout
+    Timeout,
+    /// the server closed the connection
+    Closed,
+    /// the server said it is shutting down before closing the connection
+    ServerStopped,
+    /// tls is misconfigured or the handshake failed
+    Tls(String),
+    /// the server wants a token and none or a wrong one was given
+    Auth(String),
+    /// the server sent something that does not fit the protocol
+    Protocol(String),
+    /// the server answered a request with an error, e.g. selecting a host a hub does not know
+    Refused(String),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "{e}"),
+            ClientError::Timeout => write!(f, "The server did not answer in time."),
+            ClientError::Closed => write!(f, "The server closed the connection."),
+            ClientError::ServerStopped => write!(f, "The server stopped."),
+            ClientError::Tls(e) => write!(f, "TLS error: {e}"),
+            ClientError::Auth(e) => write!(f, "Authentication failed: {e}"),
+            ClientError::Protocol(e) => write!(f, "Protocol error: {e}"),
+            ClientError::Refused(e) => write!(f, "The server refused: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ClientError::Timeout,
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => ClientError::Closed,
+            io::ErrorKind::InvalidData => ClientError::Protocol(e.to_string()),
+            _ => ClientError::Io(e),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Client side of the server_info protocol, used by the gui, cli and tui clients.
+//!
+//! ```no_run
+//! use server_info_client::{Client, ClientOptions};
+//!
+//! let client = Client::connect("localhost:8111", &ClientOptions::default()).unwrap();
+//! for info in client.subscribe(std::time::Duration::from_secs(1)) {
+//!     println!("{}", info.unwrap().avg_cpu_usage);
+//! }
+//! ```
+
+pub use crate::client::{Client, Subscription};
+pub use crate::discovery::{discover, Discovered};
+pub use crate::error::ClientError;
+pub use crate::options::{ClientOptions, TlsOptions};
+pub use crate::update::Update;
+pub use server_info_packets::codec::Encoding;
+pub use server_info_packets::compression::Compression;
+pub use server_info_packets::export::ExportFormat;
+
+#[cfg(feature = "async")]
+pub use crate::async_client::AsyncClient;
+
+#[cfg(feature = "async")]
+mod async_client;
+mod client;
+mod discovery;
+mod error;
+mod options;
+#[cfg(feature = "tls")]
+mod tls;
+mod update;
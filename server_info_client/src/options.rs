@@ -0,0 +1,42 @@
+use server_info_packets::codec::Encoding;
+use server_info_packets::compression::Compression;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How to connect to a server.
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// limit for connecting and for every read and write after that
+    pub timeout: Duration,
+    /// sent when the server asks for a token
+    pub auth_token: Option<String>,
+    /// connect with tls, e.g. to a tls terminating proxy in front of the server
+    pub tls: Option<TlsOptions>,
+    /// encodings to ask the server for, most preferred first, the server falls back to json if it supports none of them
+    pub encodings: Vec<Encoding>,
+    /// compressions to ask the server for, most preferred first, frames stay uncompressed when empty
+    pub compressions: Vec<Compression>,
+    /// ask the server to send only the fields that changed instead of the whole info every time
+    pub deltas: bool,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            auth_token: None,
+            tls: None,
+            encodings: vec![Encoding::Json],
+            compressions: vec![],
+            deltas: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// name the servers certificate is checked against, defaults to the host part of the address
+    pub server_name: Option<String>,
+    /// pem file with the certificates to trust instead of the usual web roots, e.g. a self signed one
+    pub ca_file: Option<PathBuf>,
+}
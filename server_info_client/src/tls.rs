@@ -0,0 +1,92 @@
+use crate::error::ClientError;
+use crate::options::TlsOptions;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+pub(crate) fn client_config(options: &TlsOptions) -> Result<Arc<ClientConfig>, ClientError> {
+    let mut roots = RootCertStore::empty();
+    match &options.ca_file {
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        Some(ca_file) => {
+            let certificates = CertificateDer::pem_file_iter(ca_file)
+                .map_err(|e| ClientError::Tls(format!("{}: {e}", ca_file.display())))?;
+            for certificate in certificates {
+                let certificate = certificate
+                    .map_err(|e| ClientError::Tls(format!("{}: {e}", ca_file.display())))?;
+                roots
+                    .add(certificate)
+                    .map_err(|e| ClientError::Tls(e.to_string()))?;
+            }
+        }
+    }
+
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| ClientError::Tls(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// The name from the options, or the host part of a "host:port" address
+pub(crate) fn server_name(
+    options: &TlsOptions,
+    address: &str,
+) -> Result<ServerName<'static>, ClientError> {
+    let name = match &options.server_name {
+        Some(name) => name.as_str(),
+        None => {
+            let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+            host.trim_start_matches('[').trim_end_matches(']')
+        }
+    };
+    ServerName::try_from(name.to_string()).map_err(|e| ClientError::Tls(format!("{name}: {e}")))
+}
+
+pub(crate) fn wrap(
+    mut stream: TcpStream,
+    address: &str,
+    options: &TlsOptions,
+) -> Result<StreamOwned<ClientConnection, TcpStream>, ClientError> {
+    let mut connection =
+        ClientConnection::new(client_config(options)?, server_name(options, address)?)
+            .map_err(|e| ClientError::Tls(e.to_string()))?;
+    // handshake now, so a plain text server shows up as a tls error instead of a broken message later
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut stream)
+            .map_err(|e| ClientError::Tls(e.to_string()))?;
+    }
+    Ok(StreamOwned::new(connection, stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_name_defaults_to_host() {
+        let options = TlsOptions::default();
+        assert_eq!(
+            server_name(&options, "example.com:8111").unwrap(),
+            ServerName::try_from("example.com").unwrap()
+        );
+        assert_eq!(
+            server_name(&options, "[::1]:8111").unwrap(),
+            ServerName::try_from("::1").unwrap()
+        );
+
+        let options = TlsOptions {
+            server_name: Some("monitor.example.com".to_string()),
+            ca_file: None,
+        };
+        assert_eq!(
+            server_name(&options, "10.0.0.2:8111").unwrap(),
+            ServerName::try_from("monitor.example.com").unwrap()
+        );
+    }
+}
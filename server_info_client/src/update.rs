@@ -0,0 +1,215 @@
+use crate::error::ClientError;
+use crate::options::ClientOptions;
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::delta::DeltaDecoder;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::inventory::Inventory;
+use server_info_packets::protocol::{ClientRequest, ServerMessage, HEARTBEAT_INTERVAL};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::time::Duration;
+
+/// Everything the server sent up to and including its next ServerInfo.
+#[derive(Debug, Clone, Default)]
+pub struct Update {
+    pub info: ServerInfo,
+    /// new lines of the tailed log file, as (file, lines)
+    pub log_lines: Vec<(String, Vec<String>)>,
+    /// errors for earlier requests, e.g. tailing a file the server does not allow
+    pub errors: Vec<String>,
+    /// the answer to ClientRequest::ListHosts
+    pub hosts: Option<Vec<HostStatus>>,
+    /// the answer to ClientRequest::History
+    pub history: Option<Vec<ServerInfo>>,
+    /// the answer to ClientRequest::Export
+    pub export: Option<String>,
+    /// the answer to ClientRequest::Inventory
+    pub inventory: Option<Inventory>,
+}
+
+/// Where an update is at after adding a message to it
+pub(crate) enum Progress {
+    Pending,
+    /// the ServerInfo that completes it arrived
+    Complete,
+    /// a Delta arrived out of sequence, the client has to send ClientRequest::Resync and keep reading
+    Gap,
+}
+
+impl Update {
+    /// Adds a message to the update, applying Snapshots and Deltas to deltas
+    pub(crate) fn add(
+        &mut self,
+        message: ServerMessage,
+        deltas: &mut DeltaDecoder,
+    ) -> Result<Progress, ClientError> {
+        match message {
+            ServerMessage::Info(info) => {
+                self.info = *info;
+                return Ok(Progress::Complete);
+            }
+            ServerMessage::Snapshot { seq, info } => {
+                self.info = deltas.snapshot(seq, *info);
+                return Ok(Progress::Complete);
+            }
+            ServerMessage::Delta { seq, changes } => {
+                return match deltas.delta(seq, changes) {
+                    Some(info) => {
+                        self.info = info;
+                        Ok(Progress::Complete)
+                    }
+                    None => Ok(Progress::Gap),
+                };
+            }
+            ServerMessage::LogLines { file, lines } => self.log_lines.push((file, lines)),
+            ServerMessage::Heartbeat => {}
+            ServerMessage::Hosts(hosts) => self.hosts = Some(hosts),
+            ServerMessage::History(infos) => self.history = Some(infos),
+            ServerMessage::Export(text) => self.export = Some(text),
+            ServerMessage::Inventory(inventory) => self.inventory = Some(*inventory),
+            ServerMessage::Ack => {
+                return Err(ClientError::Protocol(
+                    "Server acknowledged a report that was not sent.".to_string(),
+                ))
+            }
+            ServerMessage::Error(e) => self.errors.push(e),
+            ServerMessage::AuthRequired => {
+                return Err(ClientError::Protocol(
+                    "Server asked for a token after the handshake.".to_string(),
+                ))
+            }
+            ServerMessage::ShuttingDown => return Err(ClientError::ServerStopped),
+            ServerMessage::Hello { .. } => {
+                return Err(ClientError::Protocol(
+                    "Server switched encoding without being asked.".to_string(),
+                ))
+            }
+        }
+        Ok(Progress::Pending)
+    }
+
+    /// Fails with the last error the server sent, for requests whose only answer is an error or nothing
+    pub(crate) fn refused(&mut self) -> Result<(), ClientError> {
+        match self.errors.pop() {
+            Some(e) => Err(ClientError::Refused(e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Takes the answer to a request out of an update, or the error the server sent instead
+pub(crate) fn answer<T>(
+    mut update: Update,
+    take: fn(&mut Update) -> Option<T>,
+) -> Result<T, ClientError> {
+    update.refused()?;
+    take(&mut update)
+        .ok_or_else(|| ClientError::Protocol("The server did not answer the request.".to_string()))
+}
+
+/// What the first message of the server means for the handshake
+pub(crate) enum Greeting {
+    Info(Box<ServerInfo>),
+    /// the token to answer with
+    AuthRequired(String),
+}
+
+impl Greeting {
+    pub(crate) fn from_message(
+        message: ServerMessage,
+        auth_token: Option<&str>,
+    ) -> Result<Self, ClientError> {
+        match message {
+            ServerMessage::Info(info) => Ok(Greeting::Info(info)),
+            ServerMessage::AuthRequired => match auth_token {
+                Some(token) => Ok(Greeting::AuthRequired(token.to_string())),
+                None => Err(ClientError::Auth(
+                    "The server requires a token.".to_string(),
+                )),
+            },
+            ServerMessage::Error(e) => Err(ClientError::Protocol(e)),
+            ServerMessage::ShuttingDown => Err(ClientError::ServerStopped),
+            ServerMessage::LogLines { .. }
+            | ServerMessage::Hello { .. }
+            | ServerMessage::Snapshot { .. }
+            | ServerMessage::Delta { .. }
+            | ServerMessage::Heartbeat
+            | ServerMessage::Hosts(_)
+            | ServerMessage::History(_)
+            | ServerMessage::Export(_)
+            | ServerMessage::Inventory(_)
+            | ServerMessage::Ack => Err(ClientError::Protocol(
+                "Server sent something else before its first info.".to_string(),
+            )),
+        }
+    }
+}
+
+/// The answer to ClientRequest::Hello, the codec for every frame after it
+pub(crate) fn hello_reply(message: ServerMessage) -> Result<Codec, ClientError> {
+    match message {
+        ServerMessage::Hello {
+            encoding,
+            compression,
+        } => Ok(Codec {
+            encoding,
+            compression,
+            threshold: Codec::DEFAULT_THRESHOLD,
+        }),
+        ServerMessage::ShuttingDown => Err(ClientError::ServerStopped),
+        _ => Err(ClientError::Protocol(
+            "Unexpected answer to the encoding request.".to_string(),
+        )),
+    }
+}
+
+/// What a message means while an agent waits for ServerMessage::Ack, none to keep waiting
+pub(crate) fn ack_reply(message: ServerMessage) -> Option<Result<(), ClientError>> {
+    match message {
+        ServerMessage::Ack => Some(Ok(())),
+        ServerMessage::Heartbeat => None,
+        ServerMessage::Error(e) => Some(Err(ClientError::Refused(e))),
+        ServerMessage::ShuttingDown => Some(Err(ClientError::ServerStopped)),
+        _ => Some(Err(ClientError::Protocol(
+            "Unexpected answer to the report.".to_string(),
+        ))),
+    }
+}
+
+/// Whether the client has to send ClientRequest::Hello, to ask for another encoding, compression or for deltas
+pub(crate) fn wants_hello(options: &ClientOptions) -> bool {
+    options.deltas
+        || !options.compressions.is_empty()
+        || options
+            .encodings
+            .first()
+            .is_some_and(|first| *first != Encoding::Json)
+}
+
+/// The answer to ClientRequest::Auth, either the first info or why the token was rejected
+pub(crate) fn auth_reply(message: ServerMessage) -> Result<ServerInfo, ClientError> {
+    match message {
+        ServerMessage::Info(info) => Ok(*info),
+        ServerMessage::Error(e) => Err(ClientError::Auth(e)),
+        ServerMessage::ShuttingDown => Err(ClientError::ServerStopped),
+        _ => Err(ClientError::Protocol(
+            "Unexpected answer to the token.".to_string(),
+        )),
+    }
+}
+
+/// The read timeout to use after sending request, the server holds answers back for up to a subscriptions interval,
+/// and sends something at least every HEARTBEAT_INTERVAL when pushing
+pub(crate) fn read_timeout_after(request: &ClientRequest, timeout: Duration) -> Option<Duration> {
+    match request {
+        ClientRequest::Subscribe {
+            interval_ms, push, ..
+        } => {
+            let interval = Duration::from_millis(*interval_ms);
+            match push {
+                true => Some(timeout + interval.min(HEARTBEAT_INTERVAL)),
+                false => Some(timeout + interval),
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,155 @@
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+use server_info_packets::protocol::{ClientRequest, LogFilter};
+use std::collections::VecDeque;
+
+/// Lines kept in the viewer, older lines are dropped
+const MAX_LINES: usize = 1000;
+
+/// Panel showing the lines of a log file tailed from the server.
+#[derive(Default)]
+pub struct LogViewer {
+    file: String,
+    filter: String,
+    ignore_case: bool,
+    invert: bool,
+    /// highlights lines locally, unlike filter which is applied on the server
+    search: String,
+    paused: bool,
+    tailing: bool,
+    lines: VecDeque<String>,
+    /// lines received while paused, shown once unpaused, kept to MAX_LINES like lines
+    held_lines: VecDeque<String>,
+    pub error: Option<String>,
+}
+
+impl LogViewer {
+    pub fn push_lines(&mut self, file: &str, lines: Vec<String>) {
+        if file != self.file {
+            return;
+        }
+        let kept = match self.paused {
+            true => &mut self.held_lines,
+            false => &mut self.lines,
+        };
+        kept.extend(lines);
+        while kept.len() > MAX_LINES {
+            kept.pop_front();
+        }
+    }
+
+    /// The server forgets the tail when the connection drops
+    pub fn reset(&mut self) {
+        self.tailing = false;
+        self.error = None;
+    }
+
+    /// Draws the panel, returns a request for the server when the user starts or stops a tail
+    pub fn ui(&mut self, ui: &mut egui::Ui, log_files: &[String]) -> Option<ClientRequest> {
+        let mut request = None;
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Log file")
+                .selected_text(&self.file)
+                .show_ui(ui, |ui| {
+                    for file in log_files {
+                        ui.selectable_value(&mut self.file, file.to_string(), file);
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Filter: ");
+            ui.text_edit_singleline(&mut self.filter)
+                .on_hover_text("Regular expression applied on the server, like grep.");
+            ui.checkbox(&mut self.ignore_case, "Ignore case");
+            ui.checkbox(&mut self.invert, "Invert");
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Tail").clicked() && !self.file.is_empty() {
+                self.lines.clear();
+                self.held_lines.clear();
+                self.error = None;
+                self.tailing = true;
+                request = Some(ClientRequest::TailLogs {
+                    file: self.file.to_string(),
+                    filter: Some(LogFilter {
+                        pattern: self.filter.to_string(),
+                        ignore_case: self.ignore_case,
+                        invert: self.invert,
+                    }),
+                });
+            }
+
+            if ui.button("Stop").clicked() && self.tailing {
+                self.tailing = false;
+                request = Some(ClientRequest::StopLogs);
+            }
+
+            if ui.checkbox(&mut self.paused, "Pause").changed() && !self.paused {
+                self.lines.extend(self.held_lines.drain(..));
+                while self.lines.len() > MAX_LINES {
+                    self.lines.pop_front();
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Search: ");
+            ui.text_edit_singleline(&mut self.search);
+            if !self.search.is_empty() {
+                let search = self.search.to_lowercase();
+                let matches = self
+                    .lines
+                    .iter()
+                    .filter(|line| line.to_lowercase().contains(&search))
+                    .count();
+                ui.label(format!("{matches} matching lines"));
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::from_rgb(255, 50, 50), error);
+        }
+
+        if self.paused && !self.held_lines.is_empty() {
+            ui.label(format!("{} new lines while paused", self.held_lines.len()));
+        }
+
+        let search = self.search.to_lowercase();
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &self.lines {
+                    let mut text = RichText::new(line).monospace();
+                    if !search.is_empty() && line.to_lowercase().contains(&search) {
+                        text = text.background_color(Color32::from_rgb(90, 90, 20));
+                    }
+                    ui.label(text);
+                }
+            });
+
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_at_most_max_lines_while_paused() {
+        let mut viewer = LogViewer {
+            file: "syslog".to_string(),
+            paused: true,
+            ..Default::default()
+        };
+        let lines: Vec<String> = (0..MAX_LINES + 10).map(|i| i.to_string()).collect();
+        viewer.push_lines("syslog", lines);
+        assert_eq!(viewer.held_lines.len(), MAX_LINES);
+        assert_eq!(viewer.held_lines.front(), Some(&"10".to_string()));
+        assert!(viewer.lines.is_empty());
+    }
+}
@@ -3,18 +3,34 @@
 extern crate core;
 
 use crate::egui::{Color32, Vec2};
-use crate::last_session::LastSession;
+use crate::log_viewer::LogViewer;
+use crate::profiles::{Profile, Profiles, Visible, MAX_UPDATE_RATE, MIN_UPDATE_RATE};
+use crate::recording::{Recorder, Replay};
+use crate::sensors::SensorPanel;
 use eframe::egui;
 use eframe::egui::{Pos2, Rounding};
 use eframe::epaint::Rect;
+use server_info_client::{
+    discover, Client, ClientError, ClientOptions, Discovered, ExportFormat, TlsOptions,
+};
+use server_info_packets::discovery::DISCOVERY_PORT;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::inventory::Inventory;
+use server_info_packets::network_info::{InterfaceKind, NetworkInterfaceInfo};
+use server_info_packets::protocol::{ClientRequest, Sections};
 use server_info_packets::server_info_packet::ServerInfo;
-use std::borrow::Cow;
-use std::io::{Read, Write};
-use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
-mod last_session;
+mod log_viewer;
+mod profiles;
+mod recording;
+mod sensors;
 
-static LAST_SESSION_FILE_NAME: &str = "server_info_last_session.sav";
+/// How long the discover button waits for servers to answer
+const DISCOVER_TIMEOUT: Duration = Duration::from_secs(1);
 
 fn main() {
     let native_options = eframe::NativeOptions {
@@ -31,14 +47,66 @@ fn main() {
 
 #[derive(Default)]
 struct MyEguiApp {
-    stream: Option<TcpStream>,
-    buf_vec: Vec<u8>,
+    client: Option<Client>,
+    /// sent instead of ClientRequest::Next on the next update
+    pending_request: Option<ClientRequest>,
+    /// the last ClientRequest::Subscribe sent, a new one is sent when the toggles or update rate change
+    subscribed: Option<ClientRequest>,
+    profiles: Profiles,
+    /// what the current settings are saved as
+    profile_name: String,
+    /// file profiles are imported from and exported to
+    profile_file: String,
+    /// the profiles file could not be read, it is not written until the user chooses to overwrite it
+    profiles_unreadable: bool,
+    /// how the last profile action went
+    profiles_status: Option<String>,
     address: String,
+    /// sent when the server asks for a token, empty for none
+    auth_token: String,
+    /// environment variable the token is read from when none is typed in, saved in the profile instead of the token
+    token_env: String,
+    use_tls: bool,
+    /// certificates to trust instead of the web roots, empty for none
+    tls_ca: String,
+    /// the server said it was shutting down, rather than the connection just breaking
+    server_stopped: bool,
     server_info: ServerInfo,
-    frames: i32,
+    /// the hosts of a hub, a server that is not a hub lists only itself
+    hosts: Vec<HostStatus>,
+    selected_host: String,
+    /// the answers of a discovery running on its own thread
+    discovering: Option<mpsc::Receiver<Result<Vec<Discovered>, String>>>,
+    discovered: Vec<Discovered>,
+    /// file sessions are recorded to and replayed from
+    recording_file: String,
+    recorder: Option<Recorder>,
+    /// displayed instead of the infos of a server while set
+    replay: Option<Replay>,
+    export_format: ExportFormat,
+    /// comma separated, every column when empty
+    export_columns: String,
+    /// unix time in seconds, empty for no limit
+    export_from: String,
+    export_to: String,
+    /// waiting for the server to answer a ClientRequest::Export
+    exporting: bool,
+    /// how the last export went
+    export_status: Option<String>,
+    /// the hardware of the server, shown in a window until closed
+    inventory: Option<Inventory>,
     displaying_disks: bool,
     displaying_interfaces: bool,
     displaying_cpus: bool,
+    displaying_containers: bool,
+    displaying_services: bool,
+    displaying_custom: bool,
+    displaying_logs: bool,
+    displaying_sensors: bool,
+    hiding_loopback: bool,
+    hiding_virtual: bool,
+    log_viewer: LogViewer,
+    sensor_panel: SensorPanel,
     update_rate: f32,
     first_run: bool,
 }
@@ -46,20 +114,146 @@ struct MyEguiApp {
 impl MyEguiApp {
     fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         Self {
-            stream: None,
-            buf_vec: vec![],
+            client: None,
+            pending_request: None,
+            subscribed: None,
+            profiles: Profiles::default(),
+            profile_name: String::new(),
+            profile_file: String::new(),
+            profiles_unreadable: false,
+            profiles_status: None,
             address: "localhost:8111".to_string(),
+            auth_token: String::new(),
+            token_env: String::new(),
+            use_tls: false,
+            tls_ca: String::new(),
+            server_stopped: false,
             server_info: ServerInfo::default(),
-            frames: 0,
+            hosts: vec![],
+            selected_host: String::new(),
+            discovering: None,
+            discovered: vec![],
+            recording_file: "server_info_recording.bin".to_string(),
+            recorder: None,
+            replay: None,
+            export_format: ExportFormat::Csv,
+            export_columns: String::new(),
+            export_from: String::new(),
+            export_to: String::new(),
+            exporting: false,
+            export_status: None,
+            inventory: None,
             displaying_disks: false,
             displaying_interfaces: false,
             displaying_cpus: false,
+            displaying_containers: false,
+            displaying_services: false,
+            displaying_custom: false,
+            displaying_logs: false,
+            displaying_sensors: false,
+            hiding_loopback: false,
+            hiding_virtual: false,
+            log_viewer: LogViewer::default(),
+            sensor_panel: SensorPanel::default(),
             update_rate: 0.5,
             first_run: true,
         }
     }
 }
 
+/// A unix time in seconds typed in, default when left empty
+fn parse_date(text: &str, default: i64) -> Result<i64, String> {
+    match text.trim() {
+        "" => Ok(default),
+        text => text
+            .parse()
+            .map_err(|_| format!("{text} is not a unix time in seconds.")),
+    }
+}
+
+/// Writes an export to path, returning what to tell the user
+fn write_export(path: &Path, text: &str) -> String {
+    match std::fs::write(path, text) {
+        Ok(()) => format!(
+            "Exported {} lines to {}.",
+            text.lines().count(),
+            path.display()
+        ),
+        Err(e) => format!("Failed to export to {}: {e}", path.display()),
+    }
+}
+
+/// Cores shown per row of the cpu grid
+const CORES_PER_ROW: usize = 8;
+
+/// Steal above this percentage is pointed out, the vm is waiting for its hypervisor rather than busy
+const STEAL_WARNING: f32 = 5.0;
+
+/// Green when idle through yellow to red when busy
+fn usage_color(usage: f32) -> Color32 {
+    let busy = (usage / 100.0).clamp(0.0, 1.0);
+    let red = (510.0 * busy).min(255.0) as u8;
+    let green = (510.0 * (1.0 - busy)).min(255.0) as u8;
+    Color32::from_rgb(red, green, 50)
+}
+
+/// A cell per core filled up to and coloured by its usage, with its clock and times on hover
+fn cores_ui(ui: &mut egui::Ui, info: &ServerInfo) {
+    if let Some(times) = &info.cpu_times {
+        ui.horizontal(|ui| {
+            ui.label("CPU Times: ");
+            ui.label(times.to_string());
+        });
+        if times.steal >= STEAL_WARNING {
+            ui.colored_label(
+                Color32::from_rgb(255, 200, 50),
+                format!(
+                    "{:.1} % of the time was stolen by the hypervisor",
+                    times.steal
+                ),
+            );
+        }
+    }
+
+    egui::Grid::new("cores").show(ui, |ui| {
+        for (index, core) in info.cores.iter().enumerate() {
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(80.0, 36.0), egui::Sense::hover());
+            let painter = ui.painter();
+            painter.rect_filled(rect, Rounding::same(3.0), Color32::from_gray(40));
+            let used_height = rect.height() * (core.usage / 100.0).clamp(0.0, 1.0);
+            let used = Rect::from_min_max(
+                Pos2::new(rect.left(), rect.bottom() - used_height),
+                rect.max,
+            );
+            painter.rect_filled(used, Rounding::same(3.0), usage_color(core.usage));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("{:.0} %\n{} MHz", core.usage, core.frequency),
+                egui::FontId::monospace(11.0),
+                Color32::WHITE,
+            );
+            response.on_hover_text(core.to_string());
+
+            if (index + 1) % CORES_PER_ROW == 0 {
+                ui.end_row();
+            }
+        }
+    });
+}
+
+/// Red while down, yellow once it had errors, white otherwise, drops are common enough to not count
+fn interface_color(interface: &NetworkInterfaceInfo) -> Color32 {
+    if interface.up == Some(false) {
+        Color32::from_rgb(255, 50, 50)
+    } else if interface.rx_errors + interface.tx_errors > 0 {
+        Color32::from_rgb(255, 200, 50)
+    } else {
+        Color32::from_rgb(255, 255, 255)
+    }
+}
+
 /// thank you online example <3
 fn toggle_ui_compact(ui: &mut egui::Ui, on: &mut bool) -> egui::Response {
     let desired_size = ui.spacing().interact_size.y * egui::vec2(2.0, 1.0);
@@ -85,18 +279,396 @@ fn toggle_ui_compact(ui: &mut egui::Ui, on: &mut bool) -> egui::Response {
     response
 }
 
-/// Deserializes a given string into a ServerInfo struct, returns none if data is invalid
-fn deserialize_server_info(data: &str) -> Option<ServerInfo> {
-    let result = serde_json::from_str(data);
-    // if result.is_ok() {
-    //     Some(result.unwrap())
-    // } else {
-    //     None
-    // }
-    if let Ok(res) = result {
-        return Some(res);
+impl MyEguiApp {
+    /// The current settings as a profile called profile_name
+    fn profile(&self) -> Profile {
+        Profile {
+            name: self.profile_name.to_string(),
+            address: self.address.to_string(),
+            token_env: Some(self.token_env.to_string()).filter(|t| !t.is_empty()),
+            tls: self.use_tls,
+            tls_ca: Some(PathBuf::from(&self.tls_ca)).filter(|_| !self.tls_ca.is_empty()),
+            update_rate: self.update_rate,
+            visible: Visible {
+                cpus: self.displaying_cpus,
+                interfaces: self.displaying_interfaces,
+                disks: self.displaying_disks,
+                containers: self.displaying_containers,
+                services: self.displaying_services,
+                custom: self.displaying_custom,
+                logs: self.displaying_logs,
+                sensors: self.displaying_sensors,
+                hide_loopback: self.hiding_loopback,
+                hide_virtual: self.hiding_virtual,
+            },
+        }
+    }
+
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.profile_name = profile.name.to_string();
+        self.address = profile.address.to_string();
+        self.auth_token.clear();
+        self.token_env = profile.token_env.clone().unwrap_or_default();
+        self.use_tls = profile.tls;
+        self.tls_ca = profile
+            .tls_ca
+            .as_ref()
+            .map(|ca| ca.display().to_string())
+            .unwrap_or_default();
+        self.update_rate = profile.update_rate;
+        self.displaying_cpus = profile.visible.cpus;
+        self.displaying_interfaces = profile.visible.interfaces;
+        self.displaying_disks = profile.visible.disks;
+        self.displaying_containers = profile.visible.containers;
+        self.displaying_services = profile.visible.services;
+        self.displaying_custom = profile.visible.custom;
+        self.displaying_logs = profile.visible.logs;
+        self.displaying_sensors = profile.visible.sensors;
+        self.hiding_loopback = profile.visible.hide_loopback;
+        self.hiding_virtual = profile.visible.hide_virtual;
+    }
+
+    /// Saves the profiles, unless that would replace a file that could not be read
+    fn write_profiles(&mut self) {
+        if self.profiles_unreadable {
+            self.profiles_status = Some(
+                "Not saved, the profiles file could not be read. Fix it and restart, or overwrite it."
+                    .to_string(),
+            );
+            return;
+        }
+        if let Err(e) = profiles::write_to_file(&self.profiles) {
+            self.profiles_status = Some(format!("Failed to save profiles: {e}"));
+        }
+    }
+
+    /// Profile picker, saving the current settings and sharing profiles through a file
+    fn profiles_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut selected = None;
+            egui::ComboBox::from_label("Profile")
+                .selected_text(self.profile_name.as_str())
+                .show_ui(ui, |ui| {
+                    for profile in &self.profiles.profiles {
+                        if ui
+                            .selectable_label(profile.name == self.profile_name, &profile.name)
+                            .clicked()
+                        {
+                            selected = Some(profile.clone());
+                        }
+                    }
+                });
+            if let Some(profile) = selected {
+                self.apply_profile(&profile);
+            }
+
+            ui.text_edit_singleline(&mut self.profile_name)
+                .on_hover_text("Name the current settings are saved as.");
+            if ui.button("Save").clicked() && !self.profile_name.is_empty() {
+                self.profiles.save(self.profile());
+                self.write_profiles();
+            }
+            if ui.button("Delete").clicked() {
+                self.profiles.remove(&self.profile_name);
+                self.write_profiles();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Profiles file: ");
+            ui.text_edit_singleline(&mut self.profile_file)
+                .on_hover_text("A .toml or .json file to share profiles through.");
+            let path = PathBuf::from(&self.profile_file);
+            if ui.button("Import").clicked() {
+                match self.profiles.import(&path) {
+                    Ok(count) => {
+                        self.profiles_status = Some(format!("Imported {count} profiles."));
+                        self.write_profiles();
+                    }
+                    Err(e) => {
+                        self.profiles_status = Some(format!("Failed to import profiles: {e}"))
+                    }
+                }
+            }
+            if ui.button("Export").clicked() {
+                self.profiles_status = Some(match self.profiles.export(&path) {
+                    Ok(()) => format!("Exported the profiles to {}.", path.display()),
+                    Err(e) => format!("Failed to export profiles: {e}"),
+                });
+            }
+        });
+
+        if let Some(status) = self.profiles_status.clone() {
+            ui.horizontal(|ui| {
+                ui.label(status);
+                if self.profiles_unreadable
+                    && ui
+                        .button("Overwrite")
+                        .on_hover_text("Replace the profiles file that could not be read with the profiles shown.")
+                        .clicked()
+                {
+                    self.profiles_unreadable = false;
+                    self.profiles_status = None;
+                    self.write_profiles();
+                }
+            });
+        }
+    }
+
+    /// Recording the infos received to a file, and replaying them with play, pause, speed and position controls
+    fn recording_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Recording file: ");
+            ui.text_edit_singleline(&mut self.recording_file);
+            let path = PathBuf::from(&self.recording_file);
+
+            if self.recorder.is_some() {
+                if ui.button("Stop recording").clicked() {
+                    self.recorder = None;
+                }
+            } else if ui
+                .button("Record")
+                .on_hover_text("Record the infos received to the file.")
+                .clicked()
+            {
+                match Recorder::create(&path) {
+                    Ok(recorder) => self.recorder = Some(recorder),
+                    Err(e) => println!("failed to record: {e}"),
+                }
+            }
+
+            if self.replay.is_some() {
+                if ui.button("Stop replay").clicked() {
+                    self.replay = None;
+                }
+            } else if ui
+                .button("Replay")
+                .on_hover_text("Disconnect and display the infos recorded in the file.")
+                .clicked()
+            {
+                match Replay::open(&path) {
+                    Ok(replay) => {
+                        if let Some(client) = self.client.take() {
+                            client.shutdown();
+                        }
+                        self.recorder = None;
+                        self.sensor_panel.reset();
+                        self.replay = Some(replay);
+                    }
+                    Err(e) => println!("failed to replay: {e}"),
+                }
+            }
+        });
+
+        if let Some(replay) = &mut self.replay {
+            ui.horizontal(|ui| {
+                let label = match replay.playing {
+                    true => "Pause",
+                    false => "Play",
+                };
+                if ui.button(label).clicked() {
+                    // playing from the end starts over
+                    if !replay.playing && replay.position == replay.duration() {
+                        replay.seek(0);
+                    }
+                    replay.playing = !replay.playing;
+                }
+                ui.label("Speed: ");
+                ui.add(
+                    egui::Slider::new(&mut replay.speed, 0.25..=16.0)
+                        .logarithmic(true)
+                        .suffix("x"),
+                );
+            });
+
+            let mut position = replay.position as f64 / 1000.0;
+            let duration = replay.duration() as f64 / 1000.0;
+            let scrubber = ui.add(egui::Slider::new(&mut position, 0.0..=duration).suffix(" s"));
+            if scrubber.changed() {
+                replay.seek((position * 1000.0) as i64);
+            }
+        }
+    }
+
+    /// Exporting the infos dated within a range, of the replay while replaying and otherwise of what the server kept
+    fn export_ui(&mut self, ui: &mut egui::Ui) {
+        if self.exporting && self.client.is_none() {
+            self.exporting = false;
+            self.export_status = Some("Failed to export: disconnected.".to_string());
+        }
+        if self.replay.is_none() && self.client.is_none() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.export_format, ExportFormat::Csv, "CSV");
+            ui.radio_value(&mut self.export_format, ExportFormat::Ndjson, "NDJSON");
+            ui.label("Columns: ");
+            ui.text_edit_singleline(&mut self.export_columns)
+                .on_hover_text("e.g. date,cpu,disk./,net.eth0.rx, every column when empty.");
+        });
+        ui.horizontal(|ui| {
+            ui.label("From: ");
+            ui.add(egui::TextEdit::singleline(&mut self.export_from).desired_width(100.0))
+                .on_hover_text("Unix time in seconds, from the first info when empty.");
+            ui.label("To: ");
+            ui.add(egui::TextEdit::singleline(&mut self.export_to).desired_width(100.0))
+                .on_hover_text("Unix time in seconds, up to the last info when empty.");
+
+            let hover = match self.replay.is_some() {
+                true => "Write the replayed infos next to the recording as a spreadsheet.",
+                false => {
+                    "Write the infos the server kept to a spreadsheet in the working directory."
+                }
+            };
+            if ui
+                .add_enabled(!self.exporting, egui::Button::new("Export"))
+                .on_hover_text(hover)
+                .clicked()
+            {
+                match (
+                    parse_date(&self.export_from, i64::MIN),
+                    parse_date(&self.export_to, i64::MAX),
+                ) {
+                    (Ok(from), Ok(to)) => self.export(from, to),
+                    (Err(e), _) | (_, Err(e)) => self.export_status = Some(e),
+                }
+            }
+        });
+        if let Some(status) = &self.export_status {
+            ui.label(status);
+        }
+    }
+
+    /// Exports the replay right away, or asks the server and writes its answer once it arrives
+    fn export(&mut self, from: i64, to: i64) {
+        let columns: Vec<String> = self
+            .export_columns
+            .split(',')
+            .map(|column| column.trim().to_string())
+            .filter(|column| !column.is_empty())
+            .collect();
+        match &self.replay {
+            Some(replay) => {
+                let path = PathBuf::from(&self.recording_file)
+                    .with_extension(self.export_format.to_string());
+                let text = replay.export(self.export_format, &columns, from, to);
+                self.export_status = Some(write_export(&path, &text));
+            }
+            None => {
+                self.pending_request = Some(ClientRequest::Export {
+                    from,
+                    to,
+                    format: self.export_format,
+                    columns,
+                });
+                self.exporting = true;
+                self.export_status = Some("Exporting...".to_string());
+            }
+        }
+    }
+
+    /// The inventory as a page, with a button to save it as a text file to print or attach to a ticket
+    fn inventory_ui(&mut self, ctx: &egui::Context) {
+        let Some(inventory) = &self.inventory else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Inventory")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let page = inventory.to_string();
+                if ui
+                    .button("Save")
+                    .on_hover_text("Write the page to a text file in the working directory.")
+                    .clicked()
+                {
+                    let path = PathBuf::from(format!("inventory_{}.txt", inventory.host_name));
+                    match std::fs::write(&path, &page) {
+                        Ok(()) => println!("saved the inventory to {}", path.display()),
+                        Err(e) => println!("failed to save to {}: {e}", path.display()),
+                    }
+                }
+                ui.label(egui::RichText::new(page).monospace());
+            });
+        if !open {
+            self.inventory = None;
+        }
+    }
+
+    /// Asks the server to push the sections that are toggled on, at the update rate
+    fn subscription(&self) -> ClientRequest {
+        ClientRequest::Subscribe {
+            sections: Sections {
+                disks: self.displaying_disks,
+                net_interfaces: self.displaying_interfaces,
+                cpus: self.displaying_cpus,
+                components: self.displaying_sensors,
+                containers: self.displaying_containers,
+                services: self.displaying_services,
+                custom_metrics: self.displaying_custom,
+            },
+            interval_ms: (1000.0 / self.update_rate) as u64,
+            push: true,
+        }
+    }
+
+    /// Applies an update the server pushed if one arrived, and sends pending requests or a changed subscription
+    fn receive_update(&mut self) -> Result<(), ClientError> {
+        let client = self.client.as_mut().expect("Receiving without a client.");
+        if let Some(update) = client.try_receive()? {
+            if let Some(recorder) = &mut self.recorder {
+                if let Err(e) = recorder.record(&update.info) {
+                    println!("failed to record: {e}");
+                    self.recorder = None;
+                }
+            }
+            self.server_info = update.info;
+            self.sensor_panel.push(&self.server_info);
+            for (file, lines) in update.log_lines {
+                self.log_viewer.push_lines(&file, lines);
+            }
+            if let Some(text) = update.export {
+                let format = self.export_format.to_string();
+                let path = PathBuf::from(format!("export_{}.{format}", self.server_info.host_name));
+                self.export_status = Some(write_export(&path, &text));
+                self.exporting = false;
+            }
+            if let Some(e) = update.errors.into_iter().last() {
+                // an export the server refused, e.g. as too large
+                if self.exporting {
+                    self.export_status = Some(format!("Failed to export: {e}"));
+                    self.exporting = false;
+                } else {
+                    self.log_viewer.error = Some(e);
+                }
+            }
+            if let Some(inventory) = update.inventory {
+                self.inventory = Some(inventory);
+            }
+            if let Some(hosts) = update.hosts {
+                if !hosts.iter().any(|host| host.name == self.selected_host) {
+                    self.selected_host = hosts
+                        .first()
+                        .map(|host| host.name.to_string())
+                        .unwrap_or_default();
+                }
+                self.hosts = hosts;
+            }
+        }
+
+        let subscription = self.subscription();
+        let request = match self.pending_request.take() {
+            Some(request) => request,
+            None if self.subscribed.as_ref() != Some(&subscription) => {
+                self.subscribed = Some(subscription.clone());
+                subscription
+            }
+            None => return Ok(()),
+        };
+        let client = self.client.as_mut().expect("Receiving without a client.");
+        client.send(&request)
     }
-    None
 }
 
 impl eframe::App for MyEguiApp {
@@ -104,60 +676,107 @@ impl eframe::App for MyEguiApp {
         if self.first_run {
             self.first_run = false;
 
-            let ls = match last_session::read_from_file(LAST_SESSION_FILE_NAME) {
-                Ok(f) => f,
-                Err(_) => LastSession {
-                    address: "localhost:8111".to_string(),
-                    screen_dimension: (900.0, 900.0),
-                },
+            self.profiles = match profiles::read_from_file() {
+                Ok(profiles) => profiles,
+                Err(e) => {
+                    // saving now would replace every profile in the file with the defaults
+                    self.profiles_unreadable = true;
+                    self.profiles_status = Some(format!("{e}, it is left as it is."));
+                    Profiles::default()
+                }
             };
+            let profile = self.profiles.last_used().cloned().unwrap_or_default();
+            self.apply_profile(&profile);
 
-            self.address = ls.address;
             let size = Vec2 {
-                x: ls.screen_dimension.0,
-                y: ls.screen_dimension.1,
+                x: self.profiles.window_size.0,
+                y: self.profiles.window_size.1,
             };
             frame.set_window_size(size);
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ctx.request_repaint();
-            let data: Cow<str>;
 
-            let found_data = match &self.stream {
+            let found_data = match &self.client {
                 Some(_) => {
-                    if self.frames as f32 > (60.0) / self.update_rate {
-                        let mut small_buf: [u8; 4096] = [0; 4096];
-                        match self.stream.as_ref().unwrap().read(&mut small_buf) {
-                            // try to read stream into a buffer
-                            Ok(_) => {
-                                // data was able to be read
-
-                                for value in small_buf {
-                                    // make small buffer of the data into a vector sent by the server
-                                    if !String::from_utf8_lossy(&[value]).contains('\0') {
-                                        self.buf_vec.push(value);
-                                    }
-                                }
-                                let _ = self.stream.as_ref().unwrap().write(&[0]);
-                                data = String::from_utf8_lossy(&self.buf_vec); // convert the vector to a string
-                                if let Some(sinfo) = deserialize_server_info(&data) {
-                                    self.server_info = sinfo
-                                }
-                            }
-                            Err(_) => {
-                                // data was not able to be read, because of this, remove the stream
-                                self.stream = None;
-                            }
-                        };
+                    if let Err(e) = self.receive_update() {
+                        // data was not able to be read, because of this, remove the client
+                        self.server_stopped = matches!(e, ClientError::ServerStopped);
+                        self.client = None;
                     }
                     true
                 }
                 None => false,
             };
 
+            if let Some(replay) = &mut self.replay {
+                replay.advance(Duration::from_secs_f32(ctx.input().unstable_dt));
+                self.server_info = replay.current().info.clone();
+                self.sensor_panel.push(&self.server_info);
+            }
+
+            self.profiles_ui(ui);
+
             ui.text_edit_singleline(&mut self.address);
 
+            ui.horizontal(|ui| {
+                let discover_button = ui
+                    .button("Discover")
+                    .on_hover_text("Look for servers on the local network.");
+                if discover_button.clicked() && self.discovering.is_none() {
+                    let (sender, receiver) = mpsc::channel();
+                    thread::spawn(move || {
+                        let found = discover(DISCOVERY_PORT, DISCOVER_TIMEOUT);
+                        let _ = sender.send(found.map_err(|e| e.to_string()));
+                    });
+                    self.discovering = Some(receiver);
+                }
+                if self.discovering.is_some() {
+                    ui.label("Looking for servers...");
+                }
+            });
+            let answered = self
+                .discovering
+                .as_ref()
+                .and_then(|receiver| receiver.try_recv().ok());
+            if let Some(found) = answered {
+                self.discovering = None;
+                match found {
+                    Ok(found) => self.discovered = found,
+                    Err(e) => println!("failed to discover servers: {e}"),
+                }
+            }
+            for server in &self.discovered {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}: {}", server.address, server.announcement));
+                    let compatible = server.announcement.is_compatible();
+                    if ui
+                        .add_enabled(compatible, egui::Button::new("Use"))
+                        .clicked()
+                    {
+                        self.address = server.address.to_string();
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Token: ");
+                ui.add(egui::TextEdit::singleline(&mut self.auth_token).password(true))
+                    .on_hover_text("Sent when the server asks for a token.");
+                ui.label("Token variable: ");
+                ui.text_edit_singleline(&mut self.token_env)
+                    .on_hover_text("Environment variable the token is read from when none is typed in, saved in the profile instead of the token.");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Use TLS: ");
+                toggle_ui_compact(ui, &mut self.use_tls);
+                ui.label("CA file: ");
+                ui.text_edit_singleline(&mut self.tls_ca)
+                    .on_hover_text("Certificates to trust instead of the web roots, e.g. a self signed one.");
+            });
+
             ui.horizontal(|ui| {
                 ui.horizontal(|ui| {
                     ui.label("Display CPU info: ");
@@ -173,27 +792,68 @@ impl eframe::App for MyEguiApp {
                     ui.label("Display disk info: ");
                     toggle_ui_compact(ui, &mut self.displaying_disks);
                 });
+
+                ui.horizontal(|ui| {
+                    ui.label("Display container info: ");
+                    toggle_ui_compact(ui, &mut self.displaying_containers);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Display service info: ");
+                    toggle_ui_compact(ui, &mut self.displaying_services);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Display custom metrics: ");
+                    toggle_ui_compact(ui, &mut self.displaying_custom);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Display logs: ");
+                    toggle_ui_compact(ui, &mut self.displaying_logs);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Display sensors: ");
+                    toggle_ui_compact(ui, &mut self.displaying_sensors);
+                });
             });
 
             if ui.button("Connect").clicked() {
-                self.stream = match TcpStream::connect(self.address.as_str()) {
-                    Ok(s) => {
-                        s.set_read_timeout(Some(core::time::Duration::from_secs(5)))
-                            .unwrap();
-                        s.set_write_timeout(Some(core::time::Duration::from_secs(5)))
-                            .unwrap();
+                if self.profile_name.is_empty() {
+                    self.profile_name = self.address.to_string();
+                }
+                let profile = self.profile();
+                let options = ClientOptions {
+                    auth_token: Some(self.auth_token.to_string())
+                        .filter(|t| !t.is_empty())
+                        .or_else(|| profile.token()),
+                    tls: self.use_tls.then(|| TlsOptions {
+                        ca_file: profile.tls_ca.clone(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                self.client = match Client::connect(self.address.as_str(), &options) {
+                    Ok(client) => {
+                        self.server_stopped = false;
+                        self.replay = None;
                         let size = frame.info().window_info.size;
-                        let ls = LastSession {
-                            address: self.address.to_string(),
-                            screen_dimension: (size.x, size.y),
-                        };
-                        last_session::write_to_file(LAST_SESSION_FILE_NAME, ls)
-                            .expect("Unable to write to file.");
-
-                        Some(s)
+                        self.profiles.window_size = (size.x, size.y);
+                        self.profiles.last = Some(profile.name.to_string());
+                        self.profiles.save(profile);
+                        self.write_profiles();
+                        // a hub answers with its hosts, which are shown to pick from
+                        self.pending_request = Some(ClientRequest::ListHosts);
+                        self.subscribed = None;
+                        self.hosts.clear();
+                        self.log_viewer.reset();
+                        self.sensor_panel.reset();
+
+                        Some(client)
                     }
-                    Err(_) => {
-                        println!("tcp stream failed to connect.");
+                    Err(e) => {
+                        println!("failed to connect: {e}");
                         None
                     }
                 }
@@ -201,49 +861,172 @@ impl eframe::App for MyEguiApp {
 
             ui.horizontal(|ui| {
                 ui.label("Update Rate: ");
-                ui.add(egui::Slider::new(&mut self.update_rate, 0.1..=2.0))
+                ui.add(egui::Slider::new(
+                    &mut self.update_rate,
+                    MIN_UPDATE_RATE..=MAX_UPDATE_RATE,
+                ))
                     .on_hover_text("Update rate per second.");
             });
 
             if ui.button("Disconnect").clicked() {
-                match &self.stream {
+                match &self.client {
                     None => {
                         println!("failed to disconnect");
                     }
-                    Some(stream) => {
+                    Some(client) => {
                         println!("disconnected");
-                        stream
-                            .shutdown(Shutdown::Both)
-                            .expect("Unable to shutdown tcp stream.");
-                        self.stream = None;
+                        client.shutdown();
+                        self.client = None;
                     }
                 }
             }
 
-            if found_data {
-                self.buf_vec.clear();
+            if ui
+                .add_enabled(self.client.is_some(), egui::Button::new("Inventory"))
+                .on_hover_text("Show the hardware of the server.")
+                .clicked()
+            {
+                self.pending_request = Some(ClientRequest::Inventory);
+            }
+
+            self.recording_ui(ui);
+            self.export_ui(ui);
+
+            if self.hosts.len() > 1 {
+                let mut selected = self.selected_host.to_string();
+                egui::ComboBox::from_label("Host")
+                    .selected_text(selected.as_str())
+                    .show_ui(ui, |ui| {
+                        for host in &self.hosts {
+                            let label = match host.connected {
+                                true => host.name.to_string(),
+                                false => format!("{} (disconnected)", host.name),
+                            };
+                            ui.selectable_value(&mut selected, host.name.to_string(), label);
+                        }
+                    });
+                if selected != self.selected_host {
+                    self.pending_request = Some(ClientRequest::SelectHost {
+                        name: selected.to_string(),
+                    });
+                    self.selected_host = selected;
+                }
             }
 
-            ui.label(&self.server_info.get_date_time().to_string());
+            ui.label(self.server_info.get_date_time().to_string());
+
+            if self.server_stopped {
+                ui.colored_label(Color32::from_rgb(255, 200, 50), "Server stopped");
+            }
 
             if self.displaying_disks {
                 for disk in &self.server_info.disks {
-                    ui.colored_label(Color32::from_rgb(255, 255, 255), disk);
+                    ui.colored_label(Color32::from_rgb(255, 255, 255), disk.to_string());
                 }
             }
 
             if self.displaying_interfaces {
+                ui.horizontal(|ui| {
+                    ui.label("Hide loopback: ");
+                    toggle_ui_compact(ui, &mut self.hiding_loopback);
+                    ui.label("Hide virtual: ");
+                    toggle_ui_compact(ui, &mut self.hiding_virtual)
+                        .on_hover_text("veth, bridges like docker0 and other interfaces without a network card.");
+                });
                 for interface in &self.server_info.net_interfaces {
-                    ui.colored_label(Color32::from_rgb(255, 255, 255), interface);
+                    let hidden = match interface.kind {
+                        InterfaceKind::Physical => false,
+                        InterfaceKind::Loopback => self.hiding_loopback,
+                        InterfaceKind::Virtual => self.hiding_virtual,
+                    };
+                    if !hidden {
+                        ui.colored_label(interface_color(interface), interface.to_string());
+                    }
                 }
             }
 
             if self.displaying_cpus {
-                for cpu in &self.server_info.cpus {
-                    ui.colored_label(Color32::from_rgb(255, 255, 255), cpu);
+                cores_ui(ui, &self.server_info);
+            }
+
+            if self.displaying_sensors {
+                ui.collapsing("Sensors", |ui| {
+                    self.sensor_panel.ui(ui, &self.server_info.sensors);
+                });
+            }
+
+            if self.displaying_containers {
+                match &self.server_info.cgroup {
+                    None => {
+                        ui.label("Server is not running in a container.");
+                    }
+                    Some(cgroup) => {
+                        ui.horizontal(|ui| {
+                            ui.label("Container Memory: ");
+                            let used: f64 = cgroup.memory_usage as f64 / 1000000000.0;
+                            let s = match cgroup.memory_limit {
+                                None => format!("{used:.2} GB (no limit)"),
+                                Some(limit) => {
+                                    let limit: f64 = limit as f64 / 1000000000.0;
+                                    format!("{used:.2} / {limit:.2} GB")
+                                }
+                            };
+                            ui.label(s);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Container CPU: ");
+                            let s = match cgroup.cpu_limit {
+                                None => format!("{:.2} % (no limit)", cgroup.cpu_usage),
+                                Some(limit) => {
+                                    format!("{:.2} % of {limit:.2} cores", cgroup.cpu_usage)
+                                }
+                            };
+                            ui.label(s);
+                        });
+                    }
+                }
+
+                for container in &self.server_info.containers {
+                    ui.colored_label(Color32::from_rgb(255, 255, 255), container.to_string());
+                }
+            }
+
+            if self.displaying_services {
+                for service in &self.server_info.services {
+                    let service_color = {
+                        if service.is_failed() {
+                            Color32::from_rgb(255, 50, 50)
+                        } else if service.is_restarting() {
+                            Color32::from_rgb(255, 200, 50)
+                        } else if service.active_state == "active" {
+                            Color32::from_rgb(50, 255, 50)
+                        } else {
+                            Color32::from_rgb(255, 255, 255)
+                        }
+                    };
+                    ui.colored_label(service_color, service.to_string());
                 }
             }
 
+            if self.displaying_custom {
+                ui.collapsing("Custom", |ui| {
+                    for group in &self.server_info.custom_metrics {
+                        ui.label(&group.name);
+                        for metric in &group.metrics {
+                            ui.colored_label(Color32::from_rgb(255, 255, 255), metric.to_string());
+                        }
+                    }
+                });
+            }
+
+            if self.displaying_logs {
+                ui.collapsing("Logs", |ui| {
+                    if let Some(request) = self.log_viewer.ui(ui, &self.server_info.log_files) {
+                        self.pending_request = Some(request);
+                    }
+                });
+            }
+
             ui.horizontal(|ui| {
                 ui.label("Average CPU Usage: ");
                 let s = format_args!("{:.2} %", &self.server_info.avg_cpu_usage).to_string();
@@ -287,6 +1070,8 @@ impl eframe::App for MyEguiApp {
             let indicator_rect_color = {
                 if found_data {
                     Color32::from_rgb(50, 255, 50)
+                } else if self.server_stopped {
+                    Color32::from_rgb(255, 200, 50)
                 } else {
                     Color32::from_rgb(255, 50, 50)
                 }
@@ -307,11 +1092,8 @@ impl eframe::App for MyEguiApp {
             }
 
             egui::warn_if_debug_build(ui);
-
-            if self.frames as f32 > ((60.0) / self.update_rate) {
-                self.frames = 0;
-            }
-            self.frames += 1;
         });
+
+        self.inventory_ui(ctx);
     }
 }
@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where older versions kept the last address, read once when there are no profiles yet
+static LAST_SESSION_FILE_NAME: &str = "server_info_last_session.sav";
+
+static PROFILES_FILE_NAME: &str = "profiles.toml";
+
+/// Update rates the slider offers, rates read from files are clamped to them
+pub const MIN_UPDATE_RATE: f32 = 0.1;
+pub const MAX_UPDATE_RATE: f32 = 2.0;
+
+/// The sections toggled on in the client.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct Visible {
+    pub cpus: bool,
+    pub interfaces: bool,
+    pub disks: bool,
+    pub containers: bool,
+    pub services: bool,
+    pub custom: bool,
+    pub logs: bool,
+    pub sensors: bool,
+    /// leaves lo out of the network interfaces
+    pub hide_loopback: bool,
+    /// leaves veth, bridges like docker0 and other interfaces without a network card out
+    pub hide_virtual: bool,
+}
+
+/// A server and how to connect to it and display it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub address: String,
+    /// the environment variable holding the token, tokens themselves are not stored so profiles can be shared
+    pub token_env: Option<String>,
+    pub tls: bool,
+    pub tls_ca: Option<PathBuf>,
+    /// updates per second
+    pub update_rate: f32,
+    // tables go last in toml
+    pub visible: Visible,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "localhost".to_string(),
+            address: "localhost:8111".to_string(),
+            token_env: None,
+            tls: false,
+            tls_ca: None,
+            update_rate: 0.5,
+            visible: Visible::default(),
+        }
+    }
+}
+
+impl Profile {
+    /// The token from token_env, none when it is not set
+    pub fn token(&self) -> Option<String> {
+        let name = self.token_env.as_ref()?;
+        env::var(name).ok().filter(|token| !token.is_empty())
+    }
+}
+
+/// Every saved profile, and which one was used last.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profiles {
+    /// name of the profile selected when the client starts
+    pub last: Option<String>,
+    pub window_size: (f32, f32),
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            last: None,
+            window_size: (900.0, 900.0),
+            profiles: vec![],
+        }
+    }
+}
+
+/// Profiles exported to share, without the window size and last profile of whoever exported them.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+struct Exported {
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Adds the profile, replacing the one of the same name
+    pub fn save(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|profile| profile.name != name);
+        if self.last.as_deref() == Some(name) {
+            self.last = None;
+        }
+    }
+
+    /// The last used profile, or the first one
+    pub fn last_used(&self) -> Option<&Profile> {
+        self.last
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.profiles.first())
+    }
+
+    /// Clamps the update rates to what the slider offers, failing on rates that are not a number at all
+    fn check(profiles: &mut [Profile]) -> Result<(), String> {
+        for profile in profiles {
+            if !profile.update_rate.is_finite() {
+                return Err(format!(
+                    "Profile {} has an update rate of {}.",
+                    profile.name, profile.update_rate
+                ));
+            }
+            profile.update_rate = profile.update_rate.clamp(MIN_UPDATE_RATE, MAX_UPDATE_RATE);
+        }
+        Ok(())
+    }
+
+    /// Writes every profile to path, as json when it ends in .json and as toml otherwise
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let exported = Exported {
+            profiles: self.profiles.clone(),
+        };
+        let contents = match is_json(path) {
+            true => serde_json::to_string_pretty(&exported).map_err(|e| e.to_string())?,
+            false => toml::to_string(&exported).map_err(|e| e.to_string())?,
+        };
+        fs::write(path, contents).map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// Adds the profiles of an exported file, replacing those of the same names.
+    ///
+    /// Returns how many were imported.
+    pub fn import(&mut self, path: &Path) -> Result<usize, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let mut exported: Exported = match is_json(path) {
+            true => serde_json::from_str(&contents).map_err(|e| e.to_string())?,
+            false => toml::from_str(&contents).map_err(|e| e.to_string())?,
+        };
+        Profiles::check(&mut exported.profiles)?;
+        let count = exported.profiles.len();
+        for profile in exported.profiles {
+            self.save(profile);
+        }
+        Ok(count)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+/// The directory of this client in the platform config dir, e.g. ~/.config/server_info_rs
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    Some(base?.join("server_info_rs"))
+}
+
+/// Reads the profiles, starting from the last session file of older versions when there are none yet
+pub fn read_from_file() -> Result<Profiles, String> {
+    let path = config_dir()
+        .ok_or("No config directory.")?
+        .join(PROFILES_FILE_NAME);
+    match fs::read_to_string(&path) {
+        Ok(contents) => {
+            let mut profiles: Profiles = toml::from_str(&contents)
+                .map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+            Profiles::check(&mut profiles.profiles)
+                .map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+            Ok(profiles)
+        }
+        Err(_) => match fs::read_to_string(LAST_SESSION_FILE_NAME) {
+            Ok(last_session) => Ok(from_last_session(&last_session)),
+            Err(_) => Ok(Profiles::default()),
+        },
+    }
+}
+
+pub fn write_to_file(profiles: &Profiles) -> Result<(), String> {
+    let dir = config_dir().ok_or("No config directory.")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {e}", dir.display()))?;
+    let path = dir.join(PROFILES_FILE_NAME);
+    let contents = toml::to_string(profiles).map_err(|e| e.to_string())?;
+    fs::write(&path, contents).map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+/// Profiles holding the address of a last session file, "address,width,height"
+fn from_last_session(last_session: &str) -> Profiles {
+    // split from the end, the address is whatever comes before the dimensions
+    let mut fields = last_session.trim().rsplitn(3, ',');
+    let height = fields.next().and_then(|height| height.parse().ok());
+    let width = fields.next().and_then(|width| width.parse().ok());
+    let mut profiles = Profiles::default();
+    if let (Some(width), Some(height), Some(address)) = (width, height, fields.next()) {
+        profiles.window_size = (width, height);
+        profiles.save(Profile {
+            name: address.to_string(),
+            address: address.to_string(),
+            ..Default::default()
+        });
+    }
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, address: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            address: address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profiles_round_trip_through_toml() {
+        let mut profiles = Profiles::default();
+        profiles.save(Profile {
+            token_env: Some("WEB_TOKEN".to_string()),
+            tls: true,
+            visible: Visible {
+                cpus: true,
+                logs: true,
+                ..Default::default()
+            },
+            ..profile("web", "[2001:db8::1]:8111")
+        });
+        profiles.save(profile("db", "db.internal:8111"));
+        profiles.last = Some("db".to_string());
+
+        let read: Profiles = toml::from_str(&toml::to_string(&profiles).unwrap()).unwrap();
+        assert_eq!(read, profiles);
+        assert_eq!(read.last_used().unwrap().name, "db");
+    }
+
+    #[test]
+    fn update_rates_are_clamped_to_the_slider() {
+        let mut profiles = vec![
+            Profile {
+                update_rate: 0.0,
+                ..profile("stopped", "a:8111")
+            },
+            Profile {
+                update_rate: 60.0,
+                ..profile("fast", "b:8111")
+            },
+        ];
+        assert_eq!(Profiles::check(&mut profiles), Ok(()));
+        assert_eq!(profiles[0].update_rate, MIN_UPDATE_RATE);
+        assert_eq!(profiles[1].update_rate, MAX_UPDATE_RATE);
+
+        let mut read: Profiles =
+            toml::from_str("[[profiles]]\nname = \"nan\"\nupdate_rate = nan\n").unwrap();
+        assert!(Profiles::check(&mut read.profiles).is_err());
+    }
+
+    #[test]
+    fn imports_replace_profiles_of_the_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for file_name in ["team.toml", "team.json"] {
+            let path = dir.path().join(file_name);
+            let mut shared = Profiles::default();
+            shared.save(profile("web", "web.internal:8111"));
+            shared.save(profile("db", "db.internal:8111"));
+            shared.export(&path).unwrap();
+
+            let mut profiles = Profiles::default();
+            profiles.save(profile("web", "old.internal:8111"));
+            profiles.save(profile("mine", "localhost:8111"));
+            assert_eq!(profiles.import(&path), Ok(2));
+            assert_eq!(profiles.profiles.len(), 3);
+            assert_eq!(profiles.get("web").unwrap().address, "web.internal:8111");
+        }
+    }
+
+    #[test]
+    fn last_sessions_become_a_profile() {
+        let profiles = from_last_session("[::1]:8111,800,600\n");
+        assert_eq!(profiles.window_size, (800.0, 600.0));
+        assert_eq!(profiles.profiles, vec![profile("[::1]:8111", "[::1]:8111")]);
+
+        assert_eq!(from_last_session("garbage"), Profiles::default());
+    }
+}
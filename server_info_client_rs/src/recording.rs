@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::export::{export, ExportFormat};
+use server_info_packets::protocol::{read_message, write_message};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Recordings are frames like on the wire, json so older recordings stay readable and every one compressed
+const RECORDING_CODEC: Codec = Codec {
+    encoding: Encoding::Json,
+    compression: Compression::Zstd,
+    threshold: 0,
+};
+
+/// An info and when the client received it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Recorded {
+    /// unix time in milliseconds, counted from when recording started so the clock stepping back does not reorder frames
+    pub received: i64,
+    pub info: ServerInfo,
+}
+
+/// Appends every info received to a file, one frame each so a recording cut short is still readable.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    /// unix time in milliseconds recording started at
+    started_at: i64,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file =
+            File::create(path).map_err(|e| format!("Could not create {}: {e}", path.display()))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, info: &ServerInfo) -> Result<(), String> {
+        let recorded = Recorded {
+            received: self.started_at + self.started.elapsed().as_millis() as i64,
+            info: info.clone(),
+        };
+        write_message(&mut self.writer, RECORDING_CODEC, &recorded).map_err(|e| e.to_string())
+    }
+}
+
+/// A recording being played back, the client displays the info that was received at the position.
+pub struct Replay {
+    frames: Vec<Recorded>,
+    /// milliseconds since the first info
+    pub position: i64,
+    pub playing: bool,
+    /// 1 plays at the speed the infos were received
+    pub speed: f32,
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Could not open {}: {e}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let mut frames = vec![];
+        loop {
+            match read_message::<Recorded>(&mut reader, RECORDING_CODEC) {
+                Ok(recorded) => frames.push(recorded),
+                // the end of the file, or of the last frame when recording was cut short
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(format!("Invalid recording {}: {e}", path.display())),
+            }
+        }
+        Self::new(frames).ok_or_else(|| format!("{} holds no infos.", path.display()))
+    }
+
+    /// None without frames
+    fn new(mut frames: Vec<Recorded>) -> Option<Self> {
+        if frames.is_empty() {
+            return None;
+        }
+        // current() searches the frames by time, a file that was edited or joined may hold them out of order
+        frames.sort_by_key(|frame| frame.received);
+        Some(Self {
+            frames,
+            position: 0,
+            playing: true,
+            speed: 1.0,
+        })
+    }
+
+    /// Milliseconds from the first info to the last
+    pub fn duration(&self) -> i64 {
+        self.frames[self.frames.len() - 1].received - self.frames[0].received
+    }
+
+    /// Moves the position on by elapsed at speed while playing, pausing at the end
+    pub fn advance(&mut self, elapsed: Duration) {
+        if !self.playing {
+            return;
+        }
+        let position = self.position + (elapsed.as_secs_f64() * self.speed as f64 * 1000.0) as i64;
+        self.seek(position);
+        if self.position == self.duration() {
+            self.playing = false;
+        }
+    }
+
+    pub fn seek(&mut self, position: i64) {
+        self.position = position.clamp(0, self.duration().max(0));
+    }
+
+    /// The recorded infos dated from..=to as csv or ndjson, with only the named columns or all of them when empty
+    pub fn export(&self, format: ExportFormat, columns: &[String], from: i64, to: i64) -> String {
+        let infos: Vec<ServerInfo> = self
+            .frames
+            .iter()
+            .filter(|frame| (from..=to).contains(&frame.info.date))
+            .map(|frame| frame.info.clone())
+            .collect();
+        export(&infos, format, columns)
+    }
+
+    /// The last info received at or before the position
+    pub fn current(&self) -> &Recorded {
+        let at = self.frames[0].received + self.position;
+        let index = self.frames.partition_point(|frame| frame.received <= at);
+        &self.frames[index.max(1) - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn replays_recordings_cut_short() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording");
+        let mut recorder = Recorder::create(&path).unwrap();
+        for date in 0..3 {
+            let info = ServerInfo {
+                date,
+                ..Default::default()
+            };
+            recorder.record(&info).unwrap();
+        }
+        drop(recorder);
+        // half a frame, as left by a client that was killed while writing
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0x80, 0, 1, 0, 42]).unwrap();
+
+        let replay = Replay::open(&path).unwrap();
+        let dates: Vec<i64> = replay.frames.iter().map(|frame| frame.info.date).collect();
+        assert_eq!(dates, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn exports_the_infos_dated_within_the_range() {
+        let frames = (0..5)
+            .map(|date| Recorded {
+                received: date * 1000,
+                info: ServerInfo {
+                    date,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let replay = Replay::new(frames).unwrap();
+        let columns = vec!["date".to_string()];
+        assert_eq!(
+            replay.export(ExportFormat::Csv, &columns, 1, 3),
+            "date\n1\n2\n3\n"
+        );
+        assert_eq!(
+            replay.export(ExportFormat::Csv, &columns, 4, i64::MAX),
+            "date\n4\n"
+        );
+    }
+
+    #[test]
+    fn plays_infos_at_the_time_they_were_received() {
+        let frames = [1000, 2000, 4000]
+            .into_iter()
+            .enumerate()
+            .map(|(date, received)| Recorded {
+                received,
+                info: ServerInfo {
+                    date: date as i64,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let mut replay = Replay::new(frames).unwrap();
+        assert_eq!(replay.duration(), 3000);
+
+        replay.speed = 2.0;
+        replay.advance(Duration::from_millis(600));
+        assert_eq!(replay.position, 1200);
+        assert_eq!(replay.current().info.date, 1);
+
+        replay.playing = false;
+        replay.advance(Duration::from_secs(10));
+        assert_eq!(replay.current().info.date, 1);
+
+        replay.playing = true;
+        replay.advance(Duration::from_secs(10));
+        assert_eq!(replay.position, 3000);
+        assert_eq!(replay.current().info.date, 2);
+        assert!(!replay.playing);
+
+        replay.seek(-5);
+        assert_eq!(replay.current().info.date, 0);
+        assert!(Replay::new(vec![]).is_none());
+    }
+
+    #[test]
+    fn replays_frames_that_are_out_of_order() {
+        let frames = [5000, 6000, 1000, 2000]
+            .into_iter()
+            .enumerate()
+            .map(|(date, received)| Recorded {
+                received,
+                info: ServerInfo {
+                    date: date as i64,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let mut replay = Replay::new(frames).unwrap();
+        assert_eq!(replay.duration(), 5000);
+
+        replay.seek(1500);
+        assert_eq!(replay.current().info.date, 3);
+        replay.seek(i64::MAX);
+        assert_eq!(replay.current().info.date, 1);
+    }
+}
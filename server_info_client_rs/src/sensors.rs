@@ -0,0 +1,183 @@
+use eframe::egui;
+use eframe::egui::plot::{Line, Plot};
+use eframe::egui::Color32;
+use server_info_packets::sensor_info::Sensors;
+use server_info_packets::server_info_packet::ServerInfo;
+use std::collections::{HashMap, VecDeque};
+
+/// Readings kept per sensor for its chart, older readings are dropped
+const MAX_READINGS: usize = 300;
+
+/// Temperatures this close to critical are shown as hot, and as warm a bit before
+const HOT: f32 = 0.9;
+const WARM: f32 = 0.75;
+
+/// Panel showing the sensors of the server, with a chart of each since the client connected.
+#[derive(Default)]
+pub struct SensorPanel {
+    /// (date, reading) oldest first, keyed by "<kind> <label>" as fans and temperatures can share labels
+    readings: HashMap<String, VecDeque<(i64, f64)>>,
+    /// date of the last info pushed
+    last: Option<i64>,
+}
+
+/// White without a critical value, otherwise green through yellow to red as it gets close
+pub fn heat_color(heat: Option<f32>) -> Color32 {
+    match heat {
+        None => Color32::from_rgb(255, 255, 255),
+        Some(heat) if heat >= HOT => Color32::from_rgb(255, 50, 50),
+        Some(heat) if heat >= WARM => Color32::from_rgb(255, 200, 50),
+        Some(_) => Color32::from_rgb(50, 255, 50),
+    }
+}
+
+impl SensorPanel {
+    /// Adds the readings of info to the charts, once per date, forgetting sensors that are gone
+    pub fn push(&mut self, info: &ServerInfo) {
+        if self.last == Some(info.date) {
+            return;
+        }
+        self.last = Some(info.date);
+
+        let current = readings(&info.sensors);
+        self.readings.retain(|key, _| current.contains_key(key));
+        for (key, value) in current {
+            let readings = self.readings.entry(key).or_default();
+            readings.push_back((info.date, value));
+            while readings.len() > MAX_READINGS {
+                readings.pop_front();
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.readings.clear();
+        self.last = None;
+    }
+
+    /// A row per sensor with its chart, temperatures coloured by how close they are to critical
+    pub fn ui(&self, ui: &mut egui::Ui, sensors: &Sensors) {
+        if sensors.is_empty() {
+            ui.label("The server has no sensors.");
+            return;
+        }
+        for temperature in &sensors.temperatures {
+            let color = heat_color(temperature.heat());
+            ui.colored_label(color, temperature.to_string());
+            self.chart(
+                ui,
+                &format!("temperature {}", temperature.label),
+                color,
+                temperature.critical,
+            );
+        }
+        for fan in &sensors.fans {
+            ui.colored_label(Color32::from_rgb(255, 255, 255), fan.to_string());
+            self.chart(ui, &format!("fan {}", fan.label), Color32::WHITE, None);
+        }
+        for power in &sensors.power {
+            ui.colored_label(Color32::from_rgb(255, 255, 255), power.to_string());
+            self.chart(ui, &format!("power {}", power.label), Color32::WHITE, None);
+        }
+    }
+
+    /// A small chart of the readings kept under key from 0 up to at least top, seconds before the newest reading along x
+    fn chart(&self, ui: &mut egui::Ui, key: &str, color: Color32, top: Option<f32>) {
+        let points: Vec<[f64; 2]> = match (self.readings.get(key), self.last) {
+            (Some(readings), Some(last)) => readings
+                .iter()
+                .map(|(date, value)| [(date - last) as f64, *value])
+                .collect(),
+            _ => vec![],
+        };
+        let mut plot = Plot::new(key)
+            .height(60.0)
+            .include_y(0.0)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_drag(false)
+            .allow_boxed_zoom(false);
+        if let Some(top) = top {
+            plot = plot.include_y(top);
+        }
+        plot.show(ui, |plot_ui| plot_ui.line(Line::new(points).color(color)));
+    }
+}
+
+/// Every reading of sensors by the key its chart is kept under
+fn readings(sensors: &Sensors) -> HashMap<String, f64> {
+    let temperatures = sensors.temperatures.iter().map(|temperature| {
+        (
+            format!("temperature {}", temperature.label),
+            temperature.current as f64,
+        )
+    });
+    let fans = sensors
+        .fans
+        .iter()
+        .map(|fan| (format!("fan {}", fan.label), fan.rpm as f64));
+    let power = sensors
+        .power
+        .iter()
+        .map(|power| (format!("power {}", power.label), power.watts as f64));
+    temperatures.chain(fans).chain(power).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use server_info_packets::sensor_info::{Fan, Temperature};
+
+    fn info(date: i64, current: f32, fans: bool) -> ServerInfo {
+        ServerInfo {
+            date,
+            sensors: Sensors {
+                temperatures: vec![Temperature {
+                    label: "Core 0".to_string(),
+                    current,
+                    max: current,
+                    critical: Some(100.0),
+                }],
+                fans: match fans {
+                    true => vec![Fan {
+                        label: "Core 0".to_string(),
+                        rpm: 900,
+                    }],
+                    false => vec![],
+                },
+                power: vec![],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_a_reading_per_date() {
+        let mut panel = SensorPanel::default();
+        panel.push(&info(1, 40.0, true));
+        panel.push(&info(1, 41.0, true));
+        panel.push(&info(2, 42.0, true));
+        assert_eq!(
+            panel.readings["temperature Core 0"],
+            VecDeque::from(vec![(1, 40.0), (2, 42.0)])
+        );
+        assert_eq!(panel.readings["fan Core 0"].len(), 2);
+
+        // the fan stopped being reported
+        panel.push(&info(3, 43.0, false));
+        assert!(!panel.readings.contains_key("fan Core 0"));
+
+        for date in 4..(MAX_READINGS as i64 + 10) {
+            panel.push(&info(date, 50.0, false));
+        }
+        assert_eq!(panel.readings["temperature Core 0"].len(), MAX_READINGS);
+    }
+
+    #[test]
+    fn colours_temperatures_relative_to_critical() {
+        assert_eq!(heat_color(None), Color32::from_rgb(255, 255, 255));
+        assert_eq!(heat_color(Some(0.5)), Color32::from_rgb(50, 255, 50));
+        assert_eq!(heat_color(Some(0.8)), Color32::from_rgb(255, 200, 50));
+        assert_eq!(heat_color(Some(1.2)), Color32::from_rgb(255, 50, 50));
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Deserialize;
+use server_info_client::{Compression, Encoding};
+use server_info_server_rs::config::{parse, AuthConfig, DiscoveryConfig, ListenConfig};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Settings read from the hub config file, every field falls back to its default when missing.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HubConfig {
+    /// where clients connect, the same settings as the servers
+    pub listen: ListenConfig,
+    /// the token clients of the hub have to send
+    pub auth: AuthConfig,
+    pub agents: AgentsConfig,
+    /// answering clients looking for servers, the same settings as the servers
+    pub discovery: DiscoveryConfig,
+    /// infos kept per host for ClientRequest::History
+    pub history_len: usize,
+    /// the servers to collect from
+    pub upstream: Vec<UpstreamConfig>,
+}
+
+impl Default for HubConfig {
+    fn default() -> Self {
+        Self {
+            listen: ListenConfig::default(),
+            auth: AuthConfig::default(),
+            agents: AgentsConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            history_len: 3600,
+            upstream: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentsConfig {
+    /// servers running as agents have to register with this token, agents are only accepted when it is set
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// what clients select the host by, unique among the upstreams
+    pub name: String,
+    pub address: String,
+    /// sent when the server asks for a token
+    #[serde(default)]
+    pub token: Option<String>,
+    /// how often the server pushes its info
+    #[serde(default = "default_upstream_interval_ms")]
+    pub interval_ms: u64,
+    /// json, msgpack, cbor or bincode
+    #[serde(default = "default_upstream_encoding", deserialize_with = "parse")]
+    pub encoding: Encoding,
+    /// none, deflate or zstd
+    #[serde(default, deserialize_with = "parse")]
+    pub compression: Compression,
+    /// connect with tls, e.g. to a tls terminating proxy in front of the server
+    #[serde(default)]
+    pub tls: bool,
+    /// pem file with the certificates to trust for tls, instead of the usual web roots
+    #[serde(default)]
+    pub tls_ca: Option<PathBuf>,
+}
+
+fn default_upstream_interval_ms() -> u64 {
+    1000
+}
+
+fn default_upstream_encoding() -> Encoding {
+    Encoding::Bincode
+}
+
+/// The config in file_name, the defaults when there is no such file.
+///
+/// A file that can not be read or parsed is an error rather than the defaults, which have no tokens and no upstreams.
+pub fn read_from_file(file_name: &str) -> Result<HubConfig, String> {
+    let file_as_string = match fs::read_to_string(file_name) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("Using default config, {file_name} does not exist");
+            return Ok(HubConfig::default());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let config: HubConfig = toml::from_str(&file_as_string).map_err(|e| e.to_string())?;
+    for (i, upstream) in config.upstream.iter().enumerate() {
+        if config.upstream[..i]
+            .iter()
+            .any(|other| other.name == upstream.name)
+        {
+            return Err(format!("Upstream {} is configured twice.", upstream.name));
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_config_parses() {
+        let example = include_str!("../../EXAMPLE-server_info_hub.toml");
+        let config: HubConfig = toml::from_str(example).unwrap();
+        assert_eq!(config.upstream.len(), 2);
+        assert_eq!(config.upstream[0].encoding, Encoding::Bincode);
+        assert_eq!(config.upstream[1].compression, Compression::Zstd);
+        assert_eq!(config.upstream[1].interval_ms, 5000);
+        assert!(config.agents.token.is_some());
+    }
+
+    #[test]
+    fn configs_that_do_not_parse_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server_info_hub.toml");
+        let read = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            read_from_file(path.to_str().unwrap())
+        };
+        let upstream = "[[upstream]]\nname = \"web\"\naddress = \"web:8111\"\n";
+        assert!(read(upstream).is_ok());
+        assert!(read(&upstream.repeat(2)).is_err());
+        assert!(read("[agents]\ntokn = \"secret\"").is_err());
+
+        let missing = dir.path().join("missing.toml");
+        assert!(read_from_file(missing.to_str().unwrap())
+            .unwrap()
+            .upstream
+            .is_empty());
+    }
+}
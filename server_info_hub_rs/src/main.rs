@@ -0,0 +1,150 @@
+use crate::config::UpstreamConfig;
+use server_info_client::{AsyncClient, ClientError, ClientOptions, TlsOptions};
+use server_info_packets::discovery::Announcement;
+use server_info_packets::protocol::{ClientRequest, Sections};
+use server_info_server_rs::hosts::Hosts;
+use server_info_server_rs::log_tail::LogStore;
+use server_info_server_rs::server::{Shared, Source};
+use server_info_server_rs::{discovery, server};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{System, SystemExt};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+mod config;
+
+static CONFIG_FILE_NAME: &str = "server_info_hub.toml";
+
+/// Waited before connecting to an upstream again after its connection broke
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How long an upstream gets to connect and answer, on top of its interval
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn main() {
+    let config_file_name =
+        std::env::var("SERVER_INFO_HUB_CONFIG").unwrap_or_else(|_| CONFIG_FILE_NAME.to_string());
+    let config = match config::read_from_file(&config_file_name) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Unable to read {config_file_name}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if config.upstream.is_empty() {
+        println!("No upstream servers configured, clients will only get empty infos.");
+    }
+
+    let hosts = Arc::new(Hosts::new(
+        config
+            .upstream
+            .iter()
+            .map(|upstream| (upstream.name.to_string(), upstream.address.to_string())),
+        config.history_len,
+    ));
+
+    let announcement = Announcement {
+        host_name: hub_name(),
+        auth: config.auth.token.is_some(),
+        hub: true,
+        ..Default::default()
+    };
+
+    let shared = Arc::new(Shared {
+        source: Source::Hub(hosts.clone()),
+        // the hub has no logs of its own to tail
+        logs: LogStore::spawn(vec![]),
+        auth_token: config.auth.token,
+        agent_token: config.agents.token,
+        write_timeout: Duration::from_millis(config.listen.write_timeout_ms),
+        compression_threshold: config.listen.compression_threshold,
+    });
+
+    let runtime = tokio::runtime::Runtime::new().expect("Unable to start the tokio runtime.");
+    runtime.block_on(async {
+        for upstream in config.upstream {
+            tokio::spawn(collect(upstream, hosts.clone()));
+        }
+
+        discovery::start(&config.discovery, &config.listen.address, announcement).await;
+
+        let listener = match TcpListener::bind(&config.listen.address).await {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen on {}: {e}", config.listen.address),
+        };
+        println!("Listening for connections on {}!", config.listen.address);
+
+        let (shutdown_sender, shutdown) = watch::channel(false);
+        let server = tokio::spawn(server::serve(
+            listener,
+            shared,
+            config.listen.max_connections,
+            shutdown,
+        ));
+
+        server::shutdown_signal().await;
+        println!("Shutting down, telling clients.");
+        let _ = shutdown_sender.send(true);
+
+        let grace_period = Duration::from_millis(config.listen.shutdown_grace_ms);
+        if tokio::time::timeout(grace_period, server).await.is_err() {
+            println!("Not every client disconnected within {grace_period:?}, stopping anyway.");
+        }
+    });
+    // upstream connections and clients still connected are dropped with the runtime
+    runtime.shutdown_timeout(Duration::ZERO);
+}
+
+/// The host name the hub announces to clients looking for servers
+fn hub_name() -> String {
+    System::new()
+        .host_name()
+        .unwrap_or_else(|| "hub".to_string())
+}
+
+/// Keeps the infos of an upstream in hosts, reconnecting whenever the connection breaks
+async fn collect(upstream: UpstreamConfig, hosts: Arc<Hosts>) {
+    loop {
+        let Err(error) = follow(&upstream, &hosts).await;
+        println!(
+            "Lost upstream {} ({}): {error}",
+            upstream.name, upstream.address
+        );
+        hosts.disconnected(&upstream.name, error.to_string());
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Subscribes to every section of an upstream and stores what it pushes, until the connection breaks
+async fn follow(upstream: &UpstreamConfig, hosts: &Hosts) -> Result<Infallible, ClientError> {
+    let options = ClientOptions {
+        timeout: UPSTREAM_TIMEOUT,
+        auth_token: upstream.token.clone(),
+        tls: upstream.tls.then(|| TlsOptions {
+            server_name: None,
+            ca_file: upstream.tls_ca.clone(),
+        }),
+        encodings: vec![upstream.encoding],
+        compressions: vec![upstream.compression],
+        deltas: true,
+    };
+    let mut client = AsyncClient::connect(&upstream.address, &options).await?;
+    println!(
+        "Connected to upstream {} ({}).",
+        upstream.name, upstream.address
+    );
+
+    let subscribe = ClientRequest::Subscribe {
+        sections: Sections::default(),
+        interval_ms: upstream.interval_ms,
+        push: true,
+    };
+    // the greeting first, then whatever the upstream pushes
+    hosts.update(&upstream.name, client.receive().await?.info);
+    client.send(&subscribe).await?;
+    loop {
+        hosts.update(&upstream.name, client.receive().await?.info);
+    }
+}
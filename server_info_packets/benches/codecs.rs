@@ -0,0 +1,170 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use server_info_packets::codec::Encoding;
+use server_info_packets::container_info::{CgroupInfo, ContainerStats};
+use server_info_packets::cpu_info::{CoreInfo, CpuTimes};
+use server_info_packets::custom_metric::{CustomMetric, CustomMetricGroup, MetricValue};
+use server_info_packets::disk_info::DiskInfo;
+use server_info_packets::network_info::{InterfaceKind, NetworkInterfaceInfo};
+use server_info_packets::protocol::ServerMessage;
+use server_info_packets::sensor_info::{Fan, Power, Sensors, Temperature};
+use server_info_packets::server_info_packet::ServerInfo;
+use server_info_packets::service_info::ServiceStatus;
+use std::hint::black_box;
+
+/// A 64 core host running 20 containers, about what a busy server sends every half second
+fn snapshot() -> ServerMessage {
+    let info = ServerInfo {
+        date: 1_700_000_000,
+        disks: (0..8)
+            .map(|i| DiskInfo {
+                name: format!("/dev/nvme{i}n1p1"),
+                mount_point: format!("/srv/data{i}"),
+                file_system: "ext4".to_string(),
+                total_space: 2_000_000_000_000,
+                available_space: 1_234_567_890_123,
+                is_removable: false,
+            })
+            .collect(),
+        net_interfaces: (0..6)
+            .map(|i| NetworkInterfaceInfo {
+                name: format!("eth{i}"),
+                received: 123_456 * i,
+                transmitted: 654_321 * i,
+                mac: Some(format!("52:54:00:12:34:5{i}")),
+                mtu: Some(1500),
+                up: Some(true),
+                speed: Some(10_000),
+                addresses: vec![
+                    format!("10.0.{i}.2").parse().unwrap(),
+                    format!("fd00::{i}:2").parse().unwrap(),
+                ],
+                rx_errors: 0,
+                tx_errors: 0,
+                rx_dropped: 12 * i,
+                tx_dropped: 0,
+                kind: InterfaceKind::Physical,
+            })
+            .collect(),
+        total_ram: 270_000_000_000,
+        used_memory: 123_000_000_000,
+        system_name: "Ubuntu".to_string(),
+        kernel_version: "6.8.0-45-generic".to_string(),
+        os_version: "24.04".to_string(),
+        host_name: "db-primary-01".to_string(),
+        total_cpus: 64,
+        avg_cpu_usage: 37.5,
+        cgroup: Some(CgroupInfo {
+            version: 2,
+            memory_limit: Some(8_000_000_000),
+            memory_usage: 2_500_000_000,
+            cpu_limit: Some(4.0),
+            cpu_usage: 120.5,
+        }),
+        containers: (0..20)
+            .map(|i| ContainerStats {
+                name: format!("service-{i}"),
+                cpu_usage: 3.5,
+                memory_usage: 300_000_000,
+                memory_limit: Some(1_000_000_000),
+                io_read_bytes: 12_345_678,
+                io_write_bytes: 87_654_321,
+            })
+            .collect(),
+        services: ["nginx", "postgresql", "redis", "sshd", "cron"]
+            .iter()
+            .map(|name| ServiceStatus {
+                name: format!("{name}.service"),
+                active_state: "active".to_string(),
+                sub_state: "running".to_string(),
+                restarts: 0,
+                memory_usage: Some(50_000_000),
+            })
+            .collect(),
+        custom_metrics: vec![CustomMetricGroup {
+            name: "queue".to_string(),
+            metrics: vec![
+                CustomMetric {
+                    name: "depth".to_string(),
+                    value: MetricValue::Number(42.0),
+                },
+                CustomMetric {
+                    name: "state".to_string(),
+                    value: MetricValue::Text("draining".to_string()),
+                },
+            ],
+        }],
+        log_files: vec!["/var/log/syslog".to_string()],
+        cores: (0..64)
+            .map(|i| CoreInfo {
+                name: format!("cpu{i}"),
+                usage: (i % 100) as f32 + 0.5,
+                frequency: 3400,
+                times: Some(CpuTimes {
+                    user: 30.0,
+                    system: 5.0,
+                    idle: 60.0,
+                    steal: 5.0,
+                    ..Default::default()
+                }),
+            })
+            .collect(),
+        cpu_times: Some(CpuTimes {
+            user: 30.0,
+            system: 5.0,
+            idle: 60.0,
+            steal: 5.0,
+            ..Default::default()
+        }),
+        sensors: Sensors {
+            temperatures: (0..10)
+                .map(|i| Temperature {
+                    label: format!("coretemp Core {i}"),
+                    current: 55.0,
+                    max: 71.0,
+                    critical: Some(100.0),
+                })
+                .collect(),
+            fans: vec![Fan {
+                label: "nct6775 fan2".to_string(),
+                rpm: 1200,
+            }],
+            power: vec![Power {
+                label: "amdgpu PPT".to_string(),
+                watts: 45.5,
+            }],
+        },
+    };
+    ServerMessage::Info(Box::new(info))
+}
+
+fn codecs(c: &mut Criterion) {
+    let message = snapshot();
+
+    // criterion only measures time, so the sizes are printed once up front
+    for encoding in Encoding::ALL {
+        let size = encoding.encode(&message).unwrap().len();
+        println!("{encoding}: {size} bytes per snapshot");
+    }
+
+    let mut encode = c.benchmark_group("encode");
+    for encoding in Encoding::ALL {
+        encode.bench_with_input(
+            BenchmarkId::from_parameter(encoding),
+            &encoding,
+            |b, encoding| b.iter(|| encoding.encode(black_box(&message)).unwrap()),
+        );
+    }
+    encode.finish();
+
+    let mut decode = c.benchmark_group("decode");
+    for encoding in Encoding::ALL {
+        let bytes = encoding.encode(&message).unwrap();
+        decode.bench_with_input(BenchmarkId::from_parameter(encoding), &bytes, |b, bytes| {
+            b.iter(|| encoding.decode::<ServerMessage>(black_box(bytes)).unwrap())
+        });
+    }
+    decode.finish();
+}
+
+criterion_group!(benches, codecs);
+criterion_main!(benches);
@@ -0,0 +1,221 @@
+use crate::compression::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+/// How the payload of a frame is encoded.
+///
+/// Connections start out as json, a client switches to another encoding by sending ClientRequest::Hello.
+/// Every encoding but json is behind a cargo feature of the same name, all of them are on by default.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// MessagePack with field names, so fields can still be added later
+    MessagePack,
+    /// CBOR with field names
+    Cbor,
+    /// bincode, the smallest and fastest, but fields are positional so both sides need the same version of the packets
+    Bincode,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] = [
+        Encoding::Json,
+        Encoding::MessagePack,
+        Encoding::Cbor,
+        Encoding::Bincode,
+    ];
+
+    /// Whether this build can encode and decode it
+    pub fn is_supported(self) -> bool {
+        match self {
+            Encoding::Json => true,
+            Encoding::MessagePack => cfg!(feature = "msgpack"),
+            Encoding::Cbor => cfg!(feature = "cbor"),
+            Encoding::Bincode => cfg!(feature = "bincode"),
+        }
+    }
+
+    /// The first of the clients preferred encodings this build supports, json if there is none
+    pub fn negotiate(preferred: &[Encoding]) -> Encoding {
+        preferred
+            .iter()
+            .copied()
+            .find(|encoding| encoding.is_supported())
+            .unwrap_or_default()
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Json => Ok(serde_json::to_vec(value)?),
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => rmp_serde::to_vec_named(value).map_err(invalid_data),
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(value, &mut bytes).map_err(invalid_data)?;
+                Ok(bytes)
+            }
+            #[cfg(feature = "bincode")]
+            Encoding::Bincode => bincode::serialize(value).map_err(invalid_data),
+            #[allow(unreachable_patterns)]
+            unsupported => Err(unsupported.unsupported()),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> io::Result<T> {
+        match self {
+            Encoding::Json => Ok(serde_json::from_slice(bytes)?),
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => rmp_serde::from_slice(bytes).map_err(invalid_data),
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => ciborium::from_reader(bytes).map_err(invalid_data),
+            #[cfg(feature = "bincode")]
+            Encoding::Bincode => bincode::deserialize(bytes).map_err(invalid_data),
+            #[allow(unreachable_patterns)]
+            unsupported => Err(unsupported.unsupported()),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn unsupported(self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{self} support was not compiled in."),
+        )
+    }
+}
+
+/// How frames are written and read on one connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codec {
+    pub encoding: Encoding,
+    pub compression: Compression,
+    /// payloads smaller than this many bytes are sent uncompressed, compressing them costs more than it saves
+    pub threshold: usize,
+}
+
+impl Codec {
+    pub const DEFAULT_THRESHOLD: usize = 1024;
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Encoding::Json.into()
+    }
+}
+
+impl From<Encoding> for Codec {
+    fn from(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            compression: Compression::None,
+            threshold: Codec::DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn invalid_data(e: impl Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Encoding::Json => "json",
+            Encoding::MessagePack => "msgpack",
+            Encoding::Cbor => "cbor",
+            Encoding::Bincode => "bincode",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.to_string() == s.to_lowercase())
+            .ok_or_else(|| {
+                format!("Unknown encoding {s}, expected json, msgpack, cbor or bincode.")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_info::CoreInfo;
+    use crate::custom_metric::{CustomMetric, CustomMetricGroup, MetricValue};
+    use crate::disk_info::DiskInfo;
+    use crate::server_info_packet::ServerInfo;
+
+    #[test]
+    fn every_encoding_round_trips_a_server_info() {
+        let info = ServerInfo {
+            host_name: "box".to_string(),
+            total_cpus: 2,
+            cores: vec![
+                CoreInfo {
+                    name: "cpu0".to_string(),
+                    usage: 10.0,
+                    ..Default::default()
+                },
+                CoreInfo {
+                    name: "cpu1".to_string(),
+                    usage: 15.0,
+                    ..Default::default()
+                },
+            ],
+            avg_cpu_usage: 12.5,
+            disks: vec![DiskInfo {
+                mount_point: "/".to_string(),
+                total_space: 100,
+                ..Default::default()
+            }],
+            custom_metrics: vec![CustomMetricGroup {
+                name: "queue".to_string(),
+                metrics: vec![
+                    CustomMetric {
+                        name: "depth".to_string(),
+                        value: MetricValue::Number(3.0),
+                    },
+                    CustomMetric {
+                        name: "state".to_string(),
+                        value: MetricValue::Text("ok".to_string()),
+                    },
+                ],
+            }],
+            ..Default::default()
+        };
+
+        for encoding in Encoding::ALL {
+            let bytes = encoding.encode(&info).unwrap();
+            let decoded: ServerInfo = encoding.decode(&bytes).unwrap();
+            assert_eq!(decoded.host_name, "box", "{encoding}");
+            assert_eq!(decoded.cores, info.cores, "{encoding}");
+            assert_eq!(decoded.disks[0].total_space, 100, "{encoding}");
+            assert_eq!(
+                decoded.custom_metrics[0].metrics[1].value,
+                MetricValue::Text("ok".to_string()),
+                "{encoding}"
+            );
+        }
+    }
+
+    #[test]
+    fn negotiation_falls_back_to_json() {
+        assert_eq!(
+            Encoding::negotiate(&[Encoding::Bincode, Encoding::Json]),
+            Encoding::Bincode
+        );
+        assert_eq!(Encoding::negotiate(&[]), Encoding::Json);
+        assert_eq!("MsgPack".parse::<Encoding>(), Ok(Encoding::MessagePack));
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+/// How frames above the compression threshold are compressed.
+///
+/// Connections start out uncompressed, a client asks for compression in ClientRequest::Hello.
+/// Deflate and zstd are behind cargo features of the same name, both are on by default.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum Compression {
+    #[default]
+    None,
+    Deflate,
+    /// smaller and faster than deflate, but links the zstd C library
+    Zstd,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Deflate, Compression::Zstd];
+
+    /// Whether this build can compress and decompress it
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Deflate => cfg!(feature = "deflate"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// The first of the clients preferred compressions this build supports, none if there is none
+    pub fn negotiate(preferred: &[Compression]) -> Compression {
+        preferred
+            .iter()
+            .copied()
+            .find(|compression| compression.is_supported())
+            .unwrap_or_default()
+    }
+
+    pub fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(bytes, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[allow(unreachable_patterns)]
+            unsupported => Err(unsupported.unsupported()),
+        }
+    }
+
+    /// Decompresses a frame, failing when it would grow past MAX_FRAME_SIZE
+    pub fn decompress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                use std::io::Read;
+
+                let mut decompressed = vec![];
+                flate2::read::DeflateDecoder::new(bytes)
+                    .take(crate::protocol::MAX_FRAME_SIZE as u64 + 1)
+                    .read_to_end(&mut decompressed)?;
+                if decompressed.len() > crate::protocol::MAX_FRAME_SIZE as usize {
+                    return Err(too_large());
+                }
+                Ok(decompressed)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                zstd::bulk::decompress(bytes, crate::protocol::MAX_FRAME_SIZE as usize)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            }
+            #[allow(unreachable_patterns)]
+            unsupported => Err(unsupported.unsupported()),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn unsupported(self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{self} support was not compiled in."),
+        )
+    }
+}
+
+#[allow(dead_code)]
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Frame is too large once decompressed.",
+    )
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Deflate => "deflate",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| compression.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("Unknown compression {s}, expected none, deflate or zstd."))
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Limits and usage of the cgroup the server itself runs in, e.g. its docker container.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct CgroupInfo {
+    /// cgroup version, 1 or 2
+    pub version: u8,
+    /// memory limit in bytes, none when the cgroup is unlimited
+    pub memory_limit: Option<u64>,
+    pub memory_usage: u64,
+    /// cpu limit in cores, none when the cgroup is unlimited
+    pub cpu_limit: Option<f64>,
+    /// cpu usage in percent of a single core
+    pub cpu_usage: f32,
+}
+
+/// Resource usage of a single container found by enumerating sibling cgroups.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct ContainerStats {
+    pub name: String,
+    /// cpu usage in percent of a single core
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+    pub memory_limit: Option<u64>,
+    pub io_read_bytes: u64,
+    pub io_write_bytes: u64,
+}
+
+impl fmt::Display for CgroupInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cgroup v{}: memory {} B",
+            self.version, self.memory_usage
+        )?;
+        if let Some(limit) = self.memory_limit {
+            write!(f, "/{limit} B")?;
+        }
+        write!(f, ", cpu {:.2} %", self.cpu_usage)?;
+        if let Some(limit) = self.cpu_limit {
+            write!(f, " of {limit:.2} cores")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ContainerStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: cpu {:.2} %, memory {} B",
+            self.name, self.cpu_usage, self.memory_usage
+        )?;
+        if let Some(limit) = self.memory_limit {
+            write!(f, "/{limit} B")?;
+        }
+        write!(f, ", io {}/{} B", self.io_read_bytes, self.io_write_bytes)
+    }
+}
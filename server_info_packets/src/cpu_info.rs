@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Usage and clock of a single core.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct CoreInfo {
+    /// e.g. "cpu0"
+    pub name: String,
+    /// in percent
+    pub usage: f32,
+    /// current clock in MHz
+    pub frequency: u64,
+    /// where the time went since the previous info, none when the server can not tell (anything but Linux)
+    pub times: Option<CpuTimes>,
+}
+
+/// How a cpu spent its time since the previous info, each in percent, from /proc/stat.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+pub struct CpuTimes {
+    pub user: f32,
+    /// user time of processes with a positive nice value
+    pub nice: f32,
+    pub system: f32,
+    pub idle: f32,
+    /// idle while waiting for disk or network io
+    pub iowait: f32,
+    pub irq: f32,
+    pub softirq: f32,
+    /// taken by the hypervisor for other guests, high steal on a vm means noisy neighbours rather than real load
+    pub steal: f32,
+}
+
+impl fmt::Display for CoreInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.1} % at {} MHz",
+            self.name, self.usage, self.frequency
+        )?;
+        if let Some(times) = &self.times {
+            write!(f, " ({times})")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CpuTimes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "user {:.1} %, nice {:.1} %, system {:.1} %, iowait {:.1} %, irq {:.1} %, softirq {:.1} %, steal {:.1} %",
+            self.user, self.nice, self.system, self.iowait, self.irq, self.softirq, self.steal
+        )
+    }
+}
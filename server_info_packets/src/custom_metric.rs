@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// The metrics reported by one custom collector, e.g. a site specific script.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct CustomMetricGroup {
+    /// name of the collector the metrics came from
+    pub name: String,
+    pub metrics: Vec<CustomMetric>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomMetric {
+    pub name: String,
+    pub value: MetricValue,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MetricValue {
+    Number(f64),
+    Text(String),
+}
+
+impl MetricValue {
+    /// Numbers stay numbers, anything else is kept as text
+    pub fn parse(value: &str) -> Self {
+        match value.trim().parse::<f64>() {
+            Ok(number) => MetricValue::Number(number),
+            Err(_) => MetricValue::Text(value.trim().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricValue::Number(number) => write!(f, "{number}"),
+            MetricValue::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+impl fmt::Display for CustomMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)
+    }
+}
@@ -0,0 +1,196 @@
+use crate::container_info::{CgroupInfo, ContainerStats};
+use crate::cpu_info::{CoreInfo, CpuTimes};
+use crate::custom_metric::CustomMetricGroup;
+use crate::disk_info::DiskInfo;
+use crate::network_info::NetworkInterfaceInfo;
+use crate::protocol::ServerMessage;
+use crate::sensor_info::Sensors;
+use crate::server_info_packet::ServerInfo;
+use crate::service_info::ServiceStatus;
+use serde::{Deserialize, Serialize};
+
+/// Declares InfoChange with a variant per field of ServerInfo, along with diff and apply
+macro_rules! info_changes {
+    ($($variant:ident($field:ident: $type:ty)),* $(,)?) => {
+        /// A field of a ServerInfo with its new value.
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        pub enum InfoChange {
+            $($variant($type),)*
+        }
+
+        impl InfoChange {
+            pub fn apply(self, info: &mut ServerInfo) {
+                match self {
+                    $(InfoChange::$variant(value) => info.$field = value,)*
+                }
+            }
+        }
+
+        /// The fields that differ between old and new, with their values in new
+        pub fn diff(old: &ServerInfo, new: &ServerInfo) -> Vec<InfoChange> {
+            let mut changes = vec![];
+            $(
+                if old.$field != new.$field {
+                    changes.push(InfoChange::$variant(new.$field.clone()));
+                }
+            )*
+            changes
+        }
+    };
+}
+
+info_changes! {
+    Date(date: i64),
+    Disks(disks: Vec<DiskInfo>),
+    NetInterfaces(net_interfaces: Vec<NetworkInterfaceInfo>),
+    TotalRam(total_ram: u64),
+    UsedMemory(used_memory: u64),
+    SystemName(system_name: String),
+    KernelVersion(kernel_version: String),
+    OsVersion(os_version: String),
+    HostName(host_name: String),
+    TotalCpus(total_cpus: usize),
+    AvgCpuUsage(avg_cpu_usage: f32),
+    Cgroup(cgroup: Option<CgroupInfo>),
+    Containers(containers: Vec<ContainerStats>),
+    Services(services: Vec<ServiceStatus>),
+    CustomMetrics(custom_metrics: Vec<CustomMetricGroup>),
+    LogFiles(log_files: Vec<String>),
+    Cores(cores: Vec<CoreInfo>),
+    CpuTimes(cpu_times: Option<CpuTimes>),
+    Sensors(sensors: Sensors),
+}
+
+/// Server side of delta updates, turns the infos sent to one client into a Snapshot followed by Deltas.
+#[derive(Default)]
+pub struct DeltaEncoder {
+    seq: u64,
+    last: Option<ServerInfo>,
+}
+
+impl DeltaEncoder {
+    pub fn message(&mut self, info: ServerInfo) -> ServerMessage {
+        self.seq += 1;
+        let seq = self.seq;
+        let message = match &self.last {
+            Some(last) => ServerMessage::Delta {
+                seq,
+                changes: diff(last, &info),
+            },
+            None => ServerMessage::Snapshot {
+                seq,
+                info: Box::new(info.clone()),
+            },
+        };
+        self.last = Some(info);
+        message
+    }
+
+    /// Makes the next message a full Snapshot
+    pub fn resync(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Client side of delta updates, keeps the info the next Delta applies to.
+#[derive(Default)]
+pub struct DeltaDecoder {
+    /// None until the first Snapshot, and again after a gap
+    seq: Option<u64>,
+    info: ServerInfo,
+}
+
+impl DeltaDecoder {
+    pub fn snapshot(&mut self, seq: u64, info: ServerInfo) -> ServerInfo {
+        self.seq = Some(seq);
+        self.info = info;
+        self.info.clone()
+    }
+
+    /// Returns the updated info, or None when the delta does not follow the last message and a resync is needed
+    pub fn delta(&mut self, seq: u64, changes: Vec<InfoChange>) -> Option<ServerInfo> {
+        if self.seq.map(|last| last + 1) != Some(seq) {
+            self.seq = None;
+            return None;
+        }
+
+        self.seq = Some(seq);
+        for change in changes {
+            change.apply(&mut self.info);
+        }
+        Some(self.info.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Encoding;
+
+    fn info(avg_cpu_usage: f32) -> ServerInfo {
+        ServerInfo {
+            host_name: "box".to_string(),
+            kernel_version: "6.8.0-45-generic".to_string(),
+            total_ram: 32_000_000_000,
+            avg_cpu_usage,
+            cgroup: Some(CgroupInfo::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn deltas_only_carry_changed_fields() {
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+
+        let snapshot = encoder.message(info(10.0));
+        let ServerMessage::Snapshot { seq, info: first } = snapshot.clone() else {
+            panic!("unexpected message {snapshot:?}");
+        };
+        assert_eq!(decoder.snapshot(seq, *first).avg_cpu_usage, 10.0);
+
+        let mut second = info(20.0);
+        second.cgroup = None;
+        let delta = encoder.message(second);
+        let ServerMessage::Delta { seq, changes } = delta.clone() else {
+            panic!("unexpected message {delta:?}");
+        };
+        assert_eq!(
+            changes,
+            vec![InfoChange::AvgCpuUsage(20.0), InfoChange::Cgroup(None)]
+        );
+        for encoding in Encoding::ALL {
+            let delta_size = encoding.encode(&delta).unwrap().len();
+            let snapshot_size = encoding.encode(&snapshot).unwrap().len();
+            assert!(delta_size < snapshot_size / 2, "{encoding}");
+        }
+
+        let decoded = decoder.delta(seq, changes).unwrap();
+        assert_eq!(decoded.avg_cpu_usage, 20.0);
+        assert_eq!(decoded.cgroup, None);
+        assert_eq!(decoded.host_name, "box");
+    }
+
+    #[test]
+    fn gaps_need_a_resync() {
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        assert!(decoder.delta(1, vec![]).is_none());
+
+        if let ServerMessage::Snapshot { seq, info } = encoder.message(info(10.0)) {
+            decoder.snapshot(seq, *info);
+        }
+        // the client missed this one
+        encoder.message(info(20.0));
+        let ServerMessage::Delta { seq, changes } = encoder.message(info(30.0)) else {
+            panic!("expected a delta");
+        };
+        assert!(decoder.delta(seq, changes).is_none());
+
+        encoder.resync();
+        assert!(matches!(
+            encoder.message(info(40.0)),
+            ServerMessage::Snapshot { seq: 4, .. }
+        ));
+    }
+}
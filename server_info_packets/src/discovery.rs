@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// The udp port servers listen for probes on, udp and tcp ports are separate so it is the same number as the default tcp port
+pub const DISCOVERY_PORT: u16 = 8111;
+
+/// Probes are padded to this many bytes, servers only answer probes at least as long as their announcement
+/// so they can not be used to amplify traffic towards a spoofed sender
+pub const PROBE_LEN: usize = 1024;
+
+/// Bumped whenever the protocol changes in a way older clients or servers can not follow
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Datagrams sent to find servers on the local network, each one is a single json encoded message.
+///
+/// Clients broadcast a Probe to DISCOVERY_PORT, every server that receives it answers the sender with an Announcement.
+/// Probes are sent as DiscoveryMessage::probe, padded to PROBE_LEN.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DiscoveryMessage {
+    Probe,
+    Announcement(Announcement),
+}
+
+impl DiscoveryMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Discovery messages always serialize.")
+    }
+
+    /// A probe padded with trailing whitespace to PROBE_LEN, which json ignores
+    pub fn probe() -> Vec<u8> {
+        let mut probe = DiscoveryMessage::Probe.encode();
+        probe.resize(PROBE_LEN.max(probe.len()), b' ');
+        probe
+    }
+
+    /// None for anything that is not a discovery message, other programs may use the port too
+    pub fn decode(datagram: &[u8]) -> Option<Self> {
+        serde_json::from_slice(datagram).ok()
+    }
+}
+
+/// What a server tells clients looking for servers.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Announcement {
+    pub host_name: String,
+    /// the tcp port clients connect to, the address is the one the announcement came from
+    pub port: u16,
+    pub protocol_version: u32,
+    /// whether the server asks for a token
+    pub auth: bool,
+    /// whether it is a server_info_hub_rs, serving the infos of other servers
+    pub hub: bool,
+}
+
+impl Announcement {
+    /// Whether a client of this version can talk to the server
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION
+    }
+}
+
+impl fmt::Display for Announcement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.host_name)?;
+        if self.hub {
+            write!(f, " (hub)")?;
+        }
+        if self.auth {
+            write!(f, ", token required")?;
+        }
+        if !self.is_compatible() {
+            write!(
+                f,
+                ", incompatible protocol version {}",
+                self.protocol_version
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct DiskInfo {
+    /// device name, e.g. "/dev/sda1"
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub total_space: u64,
+    pub available_space: u64,
+    pub is_removable: bool,
+}
+
+impl DiskInfo {
+    pub fn used_space(&self) -> u64 {
+        self.total_space.saturating_sub(self.available_space)
+    }
+}
+
+impl fmt::Display for DiskInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) mounted on {}: {}/{} B available",
+            self.name, self.file_system, self.mount_point, self.available_space, self.total_space
+        )?;
+        if self.is_removable {
+            f.write_str(", removable")?;
+        }
+        Ok(())
+    }
+}
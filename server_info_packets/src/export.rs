@@ -0,0 +1,272 @@
+use crate::server_info_packet::ServerInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// What infos are exported as, see export.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// a header row of column names and a row per info
+    #[default]
+    Csv,
+    /// a json object of the columns per line
+    Ndjson,
+}
+
+/// Flattens infos to columns and writes them in format, oldest first.
+///
+/// Columns are date, host_name, avg_cpu_usage, used_memory, total_ram, cpu.<core> (usage in %) and cpu.<core>.mhz,
+/// cpu.user, .system, .iowait, .steal and the other times of the whole machine (in %), disk.<mount point>.used and .total (bytes), and net.<interface>.rx and .tx (bytes since the previous refresh).
+/// Only the columns named in columns are exported, or everything when it is empty,
+/// a name also selects the columns below it, e.g. "disk" every disk and "net.eth0" both directions of eth0.
+pub fn export(infos: &[ServerInfo], format: ExportFormat, columns: &[String]) -> String {
+    let rows: Vec<Vec<(String, Value)>> = infos
+        .iter()
+        .map(|info| {
+            flatten(info)
+                .into_iter()
+                .filter(|(name, _)| is_selected(name, columns))
+                .collect()
+        })
+        .collect();
+
+    match format {
+        ExportFormat::Csv => to_csv(&rows),
+        ExportFormat::Ndjson => rows
+            .into_iter()
+            .map(|row| Value::Object(row.into_iter().collect::<Map<_, _>>()).to_string() + "\n")
+            .collect(),
+    }
+}
+
+/// The columns of one info, in the order they are exported
+fn flatten(info: &ServerInfo) -> Vec<(String, Value)> {
+    let mut columns = vec![
+        ("date".to_string(), info.date.into()),
+        ("host_name".to_string(), info.host_name.as_str().into()),
+        ("avg_cpu_usage".to_string(), float(info.avg_cpu_usage)),
+        ("used_memory".to_string(), info.used_memory.into()),
+        ("total_ram".to_string(), info.total_ram.into()),
+    ];
+    for (index, core) in info.cores.iter().enumerate() {
+        columns.push((format!("cpu.{index}"), float(core.usage)));
+        columns.push((format!("cpu.{index}.mhz"), core.frequency.into()));
+    }
+    if let Some(times) = &info.cpu_times {
+        for (name, percent) in [
+            ("user", times.user),
+            ("nice", times.nice),
+            ("system", times.system),
+            ("idle", times.idle),
+            ("iowait", times.iowait),
+            ("irq", times.irq),
+            ("softirq", times.softirq),
+            ("steal", times.steal),
+        ] {
+            columns.push((format!("cpu.{name}"), float(percent)));
+        }
+    }
+    for disk in &info.disks {
+        let used = disk.total_space.saturating_sub(disk.available_space);
+        columns.push((format!("disk.{}.used", disk.mount_point), used.into()));
+        columns.push((
+            format!("disk.{}.total", disk.mount_point),
+            disk.total_space.into(),
+        ));
+    }
+    for interface in &info.net_interfaces {
+        columns.push((
+            format!("net.{}.rx", interface.name),
+            interface.received.into(),
+        ));
+        columns.push((
+            format!("net.{}.tx", interface.name),
+            interface.transmitted.into(),
+        ));
+    }
+    columns
+}
+
+/// The shortest decimal that reads back as value, 1.96 rather than the 1.9600000381 a widened f32 prints as
+fn float(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .map_or(Value::Null, Value::from)
+}
+
+fn is_selected(name: &str, columns: &[String]) -> bool {
+    columns.is_empty()
+        || columns.iter().any(|column| {
+            name == column
+                || name
+                    .strip_prefix(column.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+}
+
+/// Every column that appears in any row gets a header, in the order they first appear, rows without one leave the cell empty
+fn to_csv(rows: &[Vec<(String, Value)>]) -> String {
+    let mut header: Vec<&str> = vec![];
+    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    for row in rows {
+        for (name, _) in row {
+            indexes.entry(name).or_insert_with(|| {
+                header.push(name);
+                header.len() - 1
+            });
+        }
+    }
+
+    let mut csv = csv_line(header.iter().map(|name| name.to_string()));
+    for row in rows {
+        let mut cells = vec![String::new(); header.len()];
+        for (name, value) in row {
+            cells[indexes[name.as_str()]] = match value {
+                Value::String(text) => text.to_string(),
+                value => value.to_string(),
+            };
+        }
+        csv.push_str(&csv_line(cells.into_iter()));
+    }
+    csv
+}
+
+/// Cells joined by commas, quoting those that contain a comma, quote or line break
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    let mut line = cells
+        .map(|cell| match cell.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", cell.replace('"', "\"\"")),
+            false => cell,
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            _ => Err(format!(
+                "Unknown export format {s}, expected csv or ndjson."
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_info::{CoreInfo, CpuTimes};
+    use crate::disk_info::DiskInfo;
+    use crate::network_info::NetworkInterfaceInfo;
+    use crate::protocol::{MAX_EXPORT_LEN, MAX_EXPORT_SIZE};
+
+    fn info(date: i64, mounts: &[&str]) -> ServerInfo {
+        ServerInfo {
+            date,
+            host_name: "web, 1".to_string(),
+            avg_cpu_usage: 1.96,
+            cores: vec![CoreInfo {
+                name: "cpu0".to_string(),
+                usage: 12.5,
+                frequency: 2400,
+                times: None,
+            }],
+            cpu_times: Some(CpuTimes {
+                steal: 30.0,
+                ..Default::default()
+            }),
+            disks: mounts
+                .iter()
+                .map(|mount| DiskInfo {
+                    mount_point: mount.to_string(),
+                    total_space: 100,
+                    available_space: 40,
+                    ..Default::default()
+                })
+                .collect(),
+            net_interfaces: vec![NetworkInterfaceInfo {
+                name: "eth0".to_string(),
+                received: 1,
+                transmitted: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exports_flattened_columns_as_csv() {
+        let infos = [info(1, &["/"]), info(2, &["/", "/data"])];
+        let columns = [
+            "date",
+            "host_name",
+            "cpu.0",
+            "cpu.steal",
+            "disk",
+            "net.eth0.tx",
+        ]
+        .map(String::from);
+        assert_eq!(
+            export(&infos, ExportFormat::Csv, &columns),
+            "date,host_name,cpu.0,cpu.0.mhz,cpu.steal,disk./.used,disk./.total,net.eth0.tx,disk./data.used,disk./data.total\n\
+             1,\"web, 1\",12.5,2400,30.0,60,100,2,,\n\
+             2,\"web, 1\",12.5,2400,30.0,60,100,2,60,100\n"
+        );
+    }
+
+    #[test]
+    fn exports_a_json_object_per_line() {
+        let infos = [info(1, &[]), info(2, &[])];
+        let columns = ["date", "avg_cpu_usage", "net"].map(String::from);
+        assert_eq!(
+            export(&infos, ExportFormat::Ndjson, &columns),
+            "{\"avg_cpu_usage\":1.96,\"date\":1,\"net.eth0.rx\":1,\"net.eth0.tx\":2}\n\
+             {\"avg_cpu_usage\":1.96,\"date\":2,\"net.eth0.rx\":1,\"net.eth0.tx\":2}\n"
+        );
+        assert_eq!(export(&infos, ExportFormat::Csv, &[]).lines().count(), 3);
+    }
+
+    #[test]
+    fn exports_of_large_hosts_fit_in_a_message() {
+        let mut large = info(1, &["/", "/boot", "/data", "/var/lib/docker"]);
+        large.host_name = "build-runner-42.internal".to_string();
+        large.cores = (0..64)
+            .map(|index| CoreInfo {
+                name: format!("cpu{index}"),
+                usage: 12.345,
+                frequency: 3400,
+                times: None,
+            })
+            .collect();
+        let infos = vec![large; MAX_EXPORT_LEN];
+        assert!(export(&infos, ExportFormat::Ndjson, &[]).len() < MAX_EXPORT_SIZE);
+    }
+
+    #[test]
+    fn selects_whole_names_only() {
+        let columns = ["disk./", "cpu.1"].map(String::from);
+        assert!(is_selected("disk./.used", &columns));
+        assert!(!is_selected("disk./data.used", &columns));
+        assert!(is_selected("cpu.1", &columns));
+        assert!(!is_selected("cpu.10", &columns));
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// One of the servers a hub collects from, as listed by ClientRequest::ListHosts.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct HostStatus {
+    /// what ClientRequest::SelectHost takes
+    pub name: String,
+    /// empty for a server listing itself
+    pub address: String,
+    pub connected: bool,
+    /// date of the latest info received from the host, 0 before the first one
+    pub last_seen: i64,
+    /// why the connection to the host broke, none while connected
+    pub error: Option<String>,
+}
+
+impl fmt::Display for HostStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.address.is_empty() {
+            write!(f, " ({})", self.address)?;
+        }
+        write!(f, ": ")?;
+        match (self.connected, &self.error) {
+            (true, _) => write!(f, "connected, last seen {}", self.last_seen),
+            (false, Some(error)) => write!(f, "disconnected, {error}"),
+            (false, None) => write!(f, "connecting"),
+        }
+    }
+}
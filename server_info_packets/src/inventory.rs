@@ -0,0 +1,159 @@
+use chrono::TimeZone;
+use chrono_tz::US::Pacific;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+use std::net::IpAddr;
+
+/// The hardware of a host, answers ClientRequest::Inventory.
+///
+/// Unlike a ServerInfo this is gathered once per request, what the server can not read is left empty.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Inventory {
+    /// unix time in seconds the inventory was taken at
+    pub date: i64,
+    pub host_name: String,
+    pub os_version: String,
+    pub kernel_version: String,
+    pub cpu: CpuInventory,
+    /// in bytes
+    pub total_memory: u64,
+    /// in bytes
+    pub total_swap: u64,
+    /// from the firmware tables, which only root can read
+    pub memory_modules: Vec<MemoryModule>,
+    pub block_devices: Vec<BlockDevice>,
+    pub nics: Vec<Nic>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct CpuInventory {
+    /// e.g. "Intel(R) Xeon(R) CPU E5-2690 v4 @ 2.60GHz"
+    pub brand: String,
+    /// e.g. "GenuineIntel"
+    pub vendor: String,
+    /// none when the server can not tell
+    pub physical_cores: Option<usize>,
+    /// cores including hyperthreads
+    pub logical_cores: usize,
+}
+
+/// A populated memory slot.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct MemoryModule {
+    /// the slot, e.g. "DIMM_A1"
+    pub locator: String,
+    /// in bytes
+    pub size: u64,
+    /// in MT/s, none when the firmware does not say
+    pub speed: Option<u32>,
+}
+
+/// A disk, without partitions and virtual devices like loop or zram.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct BlockDevice {
+    /// e.g. "sda" or "nvme0n1"
+    pub name: String,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// in bytes
+    pub size: u64,
+    /// a spinning disk rather than flash
+    pub rotational: bool,
+}
+
+/// A network interface.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Nic {
+    pub name: String,
+    /// e.g. "52:54:00:12:34:56"
+    pub mac: Option<String>,
+    /// in Mbit/s, none while the link is down or for virtual interfaces
+    pub speed: Option<u32>,
+    pub mtu: Option<u32>,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// Bytes as GB like the rest of the clients
+fn gigabytes(bytes: u64) -> String {
+    format!("{:.2} GB", bytes as f64 / 1000000000.0)
+}
+
+impl fmt::Display for Inventory {
+    /// A page meant to be printed or pasted into a ticket
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Inventory of {}", self.host_name)?;
+        match Pacific.timestamp_opt(self.date, 0).single() {
+            Some(date) => writeln!(f, "Taken at: {date}")?,
+            None => writeln!(f, "Taken at: {}", self.date)?,
+        }
+        writeln!(f, "OS Version: {}", self.os_version)?;
+        writeln!(f, "Kernel Version: {}", self.kernel_version)?;
+
+        writeln!(f)?;
+        writeln!(f, "CPU: {}", self.cpu.brand)?;
+        writeln!(f, "  Vendor: {}", self.cpu.vendor)?;
+        match self.cpu.physical_cores {
+            Some(physical) => writeln!(
+                f,
+                "  Cores: {physical} physical, {} logical",
+                self.cpu.logical_cores
+            )?,
+            None => writeln!(f, "  Cores: {} logical", self.cpu.logical_cores)?,
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Memory: {}, swap {}",
+            gigabytes(self.total_memory),
+            gigabytes(self.total_swap)
+        )?;
+        for module in &self.memory_modules {
+            write!(f, "  {}: {}", module.locator, gigabytes(module.size))?;
+            if let Some(speed) = module.speed {
+                write!(f, " at {speed} MT/s")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Block Devices:")?;
+        for device in &self.block_devices {
+            let kind = match device.rotational {
+                true => "rotational",
+                false => "solid state",
+            };
+            writeln!(
+                f,
+                "  {}: {} {kind}, model {}, serial {}",
+                device.name,
+                gigabytes(device.size),
+                device.model.as_deref().unwrap_or("unknown"),
+                device.serial.as_deref().unwrap_or("unknown")
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Network Interfaces:")?;
+        for nic in &self.nics {
+            write!(
+                f,
+                "  {}: mac {}",
+                nic.name,
+                nic.mac.as_deref().unwrap_or("none")
+            )?;
+            if let Some(speed) = nic.speed {
+                write!(f, ", {speed} Mbit/s")?;
+            }
+            if let Some(mtu) = nic.mtu {
+                write!(f, ", mtu {mtu}")?;
+            }
+            writeln!(f)?;
+            for address in &nic.addresses {
+                writeln!(f, "    {address}")?;
+            }
+        }
+        Ok(())
+    }
+}
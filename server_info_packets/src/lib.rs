@@ -1,4 +1,19 @@
+pub mod codec;
+pub mod compression;
+pub mod container_info;
+pub mod cpu_info;
+pub mod custom_metric;
+pub mod delta;
+pub mod discovery;
+pub mod disk_info;
+pub mod export;
+pub mod host_status;
+pub mod inventory;
+pub mod network_info;
+pub mod protocol;
+pub mod sensor_info;
 pub mod server_info_packet;
+pub mod service_info;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+use std::net::IpAddr;
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct NetworkInterfaceInfo {
+    pub name: String,
+    /// bytes received since the servers previous network refresh
+    pub received: u64,
+    /// bytes transmitted since the servers previous network refresh
+    pub transmitted: u64,
+    /// e.g. "52:54:00:12:34:56", none for interfaces without one like loopback
+    pub mac: Option<String>,
+    pub mtu: Option<u32>,
+    /// whether the interface is up and has a carrier, none when the server can not tell (anything but Linux)
+    pub up: Option<bool>,
+    /// in Mbit/s, none while the link is down or for virtual interfaces
+    pub speed: Option<u32>,
+    pub addresses: Vec<IpAddr>,
+    /// counted since the server booted, like the drops
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub kind: InterfaceKind,
+}
+
+/// What is behind an interface, so clients can hide the ones that are not a network card.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterfaceKind {
+    /// a network card
+    #[default]
+    Physical,
+    Loopback,
+    /// without hardware behind it, e.g. veth, bridges like docker0, tun and vpn interfaces
+    Virtual,
+}
+
+impl fmt::Display for NetworkInterfaceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}/{} B", self.name, self.received, self.transmitted)?;
+        match self.up {
+            Some(true) => f.write_str(", up")?,
+            Some(false) => f.write_str(", down")?,
+            None => {}
+        }
+        if let Some(speed) = self.speed {
+            write!(f, ", {speed} Mbit/s")?;
+        }
+        if let Some(mtu) = self.mtu {
+            write!(f, ", mtu {mtu}")?;
+        }
+        if let Some(mac) = &self.mac {
+            write!(f, ", mac {mac}")?;
+        }
+        for address in &self.addresses {
+            write!(f, ", {address}")?;
+        }
+        if self.rx_errors + self.tx_errors + self.rx_dropped + self.tx_dropped > 0 {
+            write!(
+                f,
+                ", errors {}/{}, dropped {}/{}",
+                self.rx_errors, self.tx_errors, self.rx_dropped, self.tx_dropped
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,569 @@
+use crate::codec::{Codec, Encoding};
+use crate::compression::Compression;
+use crate::delta::InfoChange;
+use crate::export::ExportFormat;
+use crate::host_status::HostStatus;
+use crate::inventory::Inventory;
+use crate::server_info_packet::ServerInfo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// Frames larger than this are rejected instead of allocating whatever length a broken peer sends
+pub const MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Pushed to clients send a ClientRequest::Heartbeat, and the server a ServerMessage::Heartbeat, after being quiet this long
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A pushed to peer that sent nothing for this long is considered dead
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Pushing is limited to this rate, however short the interval a client asks for
+pub const MIN_PUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longer intervals a client asks for are shortened to this, so the next info is always a time that can be waited for
+pub const MAX_PUSH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Agents send at most this many infos per ClientRequest::Report, so it stays well below MAX_FRAME_SIZE
+pub const MAX_REPORT_LEN: usize = 100;
+
+/// A ServerMessage::History holds at most this many infos, the newest ones, so it stays well below MAX_FRAME_SIZE
+pub const MAX_HISTORY_LEN: usize = 1000;
+
+/// A ServerMessage::Export holds at most this many infos, the newest ones.
+///
+/// A row of a 64 core host is about 3 KB as ndjson, so this stays below MAX_EXPORT_SIZE unless the host is much larger.
+pub const MAX_EXPORT_LEN: usize = 2000;
+
+/// Exports larger than this are refused with a ServerMessage::Error, leaving room below MAX_FRAME_SIZE for encoding
+/// the text, which escapes every quote of ndjson
+pub const MAX_EXPORT_SIZE: usize = MAX_FRAME_SIZE as usize / 2;
+
+/// Set in the length of a frame whose payload is compressed, frames are never large enough to need this bit
+const COMPRESSED: u32 = 1 << 31;
+
+/// Messages sent from a client to the server, the server answers every request with a ServerMessage::Info,
+/// or with a Snapshot or Delta once the client asked for deltas.
+///
+/// Once a client subscribed with push set, infos are sent every interval instead, and requests are not answered with one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ClientRequest {
+    /// Asks for the next ServerInfo
+    Next,
+    /// Starts streaming new lines of one of the servers configured log files, replacing any earlier tail
+    TailLogs {
+        file: String,
+        filter: Option<LogFilter>,
+    },
+    /// Stops streaming log lines
+    StopLogs,
+    /// Answers ServerMessage::AuthRequired with the token from the servers config
+    Auth { token: String },
+    /// Asks to switch to the first of these encodings and compressions the server supports, answered with ServerMessage::Hello
+    Hello {
+        encodings: Vec<Encoding>,
+        /// compressions to use for large frames, most preferred first
+        #[serde(default)]
+        compressions: Vec<Compression>,
+        /// send a Snapshot and then only Deltas instead of an Info every time
+        #[serde(default)]
+        deltas: bool,
+    },
+    /// Asks for a Snapshot instead of the next Delta, e.g. after a Delta arrived out of sequence
+    Resync,
+    /// Sets which sections of the info to send, and how long the server waits at least between two infos
+    Subscribe {
+        sections: Sections,
+        interval_ms: u64,
+        /// send an info every interval without waiting for requests
+        #[serde(default)]
+        push: bool,
+    },
+    /// Tells the server a pushed to client is still there, answered like Next when not pushing
+    Heartbeat,
+    /// Asks a hub for the servers it collects from, answered with ServerMessage::Hosts
+    ListHosts,
+    /// Asks a hub to send the infos of this host from now on, instead of its first one
+    SelectHost { name: String },
+    /// Asks for the infos kept of the selected host dated from..=to (unix seconds), answered with ServerMessage::History
+    History { from: i64, to: i64 },
+    /// Turns the connection into an agent reporting the infos of the host called name to a hub, answered with ServerMessage::Ack.
+    ///
+    /// Nothing but Reports and Heartbeats may follow, and the hub sends no infos on the connection.
+    Agent { name: String, token: String },
+    /// Infos of an agents host, oldest first, answered with ServerMessage::Ack once the hub stored them
+    Report(Vec<ServerInfo>),
+    /// Asks for the kept infos of the selected host dated from..=to flattened to columns, answered with ServerMessage::Export.
+    ///
+    /// Only the named columns are exported, all of them when empty, see export::export.
+    Export {
+        from: i64,
+        to: i64,
+        format: ExportFormat,
+        columns: Vec<String>,
+    },
+    /// Asks for the hardware of the server, answered with ServerMessage::Inventory
+    Inventory,
+}
+
+/// Grep style filter applied to log lines on the server before they are sent.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LogFilter {
+    /// a regular expression
+    pub pattern: String,
+    pub ignore_case: bool,
+    /// only send lines that do not match, like grep -v
+    pub invert: bool,
+}
+
+/// Sections of a ServerInfo a client displays, the server sends the others empty.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sections {
+    pub disks: bool,
+    pub net_interfaces: bool,
+    pub cpus: bool,
+    /// temperatures, fans and power
+    pub components: bool,
+    /// the cgroup of the server and its sibling containers
+    pub containers: bool,
+    pub services: bool,
+    pub custom_metrics: bool,
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Self {
+            disks: true,
+            net_interfaces: true,
+            cpus: true,
+            components: true,
+            containers: true,
+            services: true,
+            custom_metrics: true,
+        }
+    }
+}
+
+impl Sections {
+    /// Empties the sections of info that are not wanted
+    pub fn strip(&self, info: &mut ServerInfo) {
+        if !self.disks {
+            info.disks.clear();
+        }
+        if !self.net_interfaces {
+            info.net_interfaces.clear();
+        }
+        if !self.cpus {
+            info.cores.clear();
+            info.cpu_times = None;
+        }
+        if !self.components {
+            info.sensors = Default::default();
+        }
+        if !self.containers {
+            info.cgroup = None;
+            info.containers.clear();
+        }
+        if !self.services {
+            info.services.clear();
+        }
+        if !self.custom_metrics {
+            info.custom_metrics.clear();
+        }
+    }
+}
+
+/// Messages sent from the server to a client.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ServerMessage {
+    Info(Box<ServerInfo>),
+    /// New lines of the tailed log file, always sent before the Info of the same turn
+    LogLines {
+        file: String,
+        lines: Vec<String>,
+    },
+    /// A request could not be fulfilled
+    Error(String),
+    /// Sent instead of the first Info when the server has a token configured, the client has to answer with ClientRequest::Auth
+    AuthRequired,
+    /// The server is stopping, nothing follows this
+    ShuttingDown,
+    /// Answers ClientRequest::Hello, still in the old encoding, every frame after it in both directions uses these
+    Hello {
+        encoding: Encoding,
+        compression: Compression,
+    },
+    /// A whole ServerInfo for a client that asked for deltas, numbered like the Deltas that follow it
+    Snapshot {
+        seq: u64,
+        info: Box<ServerInfo>,
+    },
+    /// The fields that changed since the Snapshot or Delta numbered seq - 1
+    Delta {
+        seq: u64,
+        changes: Vec<InfoChange>,
+    },
+    /// Tells a pushed to client the server is still there when its interval is longer than HEARTBEAT_INTERVAL
+    Heartbeat,
+    /// Answers ClientRequest::ListHosts, a server that is not a hub lists only itself
+    Hosts(Vec<HostStatus>),
+    /// Answers ClientRequest::History, oldest first
+    History(Vec<ServerInfo>),
+    /// Answers ClientRequest::Agent and ClientRequest::Report
+    Ack,
+    /// Answers ClientRequest::Export with the csv or ndjson text
+    Export(String),
+    /// Answers ClientRequest::Inventory
+    Inventory(Box<Inventory>),
+}
+
+/// Encodes a message as a frame, a big endian u32 length followed by the encoded message.
+///
+/// Messages of at least codec.threshold bytes are compressed, which is marked by the highest bit of the length.
+pub fn encode_frame<T: Serialize>(codec: impl Into<Codec>, message: &T) -> io::Result<Vec<u8>> {
+    let codec = codec.into();
+    let mut payload = codec.encoding.encode(message)?;
+    let mut length = payload.len() as u32;
+    if codec.compression != Compression::None && payload.len() >= codec.threshold {
+        payload = codec.compression.compress(&payload)?;
+        length = payload.len() as u32 | COMPRESSED;
+    }
+
+    let mut frame = Vec::with_capacity(payload.len() + 4);
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Returns the payload length of a frame from its header, and whether the payload is compressed
+pub fn frame_header(header: [u8; 4]) -> io::Result<(usize, bool)> {
+    let header = u32::from_be_bytes(header);
+    let length = header & !COMPRESSED;
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {length} bytes is too large."),
+        ));
+    }
+    Ok((length as usize, header & COMPRESSED != 0))
+}
+
+/// Decodes the payload of a frame
+pub fn decode_payload<T: DeserializeOwned>(
+    codec: impl Into<Codec>,
+    payload: &[u8],
+    compressed: bool,
+) -> io::Result<T> {
+    let codec = codec.into();
+    if !compressed {
+        return codec.encoding.decode(payload);
+    }
+    if codec.compression == Compression::None {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Received a compressed frame without having agreed on a compression.",
+        ));
+    }
+    codec
+        .encoding
+        .decode(&codec.compression.decompress(payload)?)
+}
+
+/// Writes a message as a single frame
+pub fn write_message<T: Serialize>(
+    writer: &mut impl Write,
+    codec: impl Into<Codec>,
+    message: &T,
+) -> io::Result<()> {
+    writer.write_all(&encode_frame(codec, message)?)?;
+    writer.flush()
+}
+
+/// Reads a single frame written by write_message
+pub fn read_message<T: DeserializeOwned>(
+    reader: &mut impl Read,
+    codec: impl Into<Codec>,
+) -> io::Result<T> {
+    let mut header = [0; 4];
+    reader.read_exact(&mut header)?;
+
+    let (length, compressed) = frame_header(header)?;
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload)?;
+    decode_payload(codec, &payload, compressed)
+}
+
+/// Async version of write_message
+#[cfg(feature = "tokio")]
+pub async fn write_message_async<T: Serialize>(
+    writer: &mut (impl tokio::io::AsyncWrite + Unpin),
+    codec: impl Into<Codec>,
+    message: &T,
+) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    writer.write_all(&encode_frame(codec, message)?).await?;
+    writer.flush().await
+}
+
+/// Async version of read_message
+#[cfg(feature = "tokio")]
+pub async fn read_message_async<T: DeserializeOwned>(
+    reader: &mut (impl tokio::io::AsyncRead + Unpin),
+    codec: impl Into<Codec>,
+) -> io::Result<T> {
+    use tokio::io::AsyncReadExt;
+
+    let mut header = [0; 4];
+    reader.read_exact(&mut header).await?;
+
+    let (length, compressed) = frame_header(header)?;
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload).await?;
+    decode_payload(codec, &payload, compressed)
+}
+
+/// Reads frames through a buffer, so a frame cut off by a timeout, a non blocking read or a cancelled future
+/// is finished by the next read instead of being lost.
+#[derive(Default)]
+pub struct FrameReader {
+    buffer: Vec<u8>,
+}
+
+impl FrameReader {
+    /// Reads the next message, a non blocking reader fails with WouldBlock until one arrived completely
+    pub fn read<T: DeserializeOwned>(
+        &mut self,
+        reader: &mut impl Read,
+        codec: impl Into<Codec>,
+    ) -> io::Result<T> {
+        let codec = codec.into();
+        loop {
+            if let Some(message) = self.take(codec)? {
+                return Ok(message);
+            }
+
+            let mut chunk = [0; 8192];
+            let read = reader.read(&mut chunk)?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// Async version of read, cancel safe so it can race a timer in tokio::select!
+    #[cfg(feature = "tokio")]
+    pub async fn read_async<T: DeserializeOwned>(
+        &mut self,
+        reader: &mut (impl tokio::io::AsyncRead + Unpin),
+        codec: impl Into<Codec>,
+    ) -> io::Result<T> {
+        use tokio::io::AsyncReadExt;
+
+        let codec = codec.into();
+        loop {
+            if let Some(message) = self.take(codec)? {
+                return Ok(message);
+            }
+
+            if reader.read_buf(&mut self.buffer).await? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Takes the first frame out of the buffer once it is complete
+    fn take<T: DeserializeOwned>(&mut self, codec: Codec) -> io::Result<Option<T>> {
+        if self.buffer.len() < 4 {
+            return Ok(None);
+        }
+
+        let header = [
+            self.buffer[0],
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+        ];
+        let (length, compressed) = frame_header(header)?;
+        if self.buffer.len() < 4 + length {
+            return Ok(None);
+        }
+
+        let message = decode_payload(codec, &self.buffer[4..4 + length], compressed);
+        self.buffer.drain(..4 + length);
+        message.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_info::CoreInfo;
+    use std::io::Cursor;
+
+    #[test]
+    fn messages_round_trip_through_frames() {
+        for encoding in Encoding::ALL {
+            let mut buf: Vec<u8> = vec![];
+            let info = ServerInfo {
+                host_name: "box".to_string(),
+                total_cpus: 4,
+                ..Default::default()
+            };
+            write_message(&mut buf, encoding, &ServerMessage::Info(Box::new(info))).unwrap();
+            let request = ClientRequest::TailLogs {
+                file: "/var/log/syslog".to_string(),
+                filter: Some(LogFilter {
+                    pattern: "error".to_string(),
+                    ignore_case: true,
+                    invert: false,
+                }),
+            };
+            write_message(&mut buf, encoding, &request).unwrap();
+
+            let mut reader = Cursor::new(buf);
+            match read_message::<ServerMessage>(&mut reader, encoding).unwrap() {
+                ServerMessage::Info(info) => {
+                    assert_eq!(info.host_name, "box");
+                    assert_eq!(info.total_cpus, 4);
+                }
+                other => panic!("unexpected message {other:?}"),
+            }
+            assert_eq!(
+                read_message::<ClientRequest>(&mut reader, encoding).unwrap(),
+                request
+            );
+        }
+    }
+
+    /// A ServerInfo of a few hundred KiB, like one from a big host with many containers
+    fn large_info() -> ServerInfo {
+        ServerInfo {
+            host_name: "box".to_string(),
+            cores: (0..256)
+                .map(|i| CoreInfo {
+                    name: format!("cpu{i}"),
+                    usage: (i % 100) as f32,
+                    frequency: 3400,
+                    times: None,
+                })
+                .collect(),
+            log_files: (0..2000)
+                .map(|i| format!("/var/log/app/service-{i}.log"))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn large_infos_round_trip_through_every_compression() {
+        let info = large_info();
+        for encoding in Encoding::ALL {
+            for compression in Compression::ALL {
+                let codec = Codec {
+                    encoding,
+                    compression,
+                    threshold: Codec::DEFAULT_THRESHOLD,
+                };
+                let message = ServerMessage::Info(Box::new(info.clone()));
+                let frame = encode_frame(codec, &message).unwrap();
+                let uncompressed = encode_frame(encoding, &message).unwrap();
+                if compression != Compression::None {
+                    assert!(
+                        frame.len() < uncompressed.len() / 4,
+                        "{encoding} {compression}"
+                    );
+                }
+
+                match read_message(&mut Cursor::new(frame), codec).unwrap() {
+                    ServerMessage::Info(decoded) => {
+                        assert_eq!(decoded.cores, info.cores, "{encoding} {compression}");
+                        assert_eq!(decoded.log_files, info.log_files);
+                    }
+                    other => panic!("unexpected message {other:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn small_frames_stay_uncompressed() {
+        let codec = Codec {
+            encoding: Encoding::Json,
+            compression: Compression::Zstd,
+            threshold: Codec::DEFAULT_THRESHOLD,
+        };
+        let frame = encode_frame(codec, &ClientRequest::Next).unwrap();
+        assert_eq!(
+            frame,
+            encode_frame(Encoding::Json, &ClientRequest::Next).unwrap()
+        );
+
+        // a peer that never agreed on compression can not read a compressed frame
+        let message = ServerMessage::Info(Box::new(large_info()));
+        let frame = encode_frame(codec, &message).unwrap();
+        let error =
+            read_message::<ServerMessage>(&mut Cursor::new(frame), Encoding::Json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Hands out at most one byte per read, failing with WouldBlock every other read
+    struct Trickle {
+        bytes: Cursor<Vec<u8>>,
+        blocked: bool,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.blocked = !self.blocked;
+            if self.blocked {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.bytes.read(&mut buf[..1])
+        }
+    }
+
+    #[test]
+    fn frame_reader_survives_interrupted_reads() {
+        let mut bytes = vec![];
+        for total_cpus in 1..=3 {
+            let info = ServerInfo {
+                total_cpus,
+                ..Default::default()
+            };
+            write_message(
+                &mut bytes,
+                Encoding::Json,
+                &ServerMessage::Info(Box::new(info)),
+            )
+            .unwrap();
+        }
+        let mut trickle = Trickle {
+            bytes: Cursor::new(bytes),
+            blocked: false,
+        };
+
+        let mut reader = FrameReader::default();
+        let mut cpus = vec![];
+        loop {
+            match reader.read::<ServerMessage>(&mut trickle, Encoding::Json) {
+                Ok(ServerMessage::Info(info)) => cpus.push(info.total_cpus),
+                Ok(other) => panic!("unexpected message {other:?}"),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => panic!("unexpected error {e}"),
+            }
+        }
+        assert_eq!(cpus, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut reader = Cursor::new(u32::MAX.to_be_bytes().to_vec());
+        let error = read_message::<ClientRequest>(&mut reader, Encoding::Json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Hardware sensors of the server, what the hardware does not have is left empty.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Sensors {
+    pub temperatures: Vec<Temperature>,
+    pub fans: Vec<Fan>,
+    pub power: Vec<Power>,
+}
+
+/// A temperature sensor, e.g. a cpu package or a drive, all in °C.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Temperature {
+    pub label: String,
+    pub current: f32,
+    /// highest temperature seen since the server started
+    pub max: f32,
+    /// where the hardware starts to protect itself, none when the sensor does not say
+    pub critical: Option<f32>,
+}
+
+/// A fan speed from hwmon.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Fan {
+    pub label: String,
+    pub rpm: u64,
+}
+
+/// A power reading from hwmon, e.g. of a gpu or a cpu package.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Power {
+    pub label: String,
+    pub watts: f32,
+}
+
+impl Sensors {
+    pub fn is_empty(&self) -> bool {
+        self.temperatures.is_empty() && self.fans.is_empty() && self.power.is_empty()
+    }
+}
+
+impl Temperature {
+    /// How close the current temperature is to critical, 1 and above is at or past it, none without a critical value
+    pub fn heat(&self) -> Option<f32> {
+        self.critical
+            .filter(|critical| *critical > 0.0)
+            .map(|critical| self.current / critical)
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.1} °C (max {:.1} °C",
+            self.label, self.current, self.max
+        )?;
+        if let Some(critical) = self.critical {
+            write!(f, ", critical {critical:.1} °C")?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for Fan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} RPM", self.label, self.rpm)
+    }
+}
+
+impl fmt::Display for Power {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:.1} W", self.label, self.watts)
+    }
+}
@@ -1,5 +1,12 @@
+use crate::container_info::{CgroupInfo, ContainerStats};
+use crate::cpu_info::{CoreInfo, CpuTimes};
+use crate::custom_metric::CustomMetricGroup;
+use crate::disk_info::DiskInfo;
+use crate::network_info::NetworkInterfaceInfo;
+use crate::sensor_info::Sensors;
+use crate::service_info::ServiceStatus;
+use chrono::DateTime;
 use chrono::TimeZone;
-use chrono::{DateTime, NaiveDateTime, Utc};
 use chrono_tz::Tz;
 use chrono_tz::US::Pacific;
 use serde::ser::SerializeStruct;
@@ -7,12 +14,11 @@ use serde::{Deserialize, Serialize, Serializer};
 use std::fmt;
 use std::fmt::Formatter;
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct ServerInfo {
     pub date: i64,
-    pub disks: Vec<String>,
-    pub net_interfaces: Vec<String>,
-    pub components: Vec<String>,
+    pub disks: Vec<DiskInfo>,
+    pub net_interfaces: Vec<NetworkInterfaceInfo>,
     pub total_ram: u64,
     pub used_memory: u64,
     pub system_name: String,
@@ -20,16 +26,32 @@ pub struct ServerInfo {
     pub os_version: String,
     pub host_name: String,
     pub total_cpus: usize,
-    pub cpus: Vec<String>,
     pub avg_cpu_usage: f32,
+    #[serde(default)]
+    pub cgroup: Option<CgroupInfo>,
+    #[serde(default)]
+    pub containers: Vec<ContainerStats>,
+    #[serde(default)]
+    pub services: Vec<ServiceStatus>,
+    #[serde(default)]
+    pub custom_metrics: Vec<CustomMetricGroup>,
+    /// log files the server allows clients to tail
+    #[serde(default)]
+    pub log_files: Vec<String>,
+    #[serde(default)]
+    pub cores: Vec<CoreInfo>,
+    /// how the whole machine spent its time, none when the server can not tell (anything but Linux)
+    #[serde(default)]
+    pub cpu_times: Option<CpuTimes>,
+    /// temperatures, fans and power
+    #[serde(default)]
+    pub sensors: Sensors,
 }
 
 impl ServerInfo {
     pub fn get_date_time(&self) -> DateTime<Tz> {
         // pacific time zone conversion
-        let utc =
-            DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(self.date, 0), Utc).naive_utc();
-        Pacific.from_utc_datetime(&utc)
+        Pacific.timestamp_opt(self.date, 0).unwrap()
     }
 }
 
@@ -43,21 +65,29 @@ impl fmt::Display for ServerInfo {
 
         f.write_str("Disks: ")?;
         for disk in &self.disks {
-            f.write_str(disk)?;
+            f.write_str(disk.to_string().as_str())?;
             f.write_str("\n")?;
         }
 
         f.write_str("Network Interfaces: ")?;
         for interface in &self.net_interfaces {
-            f.write_str(interface)?;
+            f.write_str(interface.to_string().as_str())?;
             f.write_str("\n")?;
         }
 
-        // f.write_str("Components: ")?;
-        // for component in &self.components {
-        //     f.write_str(component)?;
-        //     f.write_str("\n")?;
-        // }
+        f.write_str("Sensors: ")?;
+        for temperature in &self.sensors.temperatures {
+            f.write_str(temperature.to_string().as_str())?;
+            f.write_str("\n")?;
+        }
+        for fan in &self.sensors.fans {
+            f.write_str(fan.to_string().as_str())?;
+            f.write_str("\n")?;
+        }
+        for power in &self.sensors.power {
+            f.write_str(power.to_string().as_str())?;
+            f.write_str("\n")?;
+        }
 
         f.write_str("Total Ram: ")?;
         f.write_str(self.total_ram.to_string().as_str())?;
@@ -87,14 +117,46 @@ impl fmt::Display for ServerInfo {
         f.write_str(self.total_cpus.to_string().as_str())?;
         f.write_str("\n")?;
 
-        for cpu in &self.cpus {
-            f.write_str(cpu)?;
+        for core in &self.cores {
+            f.write_str(core.to_string().as_str())?;
             f.write_str("\n")?;
         }
 
         f.write_str("Average CPU Usage: ")?;
         f.write_str(self.avg_cpu_usage.to_string().as_str())?;
 
+        if let Some(times) = &self.cpu_times {
+            f.write_str("\n")?;
+            f.write_str("CPU Times: ")?;
+            f.write_str(times.to_string().as_str())?;
+        }
+
+        if let Some(cgroup) = &self.cgroup {
+            f.write_str("\n")?;
+            f.write_str("Container: ")?;
+            f.write_str(cgroup.to_string().as_str())?;
+        }
+
+        for container in &self.containers {
+            f.write_str("\n")?;
+            f.write_str(container.to_string().as_str())?;
+        }
+
+        for service in &self.services {
+            f.write_str("\n")?;
+            f.write_str(service.to_string().as_str())?;
+        }
+
+        for group in &self.custom_metrics {
+            f.write_str("\n")?;
+            f.write_str(&group.name)?;
+            f.write_str(":")?;
+            for metric in &group.metrics {
+                f.write_str("\n  ")?;
+                f.write_str(metric.to_string().as_str())?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -104,11 +166,10 @@ impl Serialize for ServerInfo {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("ServerInfo", 13)?;
+        let mut state = serializer.serialize_struct("ServerInfo", 19)?;
         state.serialize_field("date", &self.date)?;
         state.serialize_field("disks", &self.disks)?;
         state.serialize_field("net_interfaces", &self.net_interfaces)?;
-        state.serialize_field("components", &self.components)?;
         state.serialize_field("total_ram", &self.total_ram)?;
         state.serialize_field("used_memory", &self.used_memory)?;
         state.serialize_field("system_name", &self.system_name)?;
@@ -116,8 +177,15 @@ impl Serialize for ServerInfo {
         state.serialize_field("os_version", &self.os_version)?;
         state.serialize_field("host_name", &self.host_name)?;
         state.serialize_field("total_cpus", &self.total_cpus)?;
-        state.serialize_field("cpus", &self.cpus)?;
         state.serialize_field("avg_cpu_usage", &self.avg_cpu_usage)?;
+        state.serialize_field("cgroup", &self.cgroup)?;
+        state.serialize_field("containers", &self.containers)?;
+        state.serialize_field("services", &self.services)?;
+        state.serialize_field("custom_metrics", &self.custom_metrics)?;
+        state.serialize_field("log_files", &self.log_files)?;
+        state.serialize_field("cores", &self.cores)?;
+        state.serialize_field("cpu_times", &self.cpu_times)?;
+        state.serialize_field("sensors", &self.sensors)?;
         state.end()
     }
 }
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// State of a single systemd unit, the states are the same strings `systemctl` reports.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct ServiceStatus {
+    pub name: String,
+    /// e.g. "active", "failed", "activating"
+    pub active_state: String,
+    /// e.g. "running", "dead", "auto-restart"
+    pub sub_state: String,
+    pub restarts: u32,
+    /// memory usage in bytes, none when memory accounting is off for the unit
+    pub memory_usage: Option<u64>,
+}
+
+impl ServiceStatus {
+    pub fn is_failed(&self) -> bool {
+        self.active_state == "failed"
+    }
+
+    /// True while systemd is (re)starting or stopping the unit
+    pub fn is_restarting(&self) -> bool {
+        matches!(
+            self.active_state.as_str(),
+            "activating" | "deactivating" | "reloading"
+        ) || self.sub_state == "auto-restart"
+    }
+}
+
+impl fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({}), restarts: {}",
+            self.name, self.active_state, self.sub_state, self.restarts
+        )?;
+        if let Some(memory_usage) = self.memory_usage {
+            write!(f, ", memory {memory_usage} B")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use server_info_client::{AsyncClient, ClientError, ClientOptions, TlsOptions};
+use server_info_packets::protocol::{ClientRequest, HEARTBEAT_INTERVAL, MAX_REPORT_LEN};
+use server_info_packets::server_info_packet::ServerInfo;
+use server_info_server_rs::config::AgentConfig;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// Waited before dialing the hub again after the connection broke
+#[cfg(not(test))]
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+#[cfg(test)]
+const RECONNECT_DELAY: Duration = Duration::from_millis(50);
+
+/// How long the hub gets to accept the connection and acknowledge a report
+const HUB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Infos taken but not yet acknowledged by the hub, oldest first.
+struct Buffer {
+    infos: VecDeque<ServerInfo>,
+    capacity: usize,
+    /// infos dropped because the buffer was full since the hub was last reached
+    dropped: u64,
+}
+
+impl Buffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            infos: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }
+    }
+
+    /// Adds an info, dropping the oldest one when full
+    fn push(&mut self, info: ServerInfo) {
+        if self.infos.len() == self.capacity {
+            self.infos.pop_front();
+            self.dropped += 1;
+        }
+        self.infos.push_back(info);
+    }
+
+    /// The oldest infos, as many as fit in one report
+    fn batch(&self) -> Vec<ServerInfo> {
+        self.infos.iter().take(MAX_REPORT_LEN).cloned().collect()
+    }
+
+    /// Drops the oldest count infos once the hub stored them
+    fn acknowledged(&mut self, count: usize) {
+        self.infos.drain(..count.min(self.infos.len()));
+    }
+}
+
+/// What the agent waits for.
+enum Event {
+    /// the next info is due
+    Sample,
+    /// nothing was sent to the hub for HEARTBEAT_INTERVAL
+    Heartbeat,
+    /// the attempt to connect to the hub finished
+    Connected(Box<Result<AsyncClient, ClientError>>),
+}
+
+/// Pushes an info of latest to the hub at endpoint every interval, buffering them while the hub can not be reached.
+///
+/// Connecting runs on its own task, a hub that drops packets instead of refusing them does not hold up sampling.
+pub async fn run(config: AgentConfig, endpoint: String, latest: Arc<RwLock<ServerInfo>>) {
+    let name = config.name.clone().unwrap_or_else(|| {
+        let latest = latest.read().expect("Server info lock poisoned.");
+        latest.host_name.to_string()
+    });
+    let config = Arc::new(config);
+    let mut buffer = Buffer::new(config.buffer_len);
+    let mut hub: Option<AsyncClient> = None;
+    let mut connecting: Option<JoinHandle<Result<AsyncClient, ClientError>>> = None;
+    let mut next_attempt = Instant::now();
+    let mut last_sent = Instant::now();
+    // the collectors may be slower than the interval, the same info is only pushed once
+    let mut last_date = i64::MIN;
+
+    let mut ticker = tokio::time::interval(Duration::from_millis(config.interval_ms.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let event = tokio::select! {
+            _ = ticker.tick() => Event::Sample,
+            _ = tokio::time::sleep_until(last_sent + HEARTBEAT_INTERVAL), if hub.is_some() => Event::Heartbeat,
+            connected = async { connecting.as_mut().expect("Checked by the guard.").await }, if connecting.is_some() => {
+                Event::Connected(Box::new(connected.unwrap_or_else(|e| Err(ClientError::Io(io::Error::other(e))))))
+            }
+        };
+
+        // whether anything was sent to the hub
+        let sent = match (event, &mut hub) {
+            (Event::Heartbeat, Some(client)) => {
+                client.send(&ClientRequest::Heartbeat).await.map(|_| true)
+            }
+            (Event::Heartbeat, None) => Ok(false),
+            (Event::Sample, _) => {
+                let info = latest.read().expect("Server info lock poisoned.").clone();
+                if info.date > last_date {
+                    last_date = info.date;
+                    buffer.push(info);
+                }
+
+                if hub.is_none() && connecting.is_none() && Instant::now() >= next_attempt {
+                    let (config, endpoint, name) = (config.clone(), endpoint.clone(), name.clone());
+                    connecting = Some(tokio::spawn(async move {
+                        connect(&config, &endpoint, &name).await
+                    }));
+                }
+                match &mut hub {
+                    Some(client) => flush(client, &mut buffer).await,
+                    None => Ok(false),
+                }
+            }
+            (Event::Connected(connected), _) => {
+                connecting = None;
+                match *connected {
+                    Ok(mut client) => {
+                        println!("Pushing to hub {endpoint} as {name}.");
+                        let flushed = flush(&mut client, &mut buffer).await;
+                        hub = Some(client);
+                        flushed
+                    }
+                    Err(e) => {
+                        println!(
+                            "Unable to reach hub {endpoint}: {e}, {} infos buffered, {} dropped.",
+                            buffer.infos.len(),
+                            buffer.dropped
+                        );
+                        next_attempt = Instant::now() + RECONNECT_DELAY;
+                        Ok(false)
+                    }
+                }
+            }
+        };
+
+        match sent {
+            Ok(true) => last_sent = Instant::now(),
+            Ok(false) => {}
+            Err(e) => {
+                println!("Lost hub {endpoint}: {e}, buffering infos until it is back.");
+                hub = None;
+                next_attempt = Instant::now() + RECONNECT_DELAY;
+            }
+        }
+    }
+}
+
+/// Connects to the hub and registers as the agent of the host called name
+async fn connect(
+    config: &AgentConfig,
+    endpoint: &str,
+    name: &str,
+) -> Result<AsyncClient, ClientError> {
+    let options = ClientOptions {
+        timeout: HUB_TIMEOUT,
+        auth_token: config.auth_token.clone(),
+        tls: config.tls.then(|| TlsOptions {
+            server_name: None,
+            ca_file: config.tls_ca.clone(),
+        }),
+        encodings: vec![config.encoding],
+        compressions: vec![config.compression],
+        deltas: false,
+    };
+    let mut client = AsyncClient::connect(endpoint, &options).await?;
+    client.register_agent(name, &config.token).await?;
+    Ok(client)
+}
+
+/// Reports every buffered info, oldest first, dropping them as the hub acknowledges them.
+///
+/// Returns whether there was anything to report.
+async fn flush(client: &mut AsyncClient, buffer: &mut Buffer) -> Result<bool, ClientError> {
+    let reported = !buffer.infos.is_empty();
+    while !buffer.infos.is_empty() {
+        let batch = buffer.batch();
+        let count = batch.len();
+        client.report(batch).await?;
+        buffer.acknowledged(count);
+    }
+    buffer.dropped = 0;
+    Ok(reported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use server_info_server_rs::hosts::Hosts;
+    use server_info_server_rs::log_tail::LogStore;
+    use server_info_server_rs::server::{serve, Shared, Source};
+    use tokio::net::TcpListener;
+    use tokio::sync::watch;
+    use tokio::task::JoinHandle;
+
+    /// Serves hosts as a hub accepting agents on address until the sender is told to stop
+    async fn spawn_hub(address: &str, hosts: Arc<Hosts>) -> (watch::Sender<bool>, JoinHandle<()>) {
+        let shared = Arc::new(Shared {
+            source: Source::Hub(hosts),
+            logs: LogStore::spawn(vec![]),
+            auth_token: None,
+            agent_token: Some("agents".to_string()),
+            write_timeout: Duration::from_secs(5),
+            compression_threshold: usize::MAX,
+        });
+        let listener = TcpListener::bind(address).await.unwrap();
+        let (sender, shutdown) = watch::channel(false);
+        let handle = tokio::spawn(serve(listener, shared, 10, shutdown));
+        (sender, handle)
+    }
+
+    /// The dates the hub stored for the agent, waiting up to a few seconds for them to become want
+    async fn wait_for_dates(hosts: &Hosts, want: &[i64]) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let dates: Vec<i64> = hosts
+                .history(Some("agent"), 0, i64::MAX)
+                .iter()
+                .map(|info| info.date)
+                .collect();
+            if dates == want {
+                return;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "hub stored {dates:?}, not {want:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Sets the info the agent takes to one dated date, and gives the agent time to take it
+    async fn collect(latest: &RwLock<ServerInfo>, date: i64) {
+        latest.write().unwrap().date = date;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+    }
+
+    #[tokio::test]
+    async fn buffers_while_the_hub_is_away_and_reconnects() {
+        // a free port nothing listens on yet
+        let address = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+        let latest = Arc::new(RwLock::new(ServerInfo::default()));
+        let config = AgentConfig {
+            name: Some("agent".to_string()),
+            token: "agents".to_string(),
+            interval_ms: 20,
+            buffer_len: 3,
+            ..Default::default()
+        };
+        let agent = tokio::spawn(run(config, address.to_string(), latest.clone()));
+
+        // the oldest infos are dropped while the hub is down
+        for date in 1..=5 {
+            collect(&latest, date).await;
+        }
+        let hosts = Arc::new(Hosts::new([], 100));
+        let (stop, hub) = spawn_hub(&address, hosts.clone()).await;
+        wait_for_dates(&hosts, &[3, 4, 5]).await;
+
+        // the hub goes away and comes back, the info taken in between is reported late
+        stop.send(true).unwrap();
+        hub.await.unwrap();
+        collect(&latest, 6).await;
+        let (_stop, _hub) = spawn_hub(&address, hosts.clone()).await;
+        collect(&latest, 7).await;
+        wait_for_dates(&hosts, &[3, 4, 5, 6, 7]).await;
+
+        agent.abort();
+    }
+
+    #[tokio::test]
+    async fn keeps_sampling_while_the_hub_does_not_answer() {
+        // the kernel completes the handshake but nothing ever answers, like a hub behind a firewall dropping packets
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = silent.local_addr().unwrap().to_string();
+        let latest = Arc::new(RwLock::new(ServerInfo::default()));
+        let config = AgentConfig {
+            name: Some("agent".to_string()),
+            token: "agents".to_string(),
+            interval_ms: 20,
+            buffer_len: 10,
+            ..Default::default()
+        };
+        let agent = tokio::spawn(run(config, address.to_string(), latest.clone()));
+
+        // every info is taken while the agent waits for the hello
+        for date in 1..=5 {
+            collect(&latest, date).await;
+        }
+        drop(silent);
+        let hosts = Arc::new(Hosts::new([], 100));
+        let (_stop, _hub) = spawn_hub(&address, hosts.clone()).await;
+        wait_for_dates(&hosts, &[1, 2, 3, 4, 5]).await;
+
+        agent.abort();
+    }
+
+    fn info(date: i64) -> ServerInfo {
+        ServerInfo {
+            date,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn buffer_drops_the_oldest_infos_and_batches_reports() {
+        let mut buffer = Buffer::new(MAX_REPORT_LEN + 10);
+        for date in 0..MAX_REPORT_LEN as i64 + 10 {
+            buffer.push(info(date));
+        }
+        assert_eq!(buffer.dropped, 0);
+        buffer.push(info(1000));
+        assert_eq!(buffer.dropped, 1);
+
+        let batch = buffer.batch();
+        assert_eq!(batch.len(), MAX_REPORT_LEN);
+        assert_eq!(batch[0].date, 1);
+
+        buffer.acknowledged(batch.len());
+        let rest: Vec<i64> = buffer.batch().iter().map(|info| info.date).collect();
+        assert_eq!(rest.len(), 10);
+        assert_eq!(rest.last(), Some(&1000));
+
+        buffer.acknowledged(100);
+        assert!(buffer.infos.is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+use server_info_packets::container_info::{CgroupInfo, ContainerStats};
+use server_info_server_rs::collector::{Collector, Section};
+use server_info_server_rs::sysfs::read_trimmed;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// cgroup v1 reports an unlimited memory limit as a page aligned i64::MAX, anything this large is treated as no limit
+const V1_UNLIMITED_MEMORY: u64 = 1 << 62;
+
+/// The directories of a single cgroup, for cgroup v2 every controller lives in the same directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgroupDirs {
+    pub version: u8,
+    pub memory: PathBuf,
+    pub cpu: PathBuf,
+    pub cpuacct: PathBuf,
+    pub io: PathBuf,
+}
+
+/// A raw reading of a cgroups counters, cpu usage is cumulative so two samples are needed for a percentage.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CgroupSample {
+    pub memory_limit: Option<u64>,
+    pub memory_usage: u64,
+    pub cpu_limit: Option<f64>,
+    pub cpu_usage_usec: u64,
+    pub io_read_bytes: u64,
+    pub io_write_bytes: u64,
+}
+
+/// Cgroup paths of processes started by these run in a container
+const CONTAINER_CGROUPS: [&str; 5] = ["docker", "kubepods", "containerd", "libpod", "lxc"];
+
+/// Finds the cgroup of the running process using /proc/self/cgroup, returns none when not running under cgroups
+pub fn detect_self(root: &Path) -> Option<CgroupDirs> {
+    let proc_self_cgroup = fs::read_to_string("/proc/self/cgroup").ok()?;
+    detect(root, &proc_self_cgroup)
+}
+
+/// Whether the running process is in a container, every process on a cgroup v2 host has a cgroup so that alone does not tell
+pub fn in_container_self() -> bool {
+    let proc_self_cgroup = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    // systemd-nspawn and lxc set this in the environment of the containers init
+    std::env::var_os("container").is_some() || in_container(Path::new("/"), &proc_self_cgroup)
+}
+
+/// Whether the files docker and podman leave under fs_root, or the contents of a /proc/<pid>/cgroup file, point to a container
+pub fn in_container(fs_root: &Path, proc_self_cgroup: &str) -> bool {
+    if fs_root.join(".dockerenv").exists() || fs_root.join("run/.containerenv").exists() {
+        return true;
+    }
+    // without a cgroup namespace the path of a container is visible, with one it is "/" and the marker files have to tell
+    proc_self_cgroup.lines().any(|line| {
+        let path = line.splitn(3, ':').nth(2).unwrap_or_default();
+        CONTAINER_CGROUPS.iter().any(|name| path.contains(name))
+    })
+}
+
+/// Finds the cgroup described by the contents of a /proc/<pid>/cgroup file, mounted at root
+pub fn detect(root: &Path, proc_self_cgroup: &str) -> Option<CgroupDirs> {
+    if root.join("cgroup.controllers").exists() {
+        let path = proc_self_cgroup
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .unwrap_or("/");
+        let dir = controller_dir(root, path);
+        return Some(CgroupDirs {
+            version: 2,
+            memory: dir.clone(),
+            cpu: dir.clone(),
+            cpuacct: dir.clone(),
+            io: dir,
+        });
+    }
+
+    if root.join("memory").exists() {
+        let path_of = |controller: &str| -> String {
+            proc_self_cgroup
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(3, ':');
+                    let _id = parts.next()?;
+                    let controllers = parts.next()?;
+                    let path = parts.next()?;
+                    Some((controllers, path))
+                })
+                .find(|(controllers, _)| controllers.split(',').any(|c| c == controller))
+                .map(|(_, path)| path.to_string())
+                .unwrap_or_else(|| "/".to_string())
+        };
+        return Some(CgroupDirs {
+            version: 1,
+            memory: controller_dir(&root.join("memory"), &path_of("memory")),
+            cpu: controller_dir(&root.join("cpu"), &path_of("cpu")),
+            cpuacct: controller_dir(&root.join("cpuacct"), &path_of("cpuacct")),
+            io: controller_dir(&root.join("blkio"), &path_of("blkio")),
+        });
+    }
+
+    None
+}
+
+/// Inside a cgroup namespace the path from /proc/self/cgroup does not exist under the mount, in that case the mount itself is our cgroup
+fn controller_dir(mount: &Path, path: &str) -> PathBuf {
+    let dir = mount.join(path.trim_start_matches('/'));
+    if dir.exists() {
+        dir
+    } else {
+        mount.to_path_buf()
+    }
+}
+
+/// Lists the cgroups of docker containers under root, named by their short container id
+pub fn list_containers(root: &Path, version: u8) -> Vec<(String, CgroupDirs)> {
+    let mut containers = vec![];
+
+    if version == 2 {
+        for parent in ["docker", "system.slice"] {
+            for (name, dir) in container_dirs(&root.join(parent)) {
+                containers.push((
+                    name,
+                    CgroupDirs {
+                        version,
+                        memory: dir.clone(),
+                        cpu: dir.clone(),
+                        cpuacct: dir.clone(),
+                        io: dir,
+                    },
+                ));
+            }
+        }
+    } else {
+        for (name, dir) in container_dirs(&root.join("memory").join("docker")) {
+            let relative = Path::new("docker").join(dir.file_name().unwrap_or_default());
+            containers.push((
+                name,
+                CgroupDirs {
+                    version,
+                    memory: dir,
+                    cpu: root.join("cpu").join(&relative),
+                    cpuacct: root.join("cpuacct").join(&relative),
+                    io: root.join("blkio").join(&relative),
+                },
+            ));
+        }
+    }
+
+    containers.sort_by(|a, b| a.0.cmp(&b.0));
+    containers
+}
+
+/// Returns the container cgroups directly inside parent, either "<id>" (cgroupfs driver) or "docker-<id>.scope" (systemd driver)
+fn container_dirs(parent: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(parent) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut dirs = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let id = match file_name
+            .strip_prefix("docker-")
+            .and_then(|name| name.strip_suffix(".scope"))
+        {
+            Some(id) => id.to_string(),
+            None if file_name.chars().all(|c| c.is_ascii_hexdigit()) => file_name,
+            None => continue,
+        };
+        dirs.push((id.chars().take(12).collect(), path));
+    }
+    dirs
+}
+
+/// Reads the current counters of a cgroup, counters that can not be read are left at zero
+pub fn read_sample(dirs: &CgroupDirs) -> CgroupSample {
+    if dirs.version == 2 {
+        read_sample_v2(dirs)
+    } else {
+        read_sample_v1(dirs)
+    }
+}
+
+fn read_sample_v2(dirs: &CgroupDirs) -> CgroupSample {
+    let memory_limit = read_trimmed(&dirs.memory.join("memory.max")).and_then(|s| s.parse().ok());
+    let memory_usage = read_u64(&dirs.memory.join("memory.current"));
+
+    // cpu.max is "<quota> <period>", quota being "max" when unlimited
+    let cpu_limit = read_trimmed(&dirs.cpu.join("cpu.max")).and_then(|s| {
+        let mut parts = s.split_whitespace();
+        let quota: f64 = parts.next()?.parse().ok()?;
+        let period: f64 = parts.next()?.parse().ok()?;
+        Some(quota / period)
+    });
+
+    let cpu_usage_usec = read_trimmed(&dirs.cpuacct.join("cpu.stat"))
+        .and_then(|s| {
+            s.lines()
+                .find_map(|line| line.strip_prefix("usage_usec "))
+                .and_then(|v| v.trim().parse().ok())
+        })
+        .unwrap_or_default();
+
+    // io.stat has a line per device, "8:0 rbytes=1 wbytes=2 rios=3 wios=4 ..."
+    let mut io_read_bytes = 0;
+    let mut io_write_bytes = 0;
+    for line in read_trimmed(&dirs.io.join("io.stat"))
+        .unwrap_or_default()
+        .lines()
+    {
+        for field in line.split_whitespace().skip(1) {
+            if let Some(v) = field.strip_prefix("rbytes=") {
+                io_read_bytes += v.parse::<u64>().unwrap_or_default();
+            } else if let Some(v) = field.strip_prefix("wbytes=") {
+                io_write_bytes += v.parse::<u64>().unwrap_or_default();
+            }
+        }
+    }
+
+    CgroupSample {
+        memory_limit,
+        memory_usage,
+        cpu_limit,
+        cpu_usage_usec,
+        io_read_bytes,
+        io_write_bytes,
+    }
+}
+
+fn read_sample_v1(dirs: &CgroupDirs) -> CgroupSample {
+    let memory_limit = read_trimmed(&dirs.memory.join("memory.limit_in_bytes"))
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|limit| *limit < V1_UNLIMITED_MEMORY);
+    let memory_usage = read_u64(&dirs.memory.join("memory.usage_in_bytes"));
+
+    // a quota of -1 means unlimited
+    let cpu_limit = read_trimmed(&dirs.cpu.join("cpu.cfs_quota_us"))
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|quota| *quota > 0.0)
+        .and_then(|quota| {
+            let period: f64 = read_trimmed(&dirs.cpu.join("cpu.cfs_period_us"))?
+                .parse()
+                .ok()?;
+            Some(quota / period)
+        });
+
+    // cpuacct.usage is in nanoseconds
+    let cpu_usage_usec = read_u64(&dirs.cpuacct.join("cpuacct.usage")) / 1000;
+
+    // blkio.throttle.io_service_bytes has lines like "8:0 Read 1234" followed by a "Total" line
+    let mut io_read_bytes = 0;
+    let mut io_write_bytes = 0;
+    for line in read_trimmed(&dirs.io.join("blkio.throttle.io_service_bytes"))
+        .unwrap_or_default()
+        .lines()
+    {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if let [_device, op, value] = parts.as_slice() {
+            let value = value.parse::<u64>().unwrap_or_default();
+            match *op {
+                "Read" => io_read_bytes += value,
+                "Write" => io_write_bytes += value,
+                _ => {}
+            }
+        }
+    }
+
+    CgroupSample {
+        memory_limit,
+        memory_usage,
+        cpu_limit,
+        cpu_usage_usec,
+        io_read_bytes,
+        io_write_bytes,
+    }
+}
+
+/// Cpu usage between two samples in percent of a single core
+pub fn cpu_usage_percent(before: &CgroupSample, after: &CgroupSample, elapsed: Duration) -> f32 {
+    let elapsed_usec = elapsed.as_micros() as f64;
+    if elapsed_usec == 0.0 {
+        return 0.0;
+    }
+    let used_usec = after.cpu_usage_usec.saturating_sub(before.cpu_usage_usec) as f64;
+    (used_usec / elapsed_usec * 100.0) as f32
+}
+
+/// Reports our own cgroup when running in a container and optionally every sibling container, cpu usage is measured between two runs
+pub struct CgroupCollector {
+    root: PathBuf,
+    list_siblings: bool,
+    /// also known outside of containers, siblings are listed with its cgroup version
+    own_cgroup: Option<CgroupDirs>,
+    in_container: bool,
+    previous_samples: HashMap<PathBuf, (CgroupSample, Instant)>,
+}
+
+impl CgroupCollector {
+    pub fn new(root: PathBuf, list_siblings: bool) -> Self {
+        let own_cgroup = detect_self(&root);
+        Self {
+            root,
+            list_siblings,
+            own_cgroup,
+            in_container: in_container_self(),
+            previous_samples: HashMap::new(),
+        }
+    }
+
+    /// Reads a cgroup and returns the sample along with the cpu usage since the previous read of the same cgroup
+    fn sample(&mut self, dirs: &CgroupDirs) -> (CgroupSample, f32) {
+        let now = Instant::now();
+        let sample = read_sample(dirs);
+        let cpu_usage = match self.previous_samples.get(&dirs.cpuacct) {
+            Some((before, at)) => cpu_usage_percent(before, &sample, now.duration_since(*at)),
+            None => 0.0,
+        };
+        self.previous_samples
+            .insert(dirs.cpuacct.clone(), (sample.clone(), now));
+        (sample, cpu_usage)
+    }
+}
+
+impl Collector for CgroupCollector {
+    fn name(&self) -> &str {
+        "containers"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        let own_cgroup = match self.own_cgroup.clone() {
+            Some(dirs) => dirs,
+            None => {
+                return Ok(Section::Containers {
+                    cgroup: None,
+                    containers: vec![],
+                })
+            }
+        };
+
+        // on a bare host our cgroup is just a slice of the host, not a container with limits of its own
+        let cgroup = match self.in_container {
+            true => {
+                let (sample, cpu_usage) = self.sample(&own_cgroup);
+                Some(CgroupInfo {
+                    version: own_cgroup.version,
+                    memory_limit: sample.memory_limit,
+                    memory_usage: sample.memory_usage,
+                    cpu_limit: sample.cpu_limit,
+                    cpu_usage,
+                })
+            }
+            false => None,
+        };
+
+        let mut containers: Vec<ContainerStats> = vec![];
+
+        if self.list_siblings {
+            let siblings = list_containers(&self.root, own_cgroup.version);
+            // forget containers that went away
+            self.previous_samples.retain(|dir, _| {
+                *dir == own_cgroup.cpuacct || siblings.iter().any(|(_, dirs)| dirs.cpuacct == *dir)
+            });
+
+            for (name, dirs) in siblings {
+                let (sample, cpu_usage) = self.sample(&dirs);
+                containers.push(ContainerStats {
+                    name,
+                    cpu_usage,
+                    memory_usage: sample.memory_usage,
+                    memory_limit: sample.memory_limit,
+                    io_read_bytes: sample.io_read_bytes,
+                    io_write_bytes: sample.io_write_bytes,
+                });
+            }
+        }
+
+        Ok(Section::Containers { cgroup, containers })
+    }
+}
+
+fn read_u64(path: &Path) -> u64 {
+    read_trimmed(path)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn reads_v2_limits_of_own_cgroup() {
+        let root = fixture("cgroup_v2");
+        let dirs = detect(&root, "0::/\n").unwrap();
+        assert_eq!(dirs.version, 2);
+        assert_eq!(dirs.memory, root);
+
+        let sample = read_sample(&dirs);
+        assert_eq!(sample.memory_limit, Some(536870912));
+        assert_eq!(sample.memory_usage, 104857600);
+        assert_eq!(sample.cpu_limit, Some(1.5));
+        assert_eq!(sample.cpu_usage_usec, 2500000);
+        assert_eq!(sample.io_read_bytes, 4096 + 1024);
+        assert_eq!(sample.io_write_bytes, 8192);
+    }
+
+    #[test]
+    fn reads_v1_limits_of_own_cgroup() {
+        let root = fixture("cgroup_v1");
+        let proc_self_cgroup = "12:memory:/\n5:cpu,cpuacct:/\n3:blkio:/\n";
+        let dirs = detect(&root, proc_self_cgroup).unwrap();
+        assert_eq!(dirs.version, 1);
+        assert_eq!(dirs.memory, root.join("memory"));
+
+        let sample = read_sample(&dirs);
+        assert_eq!(sample.memory_limit, Some(268435456));
+        assert_eq!(sample.memory_usage, 52428800);
+        assert_eq!(sample.cpu_limit, Some(0.5));
+        assert_eq!(sample.cpu_usage_usec, 1500000);
+        assert_eq!(sample.io_read_bytes, 2048);
+        assert_eq!(sample.io_write_bytes, 512);
+    }
+
+    #[test]
+    fn unlimited_cgroups_have_no_limits() {
+        let root = fixture("cgroup_v2");
+        let containers = list_containers(&root, 2);
+        let (_, dirs) = containers
+            .iter()
+            .find(|(name, _)| name == "0123456789ab")
+            .unwrap();
+        let sample = read_sample(dirs);
+        assert_eq!(sample.memory_limit, None);
+        assert_eq!(sample.cpu_limit, None);
+
+        let root = fixture("cgroup_v1");
+        let (_, dirs) = &list_containers(&root, 1)[0];
+        let sample = read_sample(dirs);
+        assert_eq!(sample.memory_limit, None);
+        assert_eq!(sample.cpu_limit, None);
+    }
+
+    #[test]
+    fn lists_sibling_containers() {
+        let names: Vec<String> = list_containers(&fixture("cgroup_v2"), 2)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["0123456789ab", "fedcba987654"]);
+
+        let containers = list_containers(&fixture("cgroup_v1"), 1);
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].0, "aaaabbbbcccc");
+        assert_eq!(read_sample(&containers[0].1).memory_usage, 1048576);
+    }
+
+    #[test]
+    fn tells_containers_from_bare_hosts() {
+        let bare = fixture("does_not_exist");
+        assert!(!in_container(
+            &bare,
+            "0::/user.slice/user-1000.slice/session-2.scope\n"
+        ));
+        assert!(!in_container(&bare, "0::/\n"));
+        assert!(in_container(
+            &bare,
+            "0::/system.slice/docker-0123456789ab.scope\n"
+        ));
+        assert!(in_container(&bare, "12:memory:/kubepods/burstable/pod1\n"));
+    }
+
+    #[test]
+    fn missing_cgroup_fs_is_not_detected() {
+        assert_eq!(detect(&fixture("does_not_exist"), "0::/\n"), None);
+    }
+
+    #[test]
+    fn cpu_usage_is_relative_to_elapsed_time() {
+        let before = CgroupSample {
+            cpu_usage_usec: 1_000_000,
+            ..Default::default()
+        };
+        let after = CgroupSample {
+            cpu_usage_usec: 1_250_000,
+            ..Default::default()
+        };
+        let usage = cpu_usage_percent(&before, &after, Duration::from_millis(500));
+        assert_eq!(usage, 50.0);
+    }
+}
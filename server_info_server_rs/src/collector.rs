@@ -0,0 +1,484 @@
+use crate::config::CollectorConfig;
+use crate::history::History;
+use chrono::Utc;
+use server_info_packets::container_info::{CgroupInfo, ContainerStats};
+use server_info_packets::cpu_info::{CoreInfo, CpuTimes};
+use server_info_packets::custom_metric::CustomMetricGroup;
+use server_info_packets::disk_info::DiskInfo;
+use server_info_packets::network_info::NetworkInterfaceInfo;
+use server_info_packets::sensor_info::Sensors;
+use server_info_packets::server_info_packet::ServerInfo;
+use server_info_packets::service_info::ServiceStatus;
+use std::collections::HashMap;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A typed part of a ServerInfo packet, produced by a collector.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Section {
+    Host {
+        system_name: String,
+        kernel_version: String,
+        os_version: String,
+        host_name: String,
+    },
+    Cpu {
+        total_cpus: usize,
+        avg_cpu_usage: f32,
+        cores: Vec<CoreInfo>,
+        cpu_times: Option<CpuTimes>,
+    },
+    Memory {
+        total_ram: u64,
+        used_memory: u64,
+    },
+    Disks(Vec<DiskInfo>),
+    Network(Vec<NetworkInterfaceInfo>),
+    Components(Sensors),
+    Containers {
+        cgroup: Option<CgroupInfo>,
+        containers: Vec<ContainerStats>,
+    },
+    Services(Vec<ServiceStatus>),
+    /// metrics from a site specific collector, replaces the earlier group with the same name
+    Custom(CustomMetricGroup),
+}
+
+impl Section {
+    /// Writes this section into the packet, replacing what an earlier run of the collector put there
+    pub fn apply(self, info: &mut ServerInfo) {
+        match self {
+            Section::Host {
+                system_name,
+                kernel_version,
+                os_version,
+                host_name,
+            } => {
+                info.system_name = system_name;
+                info.kernel_version = kernel_version;
+                info.os_version = os_version;
+                info.host_name = host_name;
+            }
+            Section::Cpu {
+                total_cpus,
+                avg_cpu_usage,
+                cores,
+                cpu_times,
+            } => {
+                info.total_cpus = total_cpus;
+                info.avg_cpu_usage = avg_cpu_usage;
+                info.cores = cores;
+                info.cpu_times = cpu_times;
+            }
+            Section::Memory {
+                total_ram,
+                used_memory,
+            } => {
+                info.total_ram = total_ram;
+                info.used_memory = used_memory;
+            }
+            Section::Disks(disks) => info.disks = disks,
+            Section::Network(net_interfaces) => info.net_interfaces = net_interfaces,
+            Section::Components(sensors) => info.sensors = sensors,
+            Section::Containers { cgroup, containers } => {
+                info.cgroup = cgroup;
+                info.containers = containers;
+            }
+            Section::Services(services) => info.services = services,
+            Section::Custom(group) => {
+                match info
+                    .custom_metrics
+                    .iter_mut()
+                    .find(|existing| existing.name == group.name)
+                {
+                    Some(existing) => *existing = group,
+                    None => info.custom_metrics.push(group),
+                }
+            }
+        }
+    }
+}
+
+/// A source of data for the ServerInfo packet.
+///
+/// Built in collectors are registered in main, any other type implementing this trait can be added with
+/// `CollectorRegistry::register` the same way.
+pub trait Collector: Send {
+    /// Unique name, used to enable/disable the collector and override its interval in the config file
+    fn name(&self) -> &str;
+
+    /// How often the collector runs unless the config file says otherwise
+    fn interval(&self) -> Duration;
+
+    /// Gathers a fresh section, on error the section from the last successful run is kept
+    fn collect(&mut self) -> Result<Section, String>;
+}
+
+/// Shortest interval a collector runs at, shorter ones in the config file would keep the run loop spinning
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long collect_due waits for the collectors it started, a collector that takes longer keeps its last section
+const COLLECT_DEADLINE: Duration = Duration::from_secs(2);
+
+struct RegisteredCollector {
+    name: String,
+    interval: Duration,
+    last_run: Option<Instant>,
+    /// asks the thread of the collector for a run
+    runs: Sender<()>,
+    /// a run was asked for and has not finished yet
+    running: bool,
+}
+
+impl RegisteredCollector {
+    fn is_due(&self, now: Instant) -> bool {
+        match self.last_run {
+            None => true,
+            Some(last_run) => now.duration_since(last_run) >= self.interval,
+        }
+    }
+}
+
+/// Runs every enabled collector on its own interval and thread, and merges their sections into one packet.
+pub struct CollectorRegistry {
+    config: HashMap<String, CollectorConfig>,
+    collectors: Vec<RegisteredCollector>,
+    /// (index of the collector, its section or none when it failed) sent by the collector threads
+    sections: Receiver<(usize, Option<Section>)>,
+    sections_sender: Sender<(usize, Option<Section>)>,
+    deadline: Duration,
+    min_interval: Duration,
+    info: ServerInfo,
+}
+
+impl CollectorRegistry {
+    pub fn new(config: HashMap<String, CollectorConfig>) -> Self {
+        let (sections_sender, sections) = mpsc::channel();
+        Self {
+            config,
+            collectors: vec![],
+            sections,
+            sections_sender,
+            deadline: COLLECT_DEADLINE,
+            min_interval: MIN_INTERVAL,
+            info: ServerInfo::default(),
+        }
+    }
+
+    /// Adds a collector and starts its thread, unless the config file disables it by name
+    pub fn register(&mut self, mut collector: Box<dyn Collector>) {
+        let name = collector.name().to_string();
+        let config = self.config.get(&name);
+
+        if !config.map(|c| c.enabled).unwrap_or(true) {
+            println!("Collector disabled: {name}");
+            return;
+        }
+
+        let interval = config
+            .and_then(|c| c.interval_ms)
+            .map(Duration::from_millis)
+            .unwrap_or_else(|| collector.interval());
+        let interval = match interval < self.min_interval {
+            true => {
+                println!(
+                    "Collector {name} runs every {:?} instead of every {interval:?}",
+                    self.min_interval
+                );
+                self.min_interval
+            }
+            false => interval,
+        };
+
+        let index = self.collectors.len();
+        let (runs, runs_receiver) = mpsc::channel::<()>();
+        let sections = self.sections_sender.clone();
+        let thread_name = name.clone();
+        // a collector that hangs only blocks its own thread, the registry stops waiting for it at the deadline
+        let spawned = thread::Builder::new()
+            .name(format!("collector {name}"))
+            .spawn(move || {
+                for () in runs_receiver {
+                    let section =
+                        match panic::catch_unwind(AssertUnwindSafe(|| collector.collect())) {
+                            Ok(Ok(section)) => Some(section),
+                            Ok(Err(e)) => {
+                                println!("Collector {thread_name} failed: {e}");
+                                None
+                            }
+                            Err(_) => {
+                                println!("Collector {thread_name} panicked");
+                                None
+                            }
+                        };
+                    if sections.send((index, section)).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            println!("Unable to start collector {name}: {e}");
+            return;
+        }
+
+        self.collectors.push(RegisteredCollector {
+            name,
+            interval,
+            last_run: None,
+            runs,
+            running: false,
+        });
+    }
+
+    /// Writes a section sent by a collector thread into the packet
+    fn apply(&mut self, index: usize, section: Option<Section>) {
+        self.collectors[index].running = false;
+        if let Some(section) = section {
+            section.apply(&mut self.info);
+        }
+    }
+
+    /// Runs every collector whose interval has passed and waits for them until the deadline.
+    ///
+    /// A collector that errors, panics or is still running at the deadline only loses its own section, a late
+    /// section is applied by a later call.
+    pub fn collect_due(&mut self) -> &ServerInfo {
+        // sections of collectors that missed an earlier deadline
+        while let Ok((index, section)) = self.sections.try_recv() {
+            self.apply(index, section);
+        }
+
+        let now = Instant::now();
+        let mut started = 0;
+        for registered in &mut self.collectors {
+            if registered.running || !registered.is_due(now) {
+                continue;
+            }
+            registered.last_run = Some(now);
+            if registered.runs.send(()).is_ok() {
+                registered.running = true;
+                started += 1;
+            }
+        }
+
+        let deadline = now + self.deadline;
+        while started > 0 {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.sections.recv_timeout(timeout) {
+                Ok((index, section)) => {
+                    if self.collectors[index].last_run == Some(now) {
+                        started -= 1;
+                    }
+                    self.apply(index, section);
+                }
+                Err(_) => {
+                    let overdue = self.collectors.iter().filter(|registered| {
+                        registered.running && registered.last_run == Some(now)
+                    });
+                    for registered in overdue {
+                        println!(
+                            "Collector {} is overdue, keeping its last section",
+                            registered.name
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.info.date = Utc::now().timestamp();
+        &self.info
+    }
+
+    /// Time until the next collector is due
+    pub fn until_next_due(&self) -> Duration {
+        let now = Instant::now();
+        self.collectors
+            .iter()
+            // a collector still running is not started again until it finishes
+            .filter(|registered| !registered.running)
+            .map(|registered| match registered.last_run {
+                None => Duration::ZERO,
+                Some(last_run) => registered
+                    .interval
+                    .saturating_sub(now.duration_since(last_run)),
+            })
+            .min()
+            .unwrap_or(Duration::from_secs(1))
+    }
+
+    /// Collects forever, publishing every new packet to latest and adding it to history
+    pub fn run(mut self, latest: Arc<RwLock<ServerInfo>>, history: Arc<RwLock<History>>) {
+        loop {
+            let info = self.collect_due().clone();
+            history
+                .write()
+                .expect("History lock poisoned.")
+                .push(info.clone());
+            *latest.write().expect("Server info lock poisoned.") = info;
+            thread::sleep(self.until_next_due().max(Duration::from_millis(10)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MemoryCollector {
+        interval: Duration,
+        runs: u64,
+    }
+
+    impl Collector for MemoryCollector {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn interval(&self) -> Duration {
+            self.interval
+        }
+
+        fn collect(&mut self) -> Result<Section, String> {
+            self.runs += 1;
+            Ok(Section::Memory {
+                total_ram: 100,
+                used_memory: self.runs,
+            })
+        }
+    }
+
+    struct FailingCollector;
+
+    impl Collector for FailingCollector {
+        fn name(&self) -> &str {
+            "disks"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        fn collect(&mut self) -> Result<Section, String> {
+            Err("no disks".to_string())
+        }
+    }
+
+    struct PanickingCollector;
+
+    impl Collector for PanickingCollector {
+        fn name(&self) -> &str {
+            "network"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        fn collect(&mut self) -> Result<Section, String> {
+            panic!("network went away")
+        }
+    }
+
+    /// Stands in for a stuck systemctl or NFS mount, the first run returns and every later one hangs
+    struct HangingCollector {
+        runs: u64,
+    }
+
+    impl Collector for HangingCollector {
+        fn name(&self) -> &str {
+            "services"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        fn collect(&mut self) -> Result<Section, String> {
+            self.runs += 1;
+            if self.runs > 1 {
+                thread::sleep(Duration::from_secs(3600));
+            }
+            Ok(Section::Services(vec![]))
+        }
+    }
+
+    fn memory_collector(interval: Duration) -> Box<MemoryCollector> {
+        Box::new(MemoryCollector { interval, runs: 0 })
+    }
+
+    #[test]
+    fn failing_collectors_do_not_affect_others() {
+        let mut registry = CollectorRegistry::new(HashMap::new());
+        registry.register(Box::new(FailingCollector));
+        registry.register(Box::new(PanickingCollector));
+        registry.register(memory_collector(Duration::ZERO));
+
+        let info = registry.collect_due();
+        assert_eq!(info.total_ram, 100);
+        assert_eq!(info.used_memory, 1);
+        assert!(info.disks.is_empty());
+        assert!(info.net_interfaces.is_empty());
+    }
+
+    #[test]
+    fn hanging_collectors_do_not_block_others() {
+        let mut registry = CollectorRegistry::new(HashMap::new());
+        registry.deadline = Duration::from_millis(100);
+        registry.min_interval = Duration::ZERO;
+        registry.register(Box::new(HangingCollector { runs: 0 }));
+        registry.register(memory_collector(Duration::ZERO));
+        assert_eq!(registry.collect_due().used_memory, 1);
+
+        let started = Instant::now();
+        assert_eq!(registry.collect_due().used_memory, 2);
+        assert_eq!(registry.collect_due().used_memory, 3);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        // the hanging collector is not started again while it runs
+        assert!(registry.collectors[0].running);
+    }
+
+    #[test]
+    fn collectors_only_run_when_due() {
+        let mut registry = CollectorRegistry::new(HashMap::new());
+        registry.register(memory_collector(Duration::from_secs(3600)));
+
+        assert_eq!(registry.collect_due().used_memory, 1);
+        assert_eq!(registry.collect_due().used_memory, 1);
+        assert!(registry.until_next_due() > Duration::from_secs(3000));
+    }
+
+    #[test]
+    fn config_overrides_interval_and_enabled() {
+        let config = HashMap::from([
+            (
+                "memory".to_string(),
+                CollectorConfig {
+                    enabled: true,
+                    interval_ms: Some(0),
+                },
+            ),
+            (
+                "disks".to_string(),
+                CollectorConfig {
+                    enabled: false,
+                    interval_ms: None,
+                },
+            ),
+        ]);
+        let mut registry = CollectorRegistry::new(config);
+        registry.register(memory_collector(Duration::from_secs(3600)));
+        registry.register(Box::new(FailingCollector));
+        assert_eq!(registry.collectors.len(), 1);
+        // 0 would keep the run loop spinning
+        assert_eq!(registry.collectors[0].interval, MIN_INTERVAL);
+
+        assert_eq!(registry.collect_due().used_memory, 1);
+        thread::sleep(MIN_INTERVAL);
+        assert_eq!(registry.collect_due().used_memory, 2);
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Deserializer};
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::discovery::DISCOVERY_PORT;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Settings read from the server config file, every field falls back to its default when missing.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen: ListenConfig,
+    pub containers: ContainerConfig,
+    pub services: ServiceConfig,
+    /// per collector overrides, keyed by collector name
+    pub collectors: HashMap<String, CollectorConfig>,
+    /// commands whose output is reported as custom metrics
+    pub scripts: Vec<ScriptConfig>,
+    pub logs: LogConfig,
+    pub auth: AuthConfig,
+    pub agent: AgentConfig,
+    pub discovery: DiscoveryConfig,
+    pub history: HistoryConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenConfig {
+    pub address: String,
+    /// clients connecting while this many are connected get an error and are disconnected
+    pub max_connections: usize,
+    /// clients that do not read what they asked for within this long are disconnected
+    pub write_timeout_ms: u64,
+    /// how long clients get to disconnect on SIGTERM/SIGINT before the server exits anyway
+    pub shutdown_grace_ms: u64,
+    /// frames at least this large are compressed for clients that asked for compression
+    pub compression_threshold: usize,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            address: "0.0.0.0:8111".to_string(),
+            max_connections: 1024,
+            write_timeout_ms: 10000,
+            shutdown_grace_ms: 5000,
+            compression_threshold: Codec::DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainerConfig {
+    /// where the cgroup filesystem is mounted, point this at a bind mount of the hosts cgroup fs to see other containers
+    pub cgroup_root: PathBuf,
+    /// report cpu/memory/io usage of the other containers next to ours
+    pub list_siblings: bool,
+}
+
+impl Default for ContainerConfig {
+    fn default() -> Self {
+        Self {
+            cgroup_root: PathBuf::from("/sys/fs/cgroup"),
+            list_siblings: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceConfig {
+    /// systemd units to report the state of, e.g. "nginx.service"
+    pub units: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectorConfig {
+    pub enabled: bool,
+    /// overrides the collectors own interval
+    pub interval_ms: Option<u64>,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_ms: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// name of the collector and the metric group in the client
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub format: ScriptOutputFormat,
+    #[serde(default = "default_script_interval_ms")]
+    pub interval_ms: u64,
+    /// the command is killed if it has not finished after this long
+    #[serde(default = "default_script_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptOutputFormat {
+    /// "key=value" or "key: value" per line
+    #[default]
+    KeyValue,
+    /// a json object, nested values are flattened to dotted names
+    Json,
+}
+
+fn default_script_interval_ms() -> u64 {
+    10000
+}
+
+fn default_script_timeout_ms() -> u64 {
+    5000
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// files clients may tail, nothing else on the server can be read through the protocol
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// when set, clients have to send this token before they receive anything
+    pub token: Option<String>,
+}
+
+/// Infos kept for clients asking for history or exports.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// infos kept, none when 0
+    pub len: usize,
+    /// where the history is saved on shutdown and read from on start, it is only kept in memory when none
+    pub file: Option<PathBuf>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            len: 3600,
+            file: None,
+        }
+    }
+}
+
+/// Answering clients looking for servers on the local network.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    /// the udp port probes are received on
+    pub port: u16,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            // the announcement tells anyone asking the host name, so it is opt in
+            enabled: false,
+            port: DISCOVERY_PORT,
+        }
+    }
+}
+
+/// Pushing to a hub instead of, or next to, being connected to.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// address of the hub to push to, the server only runs as an agent when this is set
+    pub endpoint: Option<String>,
+    /// what the host is listed as on the hub, defaults to the host name
+    pub name: Option<String>,
+    /// the `[agents] token` of the hub
+    pub token: String,
+    /// the `[auth] token` of the hub, when it has one
+    pub auth_token: Option<String>,
+    /// how often an info is taken and pushed
+    pub interval_ms: u64,
+    /// infos kept while the hub can not be reached, the oldest are dropped first
+    pub buffer_len: usize,
+    /// json, msgpack, cbor or bincode
+    #[serde(deserialize_with = "parse")]
+    pub encoding: Encoding,
+    /// none, deflate or zstd
+    #[serde(deserialize_with = "parse")]
+    pub compression: Compression,
+    /// connect with tls, e.g. to a tls terminating proxy in front of the hub
+    pub tls: bool,
+    /// pem file with the certificates to trust for tls, instead of the usual web roots
+    pub tls_ca: Option<PathBuf>,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            name: None,
+            token: String::new(),
+            auth_token: None,
+            interval_ms: 1000,
+            buffer_len: 3600,
+            encoding: Encoding::Bincode,
+            compression: Compression::None,
+            tls: false,
+            tls_ca: None,
+        }
+    }
+}
+
+/// Reads a value the way the command line flags of the clients take it, e.g. "bincode"
+pub fn parse<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<T, D::Error>
+where
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// The config in file_name, the defaults when there is no such file.
+///
+/// A file that can not be read or parsed is an error rather than the defaults, which would e.g. turn auth off.
+pub fn read_from_file(file_name: &str) -> Result<ServerConfig, String> {
+    let file_as_string = match fs::read_to_string(file_name) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("Using default config, {file_name} does not exist");
+            return Ok(ServerConfig::default());
+        }
+        Err(e) => {
+            return Err(e.to_string());
+        }
+    };
+    toml::from_str(&file_as_string).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_config_parses() {
+        let example = include_str!("../../EXAMPLE-server_info_server.toml");
+        let config: ServerConfig = toml::from_str(example).unwrap();
+        assert_eq!(config.services.units.len(), 2);
+        assert_eq!(config.scripts[1].format, ScriptOutputFormat::Json);
+        assert_eq!(config.scripts[1].timeout_ms, 5000);
+        assert_eq!(config.agent.endpoint, None);
+        assert_eq!(config.agent.encoding, Encoding::Bincode);
+        assert_eq!(config.agent.compression, Compression::Zstd);
+        assert_eq!(
+            config.history.file,
+            Some(PathBuf::from("/var/lib/server_info/history.bin"))
+        );
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config: ServerConfig = toml::from_str("").unwrap();
+        assert_eq!(
+            config.containers.cgroup_root,
+            PathBuf::from("/sys/fs/cgroup")
+        );
+        assert!(config.services.units.is_empty());
+        assert!(config.auth.token.is_none());
+        assert_eq!(config.listen.max_connections, 1024);
+        assert_eq!(config.listen.compression_threshold, 1024);
+        assert!(!config.discovery.enabled);
+        assert_eq!(config.history.len, 3600);
+        assert_eq!(config.history.file, None);
+    }
+
+    #[test]
+    fn configs_that_do_not_parse_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server_info_server.toml");
+        let read = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            read_from_file(path.to_str().unwrap())
+        };
+        assert!(read("[auth]\ntoken = \"secret\"").is_ok());
+        assert!(read("[auth\ntoken = \"secret\"").is_err());
+        // a typo would otherwise leave auth turned off
+        assert!(read("[auth]\ntokn = \"secret\"").is_err());
+
+        let missing = dir.path().join("missing.toml");
+        let config = read_from_file(missing.to_str().unwrap()).unwrap();
+        assert!(config.auth.token.is_none());
+    }
+}
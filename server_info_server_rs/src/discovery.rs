@@ -0,0 +1,167 @@
+use crate::config::DiscoveryConfig;
+use server_info_packets::discovery::{Announcement, DiscoveryMessage, PROTOCOL_VERSION};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+/// Answers sent to one address per second, so probes with a spoofed sender can not flood it
+const ANSWERS_PER_SECOND: u32 = 5;
+
+/// Senders the rate limit keeps track of, probes from new ones are ignored while this many were heard from in the last second
+const MAX_SENDERS: usize = 4096;
+
+/// Counts the answers sent to every sender within the current second.
+#[derive(Default)]
+struct RateLimit {
+    /// (start of the second, answers sent in it) by sender
+    senders: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimit {
+    /// Whether sender may be answered at now, counting the answer when it may
+    fn allow(&mut self, sender: IpAddr, now: Instant) -> bool {
+        let second = Duration::from_secs(1);
+        if self.senders.len() >= MAX_SENDERS {
+            self.senders
+                .retain(|_, (start, _)| now.duration_since(*start) < second);
+            if self.senders.len() >= MAX_SENDERS && !self.senders.contains_key(&sender) {
+                return false;
+            }
+        }
+        let (start, answers) = self.senders.entry(sender).or_insert((now, 0));
+        if now.duration_since(*start) >= second {
+            *start = now;
+            *answers = 0;
+        }
+        *answers += 1;
+        *answers <= ANSWERS_PER_SECOND
+    }
+}
+
+/// Answers the probes received on socket with the announcement, until the task is dropped.
+///
+/// Probes shorter than the answer are ignored, and every sender is only answered a few times a second.
+pub async fn answer_probes(socket: UdpSocket, announcement: Announcement) {
+    let answer = DiscoveryMessage::Announcement(announcement).encode();
+    let mut rate_limit = RateLimit::default();
+    // probes are padded to PROBE_LEN, anything much larger is not one
+    let mut datagram = [0; 2048];
+
+    loop {
+        let (length, sender) = match socket.recv_from(&mut datagram).await {
+            Ok(received) => received,
+            Err(e) => {
+                println!("Unable to receive discovery probes: {e}");
+                return;
+            }
+        };
+        if length < answer.len() {
+            continue;
+        }
+        if let Some(DiscoveryMessage::Probe) = DiscoveryMessage::decode(&datagram[..length]) {
+            if !rate_limit.allow(sender.ip(), Instant::now()) {
+                continue;
+            }
+            if let Err(e) = socket.send_to(&answer, sender).await {
+                println!("Unable to answer the discovery probe of {sender}: {e}");
+            }
+        }
+    }
+}
+
+/// Starts answering probes when discovery is enabled, logging why when it can not.
+///
+/// The port and protocol version of the announcement are filled in.
+pub async fn start(config: &DiscoveryConfig, listen_address: &str, announcement: Announcement) {
+    if !config.enabled {
+        return;
+    }
+    let port = config.port;
+    let Some(listen_port) = port_of(listen_address) else {
+        println!("Not answering discovery probes, no port in {listen_address}.");
+        return;
+    };
+    match UdpSocket::bind(("0.0.0.0", port)).await {
+        Ok(socket) => {
+            println!("Answering discovery probes on udp port {port}.");
+            let announcement = Announcement {
+                port: listen_port,
+                protocol_version: PROTOCOL_VERSION,
+                ..announcement
+            };
+            tokio::spawn(answer_probes(socket, announcement));
+        }
+        Err(e) => println!("Not answering discovery probes, unable to bind udp port {port}: {e}"),
+    }
+}
+
+/// The port of an address like "0.0.0.0:8111" or "[::]:8111"
+fn port_of(address: &str) -> Option<u16> {
+    address.rsplit_once(':')?.1.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_port_of_listen_addresses() {
+        assert_eq!(port_of("0.0.0.0:8111"), Some(8111));
+        assert_eq!(port_of("[::]:9000"), Some(9000));
+        assert_eq!(port_of("localhost"), None);
+    }
+
+    #[tokio::test]
+    async fn answers_probes_only() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let announcement = Announcement {
+            host_name: "box".to_string(),
+            port: 8111,
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+        tokio::spawn(answer_probes(socket, announcement.clone()));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(b"not a probe", address).await.unwrap();
+        // shorter than the answer
+        client
+            .send_to(&DiscoveryMessage::Probe.encode(), address)
+            .await
+            .unwrap();
+        client
+            .send_to(&DiscoveryMessage::probe(), address)
+            .await
+            .unwrap();
+
+        let mut datagram = [0; 512];
+        let (length, _) =
+            tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut datagram))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            DiscoveryMessage::decode(&datagram[..length]),
+            Some(DiscoveryMessage::Announcement(announcement))
+        );
+        // the only answer
+        let second =
+            tokio::time::timeout(Duration::from_millis(200), client.recv_from(&mut datagram)).await;
+        assert!(second.is_err());
+    }
+
+    #[test]
+    fn limits_the_answers_per_sender() {
+        let mut rate_limit = RateLimit::default();
+        let now = Instant::now();
+        let sender: IpAddr = "192.168.1.10".parse().unwrap();
+        for _ in 0..ANSWERS_PER_SECOND {
+            assert!(rate_limit.allow(sender, now));
+        }
+        assert!(!rate_limit.allow(sender, now));
+        assert!(rate_limit.allow("192.168.1.11".parse().unwrap(), now));
+        assert!(rate_limit.allow(sender, now + Duration::from_secs(1)));
+    }
+}
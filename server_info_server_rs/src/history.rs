@@ -0,0 +1,137 @@
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::protocol::{read_message, write_message};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// History files are frames like on the wire, json so they survive upgrades and every one compressed
+const HISTORY_CODEC: Codec = Codec {
+    encoding: Encoding::Json,
+    compression: Compression::Zstd,
+    threshold: 0,
+};
+
+/// The recent infos of one host, for ClientRequest::History and ClientRequest::Export.
+pub struct History {
+    /// oldest first
+    infos: VecDeque<ServerInfo>,
+    /// infos kept, none when 0
+    len: usize,
+}
+
+impl History {
+    pub fn new(len: usize) -> Self {
+        Self {
+            infos: VecDeque::new(),
+            len,
+        }
+    }
+
+    /// Adds an info, dropping the oldest one when full.
+    ///
+    /// Dates are in seconds, an info dated the same as the last one replaces it.
+    pub fn push(&mut self, info: ServerInfo) {
+        if self.len == 0 {
+            return;
+        }
+        if self.infos.back().is_some_and(|last| last.date == info.date) {
+            self.infos.pop_back();
+        }
+        if self.infos.len() == self.len {
+            self.infos.pop_front();
+        }
+        self.infos.push_back(info);
+    }
+
+    /// The infos dated from..=to, oldest first
+    pub fn range(&self, from: i64, to: i64) -> Vec<ServerInfo> {
+        self.infos
+            .iter()
+            .filter(|info| (from..=to).contains(&info.date))
+            .cloned()
+            .collect()
+    }
+
+    /// Reads the infos saved to path, keeping the newest len, empty when nothing was saved yet
+    pub fn load(path: &Path, len: usize) -> Result<Self, String> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new(len)),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut reader = BufReader::new(file);
+        let mut history = Self::new(len);
+        loop {
+            match read_message(&mut reader, HISTORY_CODEC) {
+                Ok(info) => history.push(info),
+                // the end of the file, or of a file cut short, everything before it is still good
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(history)
+    }
+
+    /// Writes every info to path, replacing it only once all of them were written
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let partial = path.with_extension("partial");
+        let file = File::create(&partial).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        for info in &self.infos {
+            write_message(&mut writer, HISTORY_CODEC, info).map_err(|e| e.to_string())?;
+        }
+        fs::rename(&partial, path).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(date: i64) -> ServerInfo {
+        ServerInfo {
+            date,
+            ..Default::default()
+        }
+    }
+
+    fn dates(infos: &[ServerInfo]) -> Vec<i64> {
+        infos.iter().map(|info| info.date).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_infos() {
+        let mut history = History::new(3);
+        for date in [1, 2, 2, 3, 4] {
+            history.push(info(date));
+        }
+        assert_eq!(dates(&history.range(0, 10)), vec![2, 3, 4]);
+        assert_eq!(dates(&history.range(3, 3)), vec![3]);
+
+        let mut disabled = History::new(0);
+        disabled.push(info(1));
+        assert!(disabled.range(0, 10).is_empty());
+    }
+
+    #[test]
+    fn survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        let mut history = History::new(10);
+        for date in 1..=5 {
+            history.push(info(date));
+        }
+        history.save(&path).unwrap();
+
+        let loaded = History::load(&path, 3).unwrap();
+        assert_eq!(dates(&loaded.range(0, 10)), vec![3, 4, 5]);
+
+        let missing = History::load(Path::new("/nonexistent/history"), 3).unwrap();
+        assert!(missing.range(0, 10).is_empty());
+    }
+}
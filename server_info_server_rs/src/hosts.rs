@@ -0,0 +1,208 @@
+use crate::history::History;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::server_info_packet::ServerInfo;
+use std::sync::{RwLock, RwLockReadGuard};
+
+/// The latest info and recent history of every server a hub collects from,
+/// in the order they were configured followed by the agents in the order they first registered.
+pub struct Hosts {
+    hosts: RwLock<Vec<Host>>,
+    /// infos kept per host
+    history_len: usize,
+}
+
+struct Host {
+    status: HostStatus,
+    /// reports on its own instead of being an upstream
+    agent: bool,
+    latest: Option<ServerInfo>,
+    /// the latest info included
+    history: History,
+}
+
+impl Hosts {
+    /// Takes (name, address) of every host
+    pub fn new(hosts: impl IntoIterator<Item = (String, String)>, history_len: usize) -> Self {
+        let hosts = hosts
+            .into_iter()
+            .map(|(name, address)| Host {
+                status: HostStatus {
+                    name,
+                    address,
+                    ..Default::default()
+                },
+                agent: false,
+                latest: None,
+                history: History::new(history_len),
+            })
+            .collect();
+        Self {
+            hosts: RwLock::new(hosts),
+            history_len,
+        }
+    }
+
+    pub fn list(&self) -> Vec<HostStatus> {
+        self.read().iter().map(|host| host.status.clone()).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.read().iter().any(|host| host.status.name == name)
+    }
+
+    /// The latest info of the host called name, or of the first host when name is none.
+    ///
+    /// Hosts that never answered get an empty info with just their name.
+    pub fn latest(&self, name: Option<&str>) -> ServerInfo {
+        match find(&self.read(), name) {
+            Some(host) => host.latest.clone().unwrap_or_else(|| ServerInfo {
+                host_name: host.status.name.to_string(),
+                ..Default::default()
+            }),
+            None => ServerInfo::default(),
+        }
+    }
+
+    /// The kept infos of the host called name (the first when none) dated from..=to, oldest first
+    pub fn history(&self, name: Option<&str>, from: i64, to: i64) -> Vec<ServerInfo> {
+        find(&self.read(), name)
+            .map(|host| host.history.range(from, to))
+            .unwrap_or_default()
+    }
+
+    /// Marks the agent reporting the host called name as connected, adding the host the first time.
+    ///
+    /// Fails when name is an upstream, or another agent for it is connected.
+    pub fn register(&self, name: &str) -> Result<(), String> {
+        let mut hosts = self.hosts.write().expect("Hosts lock poisoned.");
+        match hosts.iter_mut().find(|host| host.status.name == name) {
+            Some(host) if !host.agent => Err(format!("{name} is an upstream of the hub.")),
+            Some(host) if host.status.connected => {
+                Err(format!("An agent for {name} is already connected."))
+            }
+            Some(host) => {
+                host.status.connected = true;
+                host.status.error = None;
+                Ok(())
+            }
+            None => {
+                hosts.push(Host {
+                    status: HostStatus {
+                        name: name.to_string(),
+                        address: "agent".to_string(),
+                        connected: true,
+                        ..Default::default()
+                    },
+                    agent: true,
+                    latest: None,
+                    history: History::new(self.history_len),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Stores an info received from the host called name
+    pub fn update(&self, name: &str, mut info: ServerInfo) {
+        // the hub can not tail the logs of its hosts
+        info.log_files.clear();
+
+        let mut hosts = self.hosts.write().expect("Hosts lock poisoned.");
+        let Some(host) = hosts.iter_mut().find(|host| host.status.name == name) else {
+            return;
+        };
+        host.status.connected = true;
+        host.status.last_seen = info.date;
+        host.status.error = None;
+        host.history.push(info.clone());
+        host.latest = Some(info);
+    }
+
+    /// Marks the host called name as disconnected, keeping its latest info and history
+    pub fn disconnected(&self, name: &str, error: String) {
+        let mut hosts = self.hosts.write().expect("Hosts lock poisoned.");
+        if let Some(host) = hosts.iter_mut().find(|host| host.status.name == name) {
+            host.status.connected = false;
+            host.status.error = Some(error);
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Vec<Host>> {
+        self.hosts.read().expect("Hosts lock poisoned.")
+    }
+}
+
+/// The host called name, or the first one when name is none
+fn find<'a>(hosts: &'a [Host], name: Option<&str>) -> Option<&'a Host> {
+    match name {
+        Some(name) => hosts.iter().find(|host| host.status.name == name),
+        None => hosts.first(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(host_name: &str, date: i64) -> ServerInfo {
+        ServerInfo {
+            host_name: host_name.to_string(),
+            date,
+            log_files: vec!["/var/log/syslog".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_infos_of_every_host() {
+        let hosts = Hosts::new(
+            [
+                ("web".to_string(), "web:8111".to_string()),
+                ("db".to_string(), "db:8111".to_string()),
+            ],
+            2,
+        );
+        assert_eq!(hosts.latest(None).host_name, "web");
+        assert!(!hosts.list()[0].connected);
+
+        for date in 1..=3 {
+            hosts.update("db", info("db.internal", date));
+        }
+        hosts.update("unknown", info("unknown", 1));
+
+        assert_eq!(hosts.latest(Some("db")).date, 3);
+        assert!(hosts.latest(Some("db")).log_files.is_empty());
+        let dates: Vec<i64> = hosts
+            .history(Some("db"), 0, 10)
+            .iter()
+            .map(|info| info.date)
+            .collect();
+        assert_eq!(dates, vec![2, 3]);
+        assert_eq!(hosts.history(Some("db"), 3, 3).len(), 1);
+        assert!(hosts.history(None, 0, 10).is_empty());
+
+        assert!(hosts.register("db").is_err());
+        hosts.disconnected("db", "connection reset".to_string());
+        let db = &hosts.list()[1];
+        assert!(!db.connected);
+        assert_eq!(db.last_seen, 3);
+        assert_eq!(db.error.as_deref(), Some("connection reset"));
+        assert_eq!(hosts.latest(Some("db")).date, 3);
+    }
+
+    #[test]
+    fn agents_register_once_at_a_time() {
+        let hosts = Hosts::new([], 10);
+        hosts.register("nat").unwrap();
+        assert!(hosts.register("nat").is_err());
+        hosts.update("nat", info("nat.internal", 1));
+        assert_eq!(hosts.latest(None).host_name, "nat.internal");
+
+        hosts.disconnected("nat", "timed out".to_string());
+        hosts.register("nat").unwrap();
+        let listed = hosts.list();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].connected);
+        assert_eq!(listed[0].address, "agent");
+    }
+}
@@ -0,0 +1,127 @@
+use server_info_packets::sensor_info::{Fan, Power};
+use server_info_server_rs::sysfs::read_trimmed;
+use std::fs;
+use std::path::Path;
+
+/// Fan speeds and power readings of every hwmon device under root, usually /sys/class/hwmon.
+///
+/// Temperatures are left to sysinfo which reads the same devices, empty where root does not exist.
+pub fn read(root: &Path) -> (Vec<Fan>, Vec<Power>) {
+    let mut fans = vec![];
+    let mut power = vec![];
+    let Ok(entries) = fs::read_dir(root) else {
+        return (fans, power);
+    };
+    let mut devices: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+    devices.sort_by_key(|entry| entry.file_name());
+
+    for device in devices {
+        let dir = device.path();
+        let name = read_trimmed(&dir.join("name"))
+            .unwrap_or_else(|| device.file_name().to_string_lossy().to_string());
+        for (index, rpm) in inputs(&dir, "fan", &["_input"]) {
+            fans.push(Fan {
+                label: label(&dir, &name, "fan", index),
+                rpm: rpm as u64,
+            });
+        }
+        // drivers report either the instant or the average power, in microwatts
+        for (index, microwatts) in inputs(&dir, "power", &["_input", "_average"]) {
+            power.push(Power {
+                label: label(&dir, &name, "power", index),
+                watts: (microwatts as f64 / 1_000_000.0) as f32,
+            });
+        }
+    }
+    (fans, power)
+}
+
+/// The readings of <kind><index><suffix> files in dir, by index, the first suffix found wins
+fn inputs(dir: &Path, kind: &str, suffixes: &[&str]) -> Vec<(u32, i64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut indexes: Vec<u32> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let rest = file_name.strip_prefix(kind)?;
+            let suffix = suffixes.iter().find(|suffix| rest.ends_with(*suffix))?;
+            rest.strip_suffix(suffix)?.parse().ok()
+        })
+        .collect();
+    indexes.sort();
+    indexes.dedup();
+
+    indexes
+        .into_iter()
+        .filter_map(|index| {
+            let value = suffixes.iter().find_map(|suffix| {
+                read_trimmed(&dir.join(format!("{kind}{index}{suffix}")))?
+                    .parse()
+                    .ok()
+            })?;
+            Some((index, value))
+        })
+        .collect()
+}
+
+/// The label the driver gives a reading, or the device name and the reading, e.g. "amdgpu fan1"
+fn label(dir: &Path, name: &str, kind: &str, index: u32) -> String {
+    match read_trimmed(&dir.join(format!("{kind}{index}_label"))) {
+        Some(label) => format!("{name} {label}"),
+        None => format!("{name} {kind}{index}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn reads_fans_and_power() {
+        let root = tempfile::tempdir().unwrap();
+        let gpu = root.path().join("hwmon1");
+        let board = root.path().join("hwmon0");
+        fs::create_dir_all(&gpu).unwrap();
+        fs::create_dir_all(&board).unwrap();
+        fs::write(gpu.join("name"), "amdgpu\n").unwrap();
+        fs::write(gpu.join("fan1_input"), "1200\n").unwrap();
+        fs::write(gpu.join("power1_average"), "45500000\n").unwrap();
+        fs::write(gpu.join("power1_label"), "PPT\n").unwrap();
+        fs::write(gpu.join("temp1_input"), "50000\n").unwrap();
+        fs::write(board.join("name"), "nct6775\n").unwrap();
+        fs::write(board.join("fan2_input"), "800\n").unwrap();
+        fs::write(board.join("fan10_input"), "0\n").unwrap();
+        fs::write(board.join("fan3_input"), "not a number\n").unwrap();
+
+        let (fans, power) = read(root.path());
+
+        let fans: Vec<(&str, u64)> = fans
+            .iter()
+            .map(|fan| (fan.label.as_str(), fan.rpm))
+            .collect();
+        assert_eq!(
+            fans,
+            vec![
+                ("nct6775 fan2", 800),
+                ("nct6775 fan10", 0),
+                ("amdgpu fan1", 1200)
+            ]
+        );
+        assert_eq!(
+            power,
+            vec![Power {
+                label: "amdgpu PPT".to_string(),
+                watts: 45.5,
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_roots_have_no_readings() {
+        let (fans, power) = read(&PathBuf::from("/nonexistent/hwmon"));
+        assert!(fans.is_empty() && power.is_empty());
+    }
+}
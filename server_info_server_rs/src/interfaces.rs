@@ -0,0 +1,165 @@
+use crate::sysfs::read_trimmed;
+use server_info_packets::network_info::{InterfaceKind, NetworkInterfaceInfo};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Set in /sys/class/net/<interface>/flags while the interface is turned on
+const IFF_UP: u32 = 0x1;
+
+/// Set in /sys/class/net/<interface>/flags for loopback interfaces
+const IFF_LOOPBACK: u32 = 0x8;
+
+/// The interfaces under class_net, usually /sys/class/net, with their addresses from getifaddrs.
+///
+/// Byte counts are left at 0, the network collector takes those from sysinfo.
+pub fn interfaces(class_net: &Path) -> Vec<NetworkInterfaceInfo> {
+    let addresses: Vec<(String, IpAddr)> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|interface| {
+            let address = interface.ip();
+            (interface.name, address)
+        })
+        .collect();
+    read_interfaces(class_net, &addresses)
+}
+
+/// The interfaces under class_net given the addresses of each by name
+fn read_interfaces(class_net: &Path, addresses: &[(String, IpAddr)]) -> Vec<NetworkInterfaceInfo> {
+    let Ok(entries) = fs::read_dir(class_net) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    let flags: Vec<u32> = names
+        .iter()
+        .map(|name| {
+            read_trimmed(&class_net.join(name).join("flags"))
+                .and_then(|flags| u32::from_str_radix(flags.trim_start_matches("0x"), 16).ok())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    names
+        .iter()
+        .zip(flags)
+        .map(|(name, flags)| {
+            let dir = class_net.join(name);
+            let statistic = |counter: &str| {
+                read_trimmed(&dir.join("statistics").join(counter))
+                    .and_then(|count| count.parse().ok())
+                    .unwrap_or_default()
+            };
+            let kind = if flags & IFF_LOOPBACK != 0 {
+                InterfaceKind::Loopback
+            } else if dir.join("device").exists() {
+                InterfaceKind::Physical
+            } else {
+                // veth, bridges, tun and the like have no device behind them
+                InterfaceKind::Virtual
+            };
+            NetworkInterfaceInfo {
+                name: name.to_string(),
+                mac: read_trimmed(&dir.join("address")).filter(|mac| mac != "00:00:00:00:00:00"),
+                mtu: read_trimmed(&dir.join("mtu")).and_then(|mtu| mtu.parse().ok()),
+                // carrier can not be read while the interface is down
+                up: Some(
+                    flags & IFF_UP != 0
+                        && read_trimmed(&dir.join("carrier")).as_deref() == Some("1"),
+                ),
+                // -1 or unreadable while the link is down
+                speed: read_trimmed(&dir.join("speed"))
+                    .and_then(|speed| speed.parse::<i64>().ok())
+                    .filter(|speed| *speed > 0)
+                    .map(|speed| speed as u32),
+                addresses: addresses
+                    .iter()
+                    .filter(|(interface, _)| interface == name)
+                    .map(|(_, address)| *address)
+                    .collect(),
+                rx_errors: statistic("rx_errors"),
+                tx_errors: statistic("tx_errors"),
+                rx_dropped: statistic("rx_dropped"),
+                tx_dropped: statistic("tx_dropped"),
+                kind,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::write;
+
+    #[test]
+    fn reads_addresses_link_state_and_counters() {
+        let root = tempfile::tempdir().unwrap();
+        let class_net = root.path().join("class/net");
+        write(&class_net.join("eth0/device/vendor"), "0x8086\n");
+        write(&class_net.join("eth0/address"), "52:54:00:12:34:56\n");
+        write(&class_net.join("eth0/speed"), "1000\n");
+        write(&class_net.join("eth0/mtu"), "1500\n");
+        write(&class_net.join("eth0/flags"), "0x1003\n");
+        write(&class_net.join("eth0/carrier"), "1\n");
+        write(&class_net.join("eth0/statistics/rx_errors"), "3\n");
+        write(&class_net.join("eth0/statistics/tx_dropped"), "7\n");
+        write(&class_net.join("lo/address"), "00:00:00:00:00:00\n");
+        write(&class_net.join("lo/speed"), "-1\n");
+        write(&class_net.join("lo/flags"), "0x9\n");
+        write(&class_net.join("lo/carrier"), "1\n");
+        write(&class_net.join("veth1/flags"), "0x1002\n");
+        let addresses: Vec<(String, IpAddr)> = [
+            ("eth0", "192.0.2.2"),
+            ("lo", "127.0.0.1"),
+            ("eth0", "fd00::2"),
+            ("lo", "::1"),
+        ]
+        .into_iter()
+        .map(|(name, address)| (name.to_string(), address.parse().unwrap()))
+        .collect();
+        let interfaces = read_interfaces(&class_net, &addresses);
+
+        assert_eq!(
+            interfaces,
+            vec![
+                NetworkInterfaceInfo {
+                    name: "eth0".to_string(),
+                    mac: Some("52:54:00:12:34:56".to_string()),
+                    mtu: Some(1500),
+                    up: Some(true),
+                    speed: Some(1000),
+                    addresses: vec!["192.0.2.2".parse().unwrap(), "fd00::2".parse().unwrap()],
+                    rx_errors: 3,
+                    tx_dropped: 7,
+                    kind: InterfaceKind::Physical,
+                    ..Default::default()
+                },
+                NetworkInterfaceInfo {
+                    name: "lo".to_string(),
+                    up: Some(true),
+                    addresses: vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()],
+                    kind: InterfaceKind::Loopback,
+                    ..Default::default()
+                },
+                NetworkInterfaceInfo {
+                    name: "veth1".to_string(),
+                    up: Some(false),
+                    kind: InterfaceKind::Virtual,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_roots_have_no_interfaces() {
+        assert!(interfaces(Path::new("/nonexistent/net")).is_empty());
+    }
+}
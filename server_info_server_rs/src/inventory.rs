@@ -0,0 +1,205 @@
+use crate::interfaces::interfaces;
+use crate::sysfs::read_trimmed;
+use chrono::Utc;
+use server_info_packets::inventory::{BlockDevice, CpuInventory, Inventory, MemoryModule, Nic};
+use std::fs;
+use std::path::Path;
+use sysinfo::{CpuExt, System, SystemExt};
+
+/// The SMBIOS structure describing a memory slot
+const SMBIOS_MEMORY_DEVICE: u8 = 17;
+
+/// Takes the inventory of this machine from sysinfo and the files under sys, usually /sys
+pub fn collect(sys: &Path) -> Inventory {
+    let mut system = System::new();
+    system.refresh_cpu();
+    system.refresh_memory();
+
+    let cpu = system.cpus().first();
+    Inventory {
+        date: Utc::now().timestamp(),
+        host_name: system.host_name().unwrap_or_default(),
+        os_version: system.long_os_version().unwrap_or_default(),
+        kernel_version: system.kernel_version().unwrap_or_default(),
+        cpu: CpuInventory {
+            brand: cpu.map(|cpu| cpu.brand().to_string()).unwrap_or_default(),
+            vendor: cpu
+                .map(|cpu| cpu.vendor_id().to_string())
+                .unwrap_or_default(),
+            physical_cores: system.physical_core_count(),
+            logical_cores: system.cpus().len(),
+        },
+        total_memory: system.total_memory(),
+        total_swap: system.total_swap(),
+        memory_modules: memory_modules(&sys.join("firmware/dmi/entries")),
+        block_devices: block_devices(&sys.join("block")),
+        nics: nics(&sys.join("class/net")),
+    }
+}
+
+/// The populated slots listed in the SMBIOS tables under dir, empty when they can not be read
+fn memory_modules(dir: &Path) -> Vec<MemoryModule> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut raws: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&format!("{SMBIOS_MEMORY_DEVICE}-"))
+        })
+        .map(|entry| entry.path().join("raw"))
+        .collect();
+    raws.sort();
+    raws.iter()
+        .filter_map(|raw| parse_memory_device(&fs::read(raw).ok()?))
+        .collect()
+}
+
+/// A memory device structure as SMBIOS lays it out, none for an empty slot
+fn parse_memory_device(raw: &[u8]) -> Option<MemoryModule> {
+    let word = |offset: usize| {
+        Some(u16::from_le_bytes([
+            *raw.get(offset)?,
+            *raw.get(offset + 1)?,
+        ]))
+    };
+    if *raw.first()? != SMBIOS_MEMORY_DEVICE {
+        return None;
+    }
+    let length = *raw.get(1)? as usize;
+
+    let size = match word(0x0C)? {
+        // empty or unknown
+        0 | 0xFFFF => return None,
+        // too large for the field, the size is in the extended field in MB
+        0x7FFF => {
+            let extended = u32::from_le_bytes(raw.get(0x1C..0x20)?.try_into().ok()?);
+            (extended & 0x7FFF_FFFF) as u64 * 1024 * 1024
+        }
+        // the highest bit says KB rather than MB
+        size if size & 0x8000 != 0 => (size & 0x7FFF) as u64 * 1024,
+        size => size as u64 * 1024 * 1024,
+    };
+    let speed = match length > 0x16 {
+        true => word(0x15).filter(|speed| *speed != 0).map(u32::from),
+        false => None,
+    };
+
+    // strings follow the formatted part, numbered from 1
+    let strings: Vec<String> = raw
+        .get(length..)?
+        .split(|byte| *byte == 0)
+        .take_while(|string| !string.is_empty())
+        .map(|string| String::from_utf8_lossy(string).trim().to_string())
+        .collect();
+    let locator = match *raw.get(0x10)? {
+        0 => String::new(),
+        index => strings.get(index as usize - 1).cloned().unwrap_or_default(),
+    };
+
+    Some(MemoryModule {
+        locator,
+        size,
+        speed,
+    })
+}
+
+/// The disks under dir, usually /sys/block, leaving out devices without hardware behind them like loop and zram
+fn block_devices(dir: &Path) -> Vec<BlockDevice> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut devices: Vec<BlockDevice> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("device").exists())
+        .map(|entry| {
+            let path = entry.path();
+            BlockDevice {
+                name: entry.file_name().to_string_lossy().to_string(),
+                model: read_trimmed(&path.join("device/model")),
+                // virtio disks have the serial next to the device, nvme and others below it
+                serial: read_trimmed(&path.join("serial"))
+                    .or_else(|| read_trimmed(&path.join("device/serial"))),
+                // always counted in 512 byte sectors, whatever the sector size of the disk
+                size: read_trimmed(&path.join("size"))
+                    .and_then(|sectors| sectors.parse::<u64>().ok())
+                    .unwrap_or_default()
+                    * 512,
+                rotational: read_trimmed(&path.join("queue/rotational")).as_deref() == Some("1"),
+            }
+        })
+        .collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+/// The interfaces under class_net, usually /sys/class/net
+fn nics(class_net: &Path) -> Vec<Nic> {
+    interfaces(class_net)
+        .into_iter()
+        .map(|interface| Nic {
+            name: interface.name,
+            mac: interface.mac,
+            speed: interface.speed,
+            mtu: interface.mtu,
+            addresses: interface.addresses,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::write;
+
+    #[test]
+    fn lists_disks() {
+        let root = tempfile::tempdir().unwrap();
+        let sys = root.path().join("sys");
+        write(&sys.join("block/sda/device/model"), "Samsung SSD 860\n");
+        write(&sys.join("block/sda/device/serial"), "S3Z9NB0K\n");
+        write(&sys.join("block/sda/size"), "1953525168\n");
+        write(&sys.join("block/sda/queue/rotational"), "0\n");
+        write(&sys.join("block/loop0/size"), "0\n");
+
+        let devices = block_devices(&sys.join("block"));
+
+        assert_eq!(
+            devices,
+            vec![BlockDevice {
+                name: "sda".to_string(),
+                model: Some("Samsung SSD 860".to_string()),
+                serial: Some("S3Z9NB0K".to_string()),
+                size: 1953525168 * 512,
+                rotational: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_memory_slots_from_smbios() {
+        let mut raw = vec![0; 0x22];
+        raw[0] = SMBIOS_MEMORY_DEVICE;
+        raw[1] = 0x22;
+        raw[0x0C..0x0E].copy_from_slice(&16384u16.to_le_bytes());
+        raw[0x10] = 2;
+        raw[0x15..0x17].copy_from_slice(&3200u16.to_le_bytes());
+        raw.extend_from_slice(b"P0 CHANNEL A\0DIMM_A1\0\0");
+        assert_eq!(
+            parse_memory_device(&raw),
+            Some(MemoryModule {
+                locator: "DIMM_A1".to_string(),
+                size: 16 * 1024 * 1024 * 1024,
+                speed: Some(3200),
+            })
+        );
+
+        // an empty slot
+        raw[0x0C..0x0E].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(parse_memory_device(&raw), None);
+        assert!(memory_modules(Path::new("/nonexistent/dmi")).is_empty());
+    }
+}
@@ -0,0 +1,14 @@
+//! Serving the protocol, shared by the server and server_info_hub_rs, which serves the infos it collects from other servers.
+//!
+//! The collectors filling the infos of the server are in [collector], other crates can implement and register their own.
+
+pub mod collector;
+pub mod config;
+pub mod discovery;
+pub mod history;
+pub mod hosts;
+pub mod interfaces;
+pub mod inventory;
+pub mod log_tail;
+pub mod server;
+pub mod sysfs;
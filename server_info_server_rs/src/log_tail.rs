@@ -0,0 +1,397 @@
+use regex::{Regex, RegexBuilder};
+use server_info_packets::protocol::LogFilter;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Lines kept in memory per log file
+const BUFFER_LINES: usize = 2000;
+/// Lines sent to a client when it starts tailing a file
+const BACKLOG_LINES: u64 = 200;
+/// How much of the end of a file is read when the server starts, so recent lines are there right away
+const INITIAL_READ_BYTES: u64 = 64 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Longest line kept, a file written without newlines is cut into lines of this many bytes
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// The most recent lines of a file, every line gets an increasing id so readers can keep their place.
+#[derive(Default)]
+pub struct LogBuffer {
+    lines: VecDeque<String>,
+    /// id of the line after the newest one
+    next_id: u64,
+}
+
+impl LogBuffer {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == BUFFER_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.next_id += 1;
+    }
+
+    fn first_id(&self) -> u64 {
+        self.next_id - self.lines.len() as u64
+    }
+
+    /// Lines with an id of at least from, along with the id to continue from
+    fn lines_from(&self, from: u64) -> (impl Iterator<Item = &String>, u64) {
+        let skip = from.saturating_sub(self.first_id()) as usize;
+        (self.lines.iter().skip(skip), self.next_id)
+    }
+}
+
+/// Follows a single file like `tail -F`, reopening it when it is rotated or truncated.
+struct FileTail {
+    path: PathBuf,
+    file: Option<File>,
+    position: u64,
+    /// bytes after the last newline, kept until the line is complete
+    partial: Vec<u8>,
+}
+
+impl FileTail {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            position: 0,
+            partial: vec![],
+        }
+    }
+
+    /// Reads everything appended since the last poll into buffer
+    fn poll(&mut self, buffer: &mut LogBuffer) {
+        if self.file.is_none() {
+            self.open(buffer.next_id == 0);
+        } else if self.is_rotated() {
+            // finish the old file before following the new one
+            self.read_new_lines(buffer);
+            self.open(false);
+        }
+        self.read_new_lines(buffer);
+    }
+
+    fn open(&mut self, first_open: bool) {
+        self.file = File::open(&self.path).ok();
+        self.partial.clear();
+        self.position = 0;
+
+        if let Some(file) = &mut self.file {
+            let length = file.metadata().map(|m| m.len()).unwrap_or_default();
+            if first_open && length > INITIAL_READ_BYTES {
+                self.position = length - INITIAL_READ_BYTES;
+                let _ = file.seek(SeekFrom::Start(self.position));
+                // the first line is most likely cut in half
+                self.skip_to_next_line();
+            }
+        }
+    }
+
+    fn skip_to_next_line(&mut self) {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return,
+        };
+        let mut byte = [0; 1];
+        while let Ok(1) = file.read(&mut byte) {
+            self.position += 1;
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+    }
+
+    /// A file is rotated when the path now points at a different file, or it got shorter than what was read
+    fn is_rotated(&self) -> bool {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return false,
+        };
+        let on_disk = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // moved away and not recreated yet, keep reading the old file
+            Err(_) => return false,
+        };
+        !is_same_file(file, &on_disk) || on_disk.len() < self.position
+    }
+
+    fn read_new_lines(&mut self, buffer: &mut LogBuffer) {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return,
+        };
+
+        let mut data = vec![];
+        let read = match file.read_to_end(&mut data) {
+            Ok(read) => read,
+            Err(_) => return,
+        };
+        self.position += read as u64;
+
+        self.partial.extend_from_slice(&data);
+        while let Some(newline) = self.partial.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line[..line.len() - 1]);
+            buffer.push(line.trim_end_matches('\r').to_string());
+        }
+        while self.partial.len() >= MAX_LINE_BYTES {
+            let line: Vec<u8> = self.partial.drain(..MAX_LINE_BYTES).collect();
+            buffer.push(String::from_utf8_lossy(&line).to_string());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(file: &File, on_disk: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match file.metadata() {
+        Ok(open) => open.ino() == on_disk.ino() && open.dev() == on_disk.dev(),
+        Err(_) => false,
+    }
+}
+
+/// Without inodes only truncation can be detected
+#[cfg(not(unix))]
+fn is_same_file(file: &File, on_disk: &std::fs::Metadata) -> bool {
+    match file.metadata() {
+        Ok(open) => open.len() <= on_disk.len(),
+        Err(_) => false,
+    }
+}
+
+/// The configured log files, each followed on a background thread.
+pub struct LogStore {
+    buffers: HashMap<String, Arc<Mutex<LogBuffer>>>,
+}
+
+impl LogStore {
+    /// Starts following every file in paths, files that do not exist yet are picked up once they appear
+    pub fn spawn(paths: Vec<PathBuf>) -> Arc<Self> {
+        let mut buffers = HashMap::new();
+        let mut tails = vec![];
+
+        for path in paths {
+            let buffer = Arc::new(Mutex::new(LogBuffer::default()));
+            buffers.insert(path.display().to_string(), buffer.clone());
+            tails.push((FileTail::new(path), buffer));
+        }
+
+        if !tails.is_empty() {
+            thread::spawn(move || loop {
+                for (tail, buffer) in &mut tails {
+                    tail.poll(&mut buffer.lock().expect("Log buffer lock poisoned."));
+                }
+                thread::sleep(POLL_INTERVAL);
+            });
+        }
+
+        Arc::new(Self { buffers })
+    }
+
+    /// Names of the files clients may tail
+    pub fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.buffers.keys().cloned().collect();
+        files.sort();
+        files
+    }
+
+    /// Starts tailing a configured file, the first poll returns the most recent lines
+    pub fn subscribe(
+        &self,
+        file: &str,
+        filter: Option<&LogFilter>,
+    ) -> Result<LogSubscription, String> {
+        let buffer = match self.buffers.get(file) {
+            Some(buffer) => buffer.clone(),
+            None => return Err(format!("{file} is not a configured log file.")),
+        };
+
+        let filter = match filter {
+            Some(filter) if !filter.pattern.is_empty() => {
+                let regex = RegexBuilder::new(&filter.pattern)
+                    .case_insensitive(filter.ignore_case)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                Some((regex, filter.invert))
+            }
+            _ => None,
+        };
+
+        let cursor = buffer
+            .lock()
+            .expect("Log buffer lock poisoned.")
+            .next_id
+            .saturating_sub(BACKLOG_LINES);
+
+        Ok(LogSubscription {
+            file: file.to_string(),
+            buffer,
+            filter,
+            cursor,
+        })
+    }
+}
+
+/// A clients position in a log file along with its filter.
+pub struct LogSubscription {
+    pub file: String,
+    buffer: Arc<Mutex<LogBuffer>>,
+    filter: Option<(Regex, bool)>,
+    cursor: u64,
+}
+
+impl LogSubscription {
+    /// Matching lines added since the last poll
+    pub fn poll(&mut self) -> Vec<String> {
+        let buffer = self.buffer.lock().expect("Log buffer lock poisoned.");
+        let (lines, next_id) = buffer.lines_from(self.cursor);
+        let lines = lines
+            .filter(|line| match &self.filter {
+                None => true,
+                Some((regex, invert)) => regex.is_match(line) != *invert,
+            })
+            .cloned()
+            .collect();
+        self.cursor = next_id;
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    /// Reads the file the way the background thread does, without waiting for it
+    fn poll_once(tail: &mut FileTail, buffer: &Arc<Mutex<LogBuffer>>) {
+        tail.poll(&mut buffer.lock().unwrap());
+    }
+
+    fn store_for(path: &Path) -> (LogStore, FileTail, Arc<Mutex<LogBuffer>>) {
+        let buffer = Arc::new(Mutex::new(LogBuffer::default()));
+        let store = LogStore {
+            buffers: HashMap::from([(path.display().to_string(), buffer.clone())]),
+        };
+        (store, FileTail::new(path.to_path_buf()), buffer)
+    }
+
+    #[test]
+    fn follows_appends_and_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "one\ntwo\nthr");
+
+        let (store, mut tail, buffer) = store_for(&path);
+        poll_once(&mut tail, &buffer);
+        let mut subscription = store.subscribe(&path.display().to_string(), None).unwrap();
+        assert_eq!(subscription.poll(), vec!["one", "two"]);
+
+        append(&path, "ee\nfour\n");
+        poll_once(&mut tail, &buffer);
+        assert_eq!(subscription.poll(), vec!["three", "four"]);
+        assert!(subscription.poll().is_empty());
+
+        // logrotate style, the old file is moved away after one more write and a new one is created
+        append(&path, "five\n");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        append(&path, "six\n");
+        poll_once(&mut tail, &buffer);
+        assert_eq!(subscription.poll(), vec!["five", "six"]);
+
+        // copytruncate style
+        fs::write(&path, "").unwrap();
+        poll_once(&mut tail, &buffer);
+        append(&path, "seven\n");
+        poll_once(&mut tail, &buffer);
+        assert_eq!(subscription.poll(), vec!["seven"]);
+    }
+
+    #[test]
+    fn cuts_lines_without_newlines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "start\n");
+        let (store, mut tail, buffer) = store_for(&path);
+        poll_once(&mut tail, &buffer);
+        let mut subscription = store.subscribe(&path.display().to_string(), None).unwrap();
+        assert_eq!(subscription.poll(), vec!["start"]);
+
+        append(&path, &"x".repeat(MAX_LINE_BYTES * 2 + 10));
+        poll_once(&mut tail, &buffer);
+        let lines = subscription.poll();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() == MAX_LINE_BYTES));
+        assert_eq!(tail.partial.len(), 10);
+    }
+
+    #[test]
+    fn filters_like_grep() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("syslog");
+        append(
+            &path,
+            "kernel: ERROR disk\nsshd: login ok\ncron: error job\n",
+        );
+        let (store, mut tail, buffer) = store_for(&path);
+        poll_once(&mut tail, &buffer);
+        let file = path.display().to_string();
+
+        let filter = LogFilter {
+            pattern: "error".to_string(),
+            ignore_case: true,
+            invert: false,
+        };
+        let mut subscription = store.subscribe(&file, Some(&filter)).unwrap();
+        assert_eq!(
+            subscription.poll(),
+            vec!["kernel: ERROR disk", "cron: error job"]
+        );
+
+        let filter = LogFilter {
+            pattern: "error".to_string(),
+            ignore_case: false,
+            invert: true,
+        };
+        let mut subscription = store.subscribe(&file, Some(&filter)).unwrap();
+        assert_eq!(
+            subscription.poll(),
+            vec!["kernel: ERROR disk", "sshd: login ok"]
+        );
+
+        let filter = LogFilter {
+            pattern: "(".to_string(),
+            ..Default::default()
+        };
+        assert!(store.subscribe(&file, Some(&filter)).is_err());
+        assert!(store.subscribe("/etc/shadow", None).is_err());
+    }
+
+    #[test]
+    fn buffer_keeps_the_newest_lines() {
+        let mut buffer = LogBuffer::default();
+        for i in 0..BUFFER_LINES + 10 {
+            buffer.push(i.to_string());
+        }
+        assert_eq!(buffer.first_id(), 10);
+        let (mut lines, next_id) = buffer.lines_from(0);
+        assert_eq!(lines.next().unwrap(), "10");
+        assert_eq!(next_id, BUFFER_LINES as u64 + 10);
+    }
+}
@@ -1,142 +1,146 @@
-use chrono::Utc;
-use server_info_packets::server_info_packet::*;
-use std::io::{Read, Write as OtherWrite};
-use std::net::{Shutdown, TcpListener, TcpStream};
-use std::string::String;
+use crate::cgroup::CgroupCollector;
+use crate::script_collector::ScriptCollector;
+use crate::system_collectors::*;
+use crate::systemd::{ServiceCollector, Systemctl};
+use server_info_packets::discovery::Announcement;
+use server_info_server_rs::collector::CollectorRegistry;
+use server_info_server_rs::history::History;
+use server_info_server_rs::log_tail::LogStore;
+use server_info_server_rs::server::{Shared, Source};
+use server_info_server_rs::{config, discovery, server};
+use std::sync::{Arc, RwLock};
 use std::thread;
-use std::thread::JoinHandle;
 use std::time::Duration;
-use sysinfo::{CpuExt, NetworkExt, System, SystemExt};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+#[cfg(feature = "agent")]
+mod agent;
+mod cgroup;
+mod hwmon;
+mod proc_stat;
+mod script_collector;
+mod system_collectors;
+mod systemd;
+
+static CONFIG_FILE_NAME: &str = "server_info_server.toml";
 
 fn main() {
-    println!("Listening for connections on port 8111!");
-
-    let listener = TcpListener::bind("0.0.0.0:8111").unwrap();
-
-    let mut thread_vec: Vec<JoinHandle<()>> = vec![];
-
-    for incomming in listener.incoming() {
-        for i in 0..thread_vec.len() {
-            match thread_vec.get(i) {
-                None => {}
-                Some(t) => {
-                    if t.is_finished() {
-                        //println!("Thread finished: {:?}", thread_vec.get(i).unwrap());
-                        thread_vec.remove(i);
-                    }
-                }
-            }
+    let config_file_name =
+        std::env::var("SERVER_INFO_CONFIG").unwrap_or_else(|_| CONFIG_FILE_NAME.to_string());
+    let config = match config::read_from_file(&config_file_name) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Unable to read {config_file_name}: {e}");
+            std::process::exit(1);
         }
-
-        let handle = thread::spawn(move || {
-            let stream = incomming.expect("failed to handle");
-            println!("Client connected: {stream:?}");
-            loop {
-                if !handle_client(&stream, generate_server_info_packet()) {
-                    println!("Client disconnected: {stream:?}");
-                    break;
-                }
-            }
-        });
-
-        thread_vec.push(handle);
-        println!(
-            "Number of currently connected clients: {}",
-            thread_vec.len()
-        );
+    };
+
+    let logs = LogStore::spawn(config.logs.files);
+
+    let mut registry = CollectorRegistry::new(config.collectors);
+    registry.register(Box::new(HostCollector::new()));
+    registry.register(Box::new(CpuCollector::new()));
+    registry.register(Box::new(MemoryCollector::new()));
+    registry.register(Box::new(DiskCollector::new()));
+    registry.register(Box::new(NetworkCollector::new()));
+    registry.register(Box::new(ComponentCollector::new()));
+    registry.register(Box::new(CgroupCollector::new(
+        config.containers.cgroup_root,
+        config.containers.list_siblings,
+    )));
+    registry.register(Box::new(ServiceCollector::new(
+        Box::new(Systemctl),
+        config.services.units,
+    )));
+    for script in config.scripts {
+        registry.register(Box::new(ScriptCollector::new(script)));
     }
 
-    for handle in thread_vec {
-        handle.join().expect("TODO: panic message");
+    let history_file = config.history.file;
+    let history = match &history_file {
+        Some(file) => History::load(file, config.history.len).unwrap_or_else(|e| {
+            println!(
+                "Starting without history, unable to read {}: {e}",
+                file.display()
+            );
+            History::new(config.history.len)
+        }),
+        None => History::new(config.history.len),
+    };
+    let history = Arc::new(RwLock::new(history));
+
+    // collect once before accepting clients so nobody gets an empty packet
+    let latest = Arc::new(RwLock::new(registry.collect_due().clone()));
+    {
+        let latest = latest.clone();
+        let history = history.clone();
+        thread::spawn(move || registry.run(latest, history));
     }
-}
 
-fn generate_server_info_packet() -> ServerInfo {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    thread::sleep(Duration::from_millis(250));
-    sys.refresh_cpu();
-
-    let mut disks: Vec<String> = vec![];
-
-    for disk in sys.disks() {
-        disks.push(format_args!("{disk:?}").to_string());
-    }
-
-    let mut net_interfaces: Vec<String> = vec![];
-
-    for (interface_name, data) in sys.networks() {
-        net_interfaces.push(
-            format_args!(
-                "{}: {}/{} B",
-                interface_name,
-                data.received(),
-                data.transmitted()
-            )
+    let announcement = Announcement {
+        host_name: latest
+            .read()
+            .expect("Server info lock poisoned.")
+            .host_name
             .to_string(),
-        );
-    }
-
-    let mut components: Vec<String> = vec![];
-
-    for component in sys.components() {
-        components.push(format_args!("{component:?}").to_string());
-    }
-
-    let total_ram = sys.total_memory();
-    let used_memory = sys.used_memory();
-
-    let system_name = sys.name().unwrap_or_default();
-    let kernel_version = sys.kernel_version().unwrap_or_default();
-    let os_version = sys.os_version().unwrap_or_default();
-    let host_name = sys.host_name().unwrap_or_default();
-
-    let total_cpus = sys.cpus().len();
-    let mut avg_cpu_usage = 0.0;
-
-    thread::sleep(Duration::from_millis(250));
-    sys.refresh_cpu();
-
-    let mut cpus: Vec<String> = vec![];
-
-    for cpu in sys.cpus() {
-        cpus.push(format_args!("{cpu:?}").to_string());
-        avg_cpu_usage += cpu.cpu_usage();
-    }
-    avg_cpu_usage /= total_cpus as f32;
-
-    ServerInfo {
-        date: Utc::now().timestamp(),
-        disks,
-        net_interfaces,
-        components,
-        total_ram,
-        used_memory,
-        system_name,
-        kernel_version,
-        os_version,
-        host_name,
-        total_cpus,
-        cpus,
-        avg_cpu_usage,
-    }
-}
-
-fn handle_client(mut stream: &TcpStream, info: ServerInfo) -> bool {
-    let ser = serde_json::to_string(&info).unwrap_or_default();
-
-    let write = stream.write(ser.as_bytes());
-
-    let flush = stream.flush();
-
-    let read = stream.read(&mut [0; 128]);
+        auth: config.auth.token.is_some(),
+        ..Default::default()
+    };
+
+    let shared = Arc::new(Shared {
+        source: Source::Local(latest.clone(), history.clone()),
+        logs,
+        auth_token: config.auth.token,
+        agent_token: None,
+        write_timeout: Duration::from_millis(config.listen.write_timeout_ms),
+        compression_threshold: config.listen.compression_threshold,
+    });
+
+    let runtime = tokio::runtime::Runtime::new().expect("Unable to start the tokio runtime.");
+    runtime.block_on(async {
+        if let Some(endpoint) = config.agent.endpoint.clone() {
+            #[cfg(feature = "agent")]
+            tokio::spawn(agent::run(config.agent, endpoint, latest));
+            #[cfg(not(feature = "agent"))]
+            println!(
+                "Not pushing to hub {endpoint}, the server was built without the agent feature."
+            );
+        }
 
-    if write.is_err() || flush.is_err() || read.is_err() {
-        stream
-            .shutdown(Shutdown::Both)
-            .expect("Unable to shutdown client stream.");
-        return false;
+        discovery::start(&config.discovery, &config.listen.address, announcement).await;
+
+        let listener = match TcpListener::bind(&config.listen.address).await {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen on {}: {e}", config.listen.address),
+        };
+        println!("Listening for connections on {}!", config.listen.address);
+
+        let (shutdown_sender, shutdown) = watch::channel(false);
+        let server = tokio::spawn(server::serve(
+            listener,
+            shared,
+            config.listen.max_connections,
+            shutdown,
+        ));
+
+        server::shutdown_signal().await;
+        println!("Shutting down, telling clients.");
+        let _ = shutdown_sender.send(true);
+
+        let grace_period = Duration::from_millis(config.listen.shutdown_grace_ms);
+        if tokio::time::timeout(grace_period, server).await.is_err() {
+            println!("Not every client disconnected within {grace_period:?}, stopping anyway.");
+        }
+    });
+    // clients still connected are dropped with the runtime
+    runtime.shutdown_timeout(Duration::ZERO);
+
+    if let Some(file) = history_file {
+        let history = history.read().expect("History lock poisoned.");
+        match history.save(&file) {
+            Ok(()) => println!("Saved the history to {}.", file.display()),
+            Err(e) => println!("Unable to save the history to {}: {e}", file.display()),
+        }
     }
-    true
 }
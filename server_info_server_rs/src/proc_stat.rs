@@ -0,0 +1,128 @@
+use server_info_packets::cpu_info::CpuTimes;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// The counters of a cpu line of /proc/stat in clock ticks since boot:
+/// user, nice, system, idle, iowait, irq, softirq and steal.
+type Ticks = [u64; 8];
+
+/// Turns the cumulative counters of /proc/stat into how the time since the previous reading was spent.
+pub struct ProcStat {
+    path: PathBuf,
+    /// keyed by "cpu" for the whole machine and "cpu0".. for the cores
+    last: HashMap<String, Ticks>,
+}
+
+impl ProcStat {
+    pub fn new(path: PathBuf) -> Self {
+        let mut proc_stat = Self {
+            path,
+            last: HashMap::new(),
+        };
+        // the first reading has nothing to compare to
+        proc_stat.read();
+        proc_stat
+    }
+
+    /// The times of every cpu since the previous call, empty where /proc/stat does not exist
+    pub fn read(&mut self) -> HashMap<String, CpuTimes> {
+        let Ok(contents) = fs::read_to_string(&self.path) else {
+            return HashMap::new();
+        };
+        let mut times = HashMap::new();
+        for (name, ticks) in parse(&contents) {
+            if let Some(last) = self.last.get(&name) {
+                if let Some(spent) = spent(last, &ticks) {
+                    times.insert(name.to_string(), spent);
+                }
+            }
+            self.last.insert(name, ticks);
+        }
+        times
+    }
+}
+
+/// The cpu lines of a /proc/stat file, older kernels without steal read it as 0
+fn parse(contents: &str) -> Vec<(String, Ticks)> {
+    contents
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let mut ticks = Ticks::default();
+            for (tick, field) in ticks.iter_mut().zip(fields) {
+                *tick = field.parse().ok()?;
+            }
+            Some((name, ticks))
+        })
+        .collect()
+}
+
+/// Percentages of the ticks that passed between two readings, none when no time passed
+fn spent(last: &Ticks, now: &Ticks) -> Option<CpuTimes> {
+    let mut delta = Ticks::default();
+    for ((delta, last), now) in delta.iter_mut().zip(last).zip(now) {
+        // counters go backwards when a core is taken offline and back
+        *delta = now.saturating_sub(*last);
+    }
+    let total: u64 = delta.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let percent = |ticks: u64| (ticks as f64 * 100.0 / total as f64) as f32;
+    let [user, nice, system, idle, iowait, irq, softirq, steal] = delta;
+    Some(CpuTimes {
+        user: percent(user),
+        nice: percent(nice),
+        system: percent(system),
+        idle: percent(idle),
+        iowait: percent(iowait),
+        irq: percent(irq),
+        softirq: percent(softirq),
+        steal: percent(steal),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_are_spent_since_the_previous_reading() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stat");
+        fs::write(
+            &path,
+            "cpu  100 0 100 800 0 0 0 0 0 0\n\
+             cpu0 50 0 50 400 0 0 0 0 0 0\n\
+             intr 12345 0 0\n",
+        )
+        .unwrap();
+        let mut proc_stat = ProcStat::new(path.clone());
+
+        fs::write(
+            &path,
+            "cpu  150 10 120 900 10 0 10 50 0 0\n\
+             cpu0 50 0 50 400 0 0 0 0 0 0\n\
+             cpu1 10 0 10 80\n",
+        )
+        .unwrap();
+        let times = proc_stat.read();
+
+        let machine = times["cpu"];
+        assert_eq!(machine.user, 20.0);
+        assert_eq!(machine.idle, 40.0);
+        assert_eq!(machine.steal, 20.0);
+        assert_eq!(machine.nice, 4.0);
+        // cpu0 did not tick, cpu1 was not there before
+        assert_eq!(times.len(), 1);
+    }
+
+    #[test]
+    fn missing_files_have_no_times() {
+        let mut proc_stat = ProcStat::new(PathBuf::from("/nonexistent/stat"));
+        assert!(proc_stat.read().is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use serde_json::Value;
+use server_info_packets::custom_metric::{CustomMetric, CustomMetricGroup, MetricValue};
+use server_info_server_rs::collector::{Collector, Section};
+use server_info_server_rs::config::{ScriptConfig, ScriptOutputFormat};
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs a configured command and reports what it prints as custom metrics
+pub struct ScriptCollector {
+    config: ScriptConfig,
+}
+
+impl ScriptCollector {
+    pub fn new(config: ScriptConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Collector for ScriptCollector {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.config.interval_ms)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        let output = run_with_timeout(
+            &self.config.command,
+            &self.config.args,
+            Duration::from_millis(self.config.timeout_ms),
+        )?;
+
+        let metrics = match self.config.format {
+            ScriptOutputFormat::KeyValue => parse_key_value(&output),
+            ScriptOutputFormat::Json => parse_json(&output)?,
+        };
+
+        Ok(Section::Custom(CustomMetricGroup {
+            name: self.config.name.to_string(),
+            metrics,
+        }))
+    }
+}
+
+/// Runs a command and returns its stdout, killing it if it is still running after timeout
+fn run_with_timeout(command: &str, args: &[String], timeout: Duration) -> Result<String, String> {
+    let deadline = Instant::now() + timeout;
+    let mut child = Command::new(command);
+    child
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    // its own process group, so what the script starts can be killed along with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut child, 0);
+    let mut child = child
+        .spawn()
+        .map_err(|e| format!("Unable to run {command}: {e}"))?;
+
+    // read on another thread so a chatty script can not fill the pipe and block forever
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let result = stdout.read_to_string(&mut output).map(|_| output);
+        let _ = sender.send(result);
+    });
+
+    let timed_out = || format!("{command} timed out after {timeout:?}");
+
+    // the command might close stdout and keep running, so the wait for it to exit is bounded by the deadline as well
+    let output = receiver.recv_timeout(timeout);
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if output.is_err() || Instant::now() >= deadline => {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(timed_out());
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+
+    let output = match output {
+        Ok(output) => output.map_err(|e| e.to_string())?,
+        // the command exited but something it started still holds stdout open
+        Err(_) => {
+            kill(&mut child);
+            return Err(timed_out());
+        }
+    };
+    if !status.success() {
+        return Err(format!("{command} exited with {status}"));
+    }
+    Ok(output)
+}
+
+/// Kills the command and everything it started, children of `sh -c` would otherwise keep stdout open and the reading thread alive
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: killpg only sends a signal, the command leads its own group since run_with_timeout spawned it
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+/// Parses lines of "key=value" or "key: value", blank lines and lines starting with # are skipped
+fn parse_key_value(output: &str) -> Vec<CustomMetric> {
+    let mut metrics: Vec<CustomMetric> = vec![];
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = match line.split_once('=').or_else(|| line.split_once(':')) {
+            Some(pair) => pair,
+            None => continue,
+        };
+        metrics.push(CustomMetric {
+            name: name.trim().to_string(),
+            value: MetricValue::parse(value),
+        });
+    }
+
+    metrics
+}
+
+/// Parses a json object, nested objects and arrays are flattened into dotted names
+fn parse_json(output: &str) -> Result<Vec<CustomMetric>, String> {
+    let value: Value = serde_json::from_str(output).map_err(|e| e.to_string())?;
+    if !value.is_object() {
+        return Err("Script output is not a json object.".to_string());
+    }
+
+    let mut metrics: Vec<CustomMetric> = vec![];
+    flatten_json("", &value, &mut metrics);
+    Ok(metrics)
+}
+
+fn flatten_json(prefix: &str, value: &Value, metrics: &mut Vec<CustomMetric>) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten_json(&join(key), value, metrics);
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                flatten_json(&join(&index.to_string()), value, metrics);
+            }
+        }
+        Value::Number(number) => metrics.push(CustomMetric {
+            name: prefix.to_string(),
+            value: MetricValue::Number(number.as_f64().unwrap_or_default()),
+        }),
+        Value::String(text) => metrics.push(CustomMetric {
+            name: prefix.to_string(),
+            value: MetricValue::Text(text.to_string()),
+        }),
+        Value::Bool(b) => metrics.push(CustomMetric {
+            name: prefix.to_string(),
+            value: MetricValue::Text(b.to_string()),
+        }),
+        Value::Null => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(name: &str, value: MetricValue) -> CustomMetric {
+        CustomMetric {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn parses_key_value_output() {
+        let output = "# queue stats\nqueue_depth=42\nreplication_lag: 1.5\n\nstate = degraded\nnot a metric\n";
+        assert_eq!(
+            parse_key_value(output),
+            vec![
+                metric("queue_depth", MetricValue::Number(42.0)),
+                metric("replication_lag", MetricValue::Number(1.5)),
+                metric("state", MetricValue::Text("degraded".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_nested_json_output() {
+        let output = r#"{"queue": {"depth": 42, "state": "ok"}, "replicas": [1.5, 2], "healthy": true, "note": null}"#;
+        let mut metrics = parse_json(output).unwrap();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(
+            metrics,
+            vec![
+                metric("healthy", MetricValue::Text("true".to_string())),
+                metric("queue.depth", MetricValue::Number(42.0)),
+                metric("queue.state", MetricValue::Text("ok".to_string())),
+                metric("replicas.0", MetricValue::Number(1.5)),
+                metric("replicas.1", MetricValue::Number(2.0)),
+            ]
+        );
+        assert!(parse_json("[1, 2]").is_err());
+        assert!(parse_json("not json").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_commands_with_a_timeout() {
+        let args = |script: &str| vec!["-c".to_string(), script.to_string()];
+
+        let output = run_with_timeout("sh", &args("echo depth=3"), Duration::from_secs(5));
+        assert_eq!(output, Ok("depth=3\n".to_string()));
+
+        let output = run_with_timeout("sh", &args("sleep 5"), Duration::from_millis(100));
+        assert!(output.unwrap_err().contains("timed out"));
+
+        let output = run_with_timeout("sh", &args("exit 2"), Duration::from_secs(5));
+        assert!(output.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeouts_kill_what_the_command_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let script = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let output = run_with_timeout(
+            "sh",
+            &["-c".to_string(), script],
+            Duration::from_millis(200),
+        );
+        assert!(output.unwrap_err().contains("timed out"));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        // killed, either reaped already or a zombie until its new parent reaps it
+        let started = Instant::now();
+        while std::fs::read_to_string(&stat).is_ok_and(|stat| !stat.contains(") Z ")) {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "sleep is still running"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
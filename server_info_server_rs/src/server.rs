@@ -0,0 +1,1163 @@
+use crate::history::History;
+use crate::hosts::Hosts;
+use crate::inventory;
+use crate::log_tail::{LogStore, LogSubscription};
+use server_info_packets::codec::{Codec, Encoding};
+use server_info_packets::compression::Compression;
+use server_info_packets::delta::DeltaEncoder;
+use server_info_packets::export::export;
+use server_info_packets::host_status::HostStatus;
+use server_info_packets::protocol::{
+    write_message_async, ClientRequest, FrameReader, Sections, ServerMessage, HEARTBEAT_INTERVAL,
+    HEARTBEAT_TIMEOUT, MAX_EXPORT_LEN, MAX_EXPORT_SIZE, MAX_HISTORY_LEN, MAX_PUSH_INTERVAL,
+    MIN_PUSH_INTERVAL,
+};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Semaphore};
+use tokio::time::Instant;
+
+/// Waited after a failed accept, which mostly means the process ran out of file descriptors
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// What every client task needs.
+pub struct Shared {
+    pub source: Source,
+    pub logs: Arc<LogStore>,
+    pub auth_token: Option<String>,
+    /// agents have to register with this token, none when agents are not accepted
+    pub agent_token: Option<String>,
+    pub write_timeout: Duration,
+    pub compression_threshold: usize,
+}
+
+/// Where the infos sent to clients come from.
+pub enum Source {
+    /// this machine and its history, kept up to date by the collectors
+    Local(Arc<RwLock<ServerInfo>>, Arc<RwLock<History>>),
+    /// the servers a hub collects from, clients pick one with ClientRequest::SelectHost
+    Hub(Arc<Hosts>),
+}
+
+/// Accepts clients, each on its own task, rejecting them while max_connections are connected.
+///
+/// Once shutdown turns true no more clients are accepted, and this returns after every client was told and disconnected.
+pub async fn serve(
+    listener: TcpListener,
+    shared: Arc<Shared>,
+    max_connections: usize,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let permits = Arc::new(Semaphore::new(max_connections));
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            Ok(_) = shutdown.wait_for(|stopping| *stopping) => break,
+        };
+        let (stream, address) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                println!("Unable to accept a client: {e}");
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
+
+        let permit = match permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                println!("Rejecting {address}, {max_connections} clients are already connected.");
+                tokio::spawn(reject(stream, shared.write_timeout));
+                continue;
+            }
+        };
+
+        let shared = shared.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            println!("Client connected: {address}");
+            if let Err(e) = handle_client(stream, &shared, shutdown).await {
+                println!("Client disconnected: {address}, {e}");
+            }
+            drop(permit);
+        });
+    }
+
+    // every client task holds a permit until it is done
+    let _ = permits.acquire_many(max_connections as u32).await;
+}
+
+/// Resolves on SIGINT (ctrl c) or SIGTERM (docker stop, systemctl stop)
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Unable to listen for SIGTERM.");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+async fn reject(stream: TcpStream, write_timeout: Duration) {
+    let mut peer = Peer::new(stream, write_timeout);
+    let message = ServerMessage::Error("Server is full, try again later.".to_string());
+    let _ = peer.send(&message).await;
+    let _ = peer.stream.shutdown().await;
+}
+
+/// Sends the latest packet every time the client asks for one, along with new lines of the log it tails.
+///
+/// Nothing is sent that the client did not ask for, so a slow client only slows down itself,
+/// and one that stops reading altogether is dropped after the write timeout.
+/// A client waiting for its next request is sent ServerMessage::ShuttingDown once shutdown turns true.
+/// Clients that asked for deltas get a Snapshot and then only the fields that changed since the last message.
+/// The interval a client subscribed with, at most MAX_PUSH_INTERVAL so it can be added to an Instant
+fn interval(interval_ms: u64) -> Duration {
+    Duration::from_millis(interval_ms).min(MAX_PUSH_INTERVAL)
+}
+
+/// A ClientRequest::Subscribe limits the sections sent, and holds answers back until the clients interval passed.
+///
+/// Clients that subscribed with push set are sent an info every interval without asking,
+/// and are dropped when they send nothing, not even a heartbeat, for HEARTBEAT_TIMEOUT.
+///
+/// A ClientRequest::Agent hands the connection over to serve_agent.
+pub async fn handle_client(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    shared: &Shared,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let mut peer = Peer::new(stream, shared.write_timeout);
+
+    if let Some(token) = &shared.auth_token {
+        authenticate(&mut peer, token).await?;
+    }
+
+    let mut session = Session::default();
+    // when the next info goes out, None while waiting for a request
+    let mut info_due = Some(Instant::now());
+    let mut last_info = Instant::now();
+    let mut last_sent = Instant::now();
+    let mut last_heard = Instant::now();
+
+    loop {
+        let event = tokio::select! {
+            _ = tokio::time::sleep_until(info_due.unwrap_or(last_info)), if info_due.is_some() => Event::Info,
+            request = peer.receive() => Event::Request(request?),
+            _ = tokio::time::sleep_until(last_sent + HEARTBEAT_INTERVAL), if session.push => Event::Heartbeat,
+            _ = tokio::time::sleep_until(last_heard + HEARTBEAT_TIMEOUT), if session.push => Event::Silent,
+            Ok(_) = shutdown.wait_for(|stopping| *stopping) => Event::Shutdown,
+        };
+
+        match event {
+            Event::Info => {
+                session.send_info(&mut peer, shared).await?;
+                last_info = Instant::now();
+                last_sent = last_info;
+                info_due = session
+                    .push
+                    .then(|| last_info + session.interval.max(MIN_PUSH_INTERVAL));
+            }
+            Event::Request(ClientRequest::Agent { name, token }) => {
+                return serve_agent(&mut peer, shared, name, &token, shutdown).await;
+            }
+            Event::Request(request) => {
+                last_heard = Instant::now();
+                session.handle(request, &mut peer, shared).await?;
+                if !session.push {
+                    info_due = Some(last_info + session.interval);
+                } else if info_due.is_none() {
+                    info_due = Some(last_info + session.interval.max(MIN_PUSH_INTERVAL));
+                }
+            }
+            Event::Heartbeat => {
+                peer.send(&ServerMessage::Heartbeat).await?;
+                last_sent = Instant::now();
+            }
+            Event::Silent => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Client stopped sending heartbeats.",
+                ))
+            }
+            Event::Shutdown => return peer.stop().await,
+        }
+    }
+}
+
+/// Stores the reports of an agent in the hosts of a hub, until it disconnects or stays silent for HEARTBEAT_TIMEOUT
+async fn serve_agent(
+    peer: &mut Peer<impl AsyncRead + AsyncWrite + Unpin>,
+    shared: &Shared,
+    name: String,
+    token: &str,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let hosts = match (&shared.source, &shared.agent_token) {
+        (Source::Hub(hosts), Some(agent_token)) if tokens_match(token, agent_token) => hosts,
+        (Source::Hub(_), Some(_)) => return refuse(peer, "Wrong agent token.").await,
+        _ => return refuse(peer, "This server does not accept agents.").await,
+    };
+    if let Err(e) = hosts.register(&name) {
+        return refuse(peer, &e).await;
+    }
+    println!("Agent {name} registered.");
+    peer.send(&ServerMessage::Ack).await?;
+
+    let result = loop {
+        let request = tokio::select! {
+            request = tokio::time::timeout(HEARTBEAT_TIMEOUT, peer.receive()) => Some(request),
+            Ok(_) = shutdown.wait_for(|stopping| *stopping) => None,
+        };
+        let request = match request {
+            None => break peer.stop().await,
+            Some(Ok(Ok(request))) => request,
+            Some(Ok(Err(e))) => break Err(e),
+            Some(Err(_)) => {
+                break Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Agent stopped sending heartbeats.",
+                ))
+            }
+        };
+        match request {
+            ClientRequest::Report(infos) => {
+                for info in infos {
+                    hosts.update(&name, info);
+                }
+                if let Err(e) = peer.send(&ServerMessage::Ack).await {
+                    break Err(e);
+                }
+            }
+            ClientRequest::Heartbeat => {}
+            _ => {
+                break Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Agents can only send reports.",
+                ))
+            }
+        }
+    };
+
+    let reason = match &result {
+        Ok(()) => "the hub stopped".to_string(),
+        Err(e) => e.to_string(),
+    };
+    hosts.disconnected(&name, reason);
+    result
+}
+
+/// Sends the client why its request was refused, and fails with it
+async fn refuse(
+    peer: &mut Peer<impl AsyncRead + AsyncWrite + Unpin>,
+    reason: &str,
+) -> io::Result<()> {
+    peer.send(&ServerMessage::Error(reason.to_string())).await?;
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, reason))
+}
+
+/// What handle_client waits for.
+enum Event {
+    /// the next info is due
+    Info,
+    Request(ClientRequest),
+    /// nothing was sent to a pushed to client for HEARTBEAT_INTERVAL
+    Heartbeat,
+    /// a pushed to client sent nothing for HEARTBEAT_TIMEOUT
+    Silent,
+    Shutdown,
+}
+
+/// What a client asked for so far.
+#[derive(Default)]
+struct Session {
+    log_subscription: Option<LogSubscription>,
+    deltas: Option<DeltaEncoder>,
+    sections: Sections,
+    interval: Duration,
+    push: bool,
+    /// the host of a hub the client selected, none for the first one
+    host: Option<String>,
+}
+
+impl Session {
+    /// Sends new log lines and the latest info, stripped to the subscribed sections
+    async fn send_info(
+        &mut self,
+        peer: &mut Peer<impl AsyncRead + AsyncWrite + Unpin>,
+        shared: &Shared,
+    ) -> io::Result<()> {
+        if let Some(subscription) = &mut self.log_subscription {
+            let lines = subscription.poll();
+            if !lines.is_empty() {
+                let file = subscription.file.to_string();
+                peer.send(&ServerMessage::LogLines { file, lines }).await?;
+            }
+        }
+
+        let mut info = self.latest(shared);
+        self.sections.strip(&mut info);
+        let message = match &mut self.deltas {
+            Some(encoder) => encoder.message(info),
+            None => ServerMessage::Info(Box::new(info)),
+        };
+        peer.send(&message).await
+    }
+
+    async fn handle(
+        &mut self,
+        request: ClientRequest,
+        peer: &mut Peer<impl AsyncRead + AsyncWrite + Unpin>,
+        shared: &Shared,
+    ) -> io::Result<()> {
+        match request {
+            ClientRequest::Next | ClientRequest::Heartbeat => {}
+            ClientRequest::TailLogs { file, filter } => {
+                match shared.logs.subscribe(&file, filter.as_ref()) {
+                    Ok(subscription) => self.log_subscription = Some(subscription),
+                    Err(e) => peer.send(&ServerMessage::Error(e)).await?,
+                }
+            }
+            ClientRequest::StopLogs => self.log_subscription = None,
+            // already authenticated, or no token is needed
+            ClientRequest::Auth { .. } => {}
+            // handle_client hands the connection over to serve_agent on ClientRequest::Agent
+            ClientRequest::Agent { .. } | ClientRequest::Report(_) => {
+                let message = ServerMessage::Error(
+                    "Reports are only accepted from registered agents.".to_string(),
+                );
+                peer.send(&message).await?;
+            }
+            ClientRequest::Hello {
+                encodings,
+                compressions,
+                deltas,
+            } => {
+                let encoding = Encoding::negotiate(&encodings);
+                let compression = Compression::negotiate(&compressions);
+                let hello = ServerMessage::Hello {
+                    encoding,
+                    compression,
+                };
+                peer.send(&hello).await?;
+                peer.codec = Codec {
+                    encoding,
+                    compression,
+                    threshold: shared.compression_threshold,
+                };
+                self.deltas = deltas.then(DeltaEncoder::default);
+            }
+            ClientRequest::Resync => {
+                if let Some(encoder) = &mut self.deltas {
+                    encoder.resync();
+                }
+            }
+            ClientRequest::Subscribe {
+                sections,
+                interval_ms,
+                push,
+            } => {
+                self.sections = sections;
+                self.interval = interval(interval_ms);
+                self.push = push;
+            }
+            ClientRequest::ListHosts => {
+                let hosts = match &shared.source {
+                    Source::Local(..) => {
+                        let info = self.latest(shared);
+                        vec![HostStatus {
+                            name: info.host_name,
+                            address: String::new(),
+                            connected: true,
+                            last_seen: info.date,
+                            error: None,
+                        }]
+                    }
+                    Source::Hub(hosts) => hosts.list(),
+                };
+                peer.send(&ServerMessage::Hosts(hosts)).await?;
+            }
+            ClientRequest::SelectHost { name } => match &shared.source {
+                Source::Hub(hosts) if hosts.contains(&name) => {
+                    self.host = Some(name);
+                    // a snapshot of the new host instead of a delta touching every field
+                    if let Some(encoder) = &mut self.deltas {
+                        encoder.resync();
+                    }
+                }
+                Source::Hub(_) => {
+                    let message = ServerMessage::Error(format!("Unknown host {name}."));
+                    peer.send(&message).await?;
+                }
+                Source::Local(..) if self.latest(shared).host_name == name => {}
+                Source::Local(..) => {
+                    let message = ServerMessage::Error(format!(
+                        "This server is not a hub, {name} is not one of its hosts."
+                    ));
+                    peer.send(&message).await?;
+                }
+            },
+            ClientRequest::History { from, to } => {
+                let mut infos = self.history(shared, from, to);
+                infos.drain(..infos.len().saturating_sub(MAX_HISTORY_LEN));
+                for info in &mut infos {
+                    self.sections.strip(info);
+                }
+                peer.send(&ServerMessage::History(infos)).await?;
+            }
+            ClientRequest::Export {
+                from,
+                to,
+                format,
+                columns,
+            } => {
+                let mut infos = self.history(shared, from, to);
+                infos.drain(..infos.len().saturating_sub(MAX_EXPORT_LEN));
+                let count = infos.len();
+                // a few thousand rows of many columns, too slow to flatten on a worker other clients share
+                let text = tokio::task::spawn_blocking(move || export(&infos, format, &columns))
+                    .await
+                    .map_err(io::Error::other)?;
+                let message = match text.len() > MAX_EXPORT_SIZE {
+                    true => ServerMessage::Error(format!(
+                        "Export of {count} infos is {} bytes, more than the {MAX_EXPORT_SIZE} that fit in a message, ask for fewer columns or a shorter range.",
+                        text.len()
+                    )),
+                    false => ServerMessage::Export(text),
+                };
+                peer.send(&message).await?;
+            }
+            ClientRequest::Inventory => {
+                let message = match &shared.source {
+                    Source::Local(..) => {
+                        let inventory =
+                            tokio::task::spawn_blocking(|| inventory::collect(Path::new("/sys")))
+                                .await
+                                .map_err(io::Error::other)?;
+                        ServerMessage::Inventory(Box::new(inventory))
+                    }
+                    // hubs only keep the infos of their hosts
+                    Source::Hub(_) => ServerMessage::Error(
+                        "A hub does not take inventories, connect to the host itself.".to_string(),
+                    ),
+                };
+                peer.send(&message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The latest info of this server, or of the selected host of a hub
+    fn latest(&self, shared: &Shared) -> ServerInfo {
+        match &shared.source {
+            Source::Local(latest, _) => {
+                let mut info = latest.read().expect("Server info lock poisoned.").clone();
+                info.log_files = shared.logs.files();
+                info
+            }
+            Source::Hub(hosts) => hosts.latest(self.host.as_deref()),
+        }
+    }
+
+    /// The kept infos of this server, or of the selected host of a hub, dated from..=to
+    fn history(&self, shared: &Shared, from: i64, to: i64) -> Vec<ServerInfo> {
+        match &shared.source {
+            Source::Local(_, history) => history
+                .read()
+                .expect("History lock poisoned.")
+                .range(from, to),
+            Source::Hub(hosts) => hosts.history(self.host.as_deref(), from, to),
+        }
+    }
+}
+
+/// A clients stream along with the encoding and compression it asked for.
+struct Peer<S> {
+    stream: S,
+    codec: Codec,
+    reader: FrameReader,
+    write_timeout: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Peer<S> {
+    fn new(stream: S, write_timeout: Duration) -> Self {
+        Self {
+            stream,
+            codec: Codec::default(),
+            reader: FrameReader::default(),
+            write_timeout,
+        }
+    }
+
+    async fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        let write = write_message_async(&mut self.stream, self.codec, message);
+        match tokio::time::timeout(self.write_timeout, write).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Client did not read in time.",
+            )),
+        }
+    }
+
+    /// Cancel safe, so it can race the timers of handle_client
+    async fn receive(&mut self) -> io::Result<ClientRequest> {
+        self.reader.read_async(&mut self.stream, self.codec).await
+    }
+
+    /// Tells the client the server is shutting down and closes the stream
+    async fn stop(&mut self) -> io::Result<()> {
+        self.send(&ServerMessage::ShuttingDown).await?;
+        self.stream.shutdown().await
+    }
+}
+
+/// Asks the client for the token, failing when it sends anything else
+async fn authenticate(
+    peer: &mut Peer<impl AsyncRead + AsyncWrite + Unpin>,
+    token: &str,
+) -> io::Result<()> {
+    peer.send(&ServerMessage::AuthRequired).await?;
+    match peer.receive().await? {
+        ClientRequest::Auth { token: given } if tokens_match(&given, token) => Ok(()),
+        _ => {
+            let message = ServerMessage::Error("Authentication failed.".to_string());
+            peer.send(&message).await?;
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Authentication failed.",
+            ))
+        }
+    }
+}
+
+/// Compares every byte so the time taken does not tell how much of the token was right
+fn tokens_match(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use server_info_packets::cpu_info::CoreInfo;
+    use server_info_packets::export::ExportFormat;
+    use server_info_packets::protocol::read_message_async;
+    use std::fs;
+    use std::time::Instant;
+
+    fn shared(auth_token: Option<&str>) -> Arc<Shared> {
+        let info = ServerInfo {
+            host_name: "box".to_string(),
+            cores: vec![CoreInfo {
+                name: "cpu0".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        Arc::new(Shared {
+            source: Source::Local(
+                Arc::new(RwLock::new(info)),
+                Arc::new(RwLock::new(History::new(10))),
+            ),
+            logs: LogStore::spawn(vec![]),
+            auth_token: auth_token.map(str::to_string),
+            agent_token: None,
+            write_timeout: Duration::from_secs(5),
+            compression_threshold: Codec::DEFAULT_THRESHOLD,
+        })
+    }
+
+    async fn spawn_server(shared: Arc<Shared>, max_connections: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (_sender, shutdown) = watch::channel(false);
+            serve(listener, shared, max_connections, shutdown).await;
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn answers_requests_after_authenticating() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(Some("secret"));
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::AuthRequired));
+        let auth = ClientRequest::Auth {
+            token: "secret".to_string(),
+        };
+        write_message_async(&mut client, Encoding::Json, &auth)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            match read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap()
+            {
+                ServerMessage::Info(info) => assert_eq!(info.host_name, "box"),
+                other => panic!("unexpected message {other:?}"),
+            }
+            write_message_async(&mut client, Encoding::Json, &ClientRequest::Next)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn switches_encoding_after_hello() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let mut shared = Arc::into_inner(shared(None)).unwrap();
+        // compress every frame
+        shared.compression_threshold = 0;
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        let hello = ClientRequest::Hello {
+            encodings: vec![Encoding::Bincode, Encoding::Json],
+            compressions: vec![Compression::Zstd],
+            deltas: false,
+        };
+        write_message_async(&mut client, Encoding::Json, &hello)
+            .await
+            .unwrap();
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(
+            message,
+            ServerMessage::Hello {
+                encoding: Encoding::Bincode,
+                compression: Compression::Zstd
+            }
+        ));
+
+        let codec = Codec {
+            encoding: Encoding::Bincode,
+            compression: Compression::Zstd,
+            threshold: 0,
+        };
+        match read_message_async(&mut client, codec).await.unwrap() {
+            ServerMessage::Info(info) => assert_eq!(info.host_name, "box"),
+            other => panic!("unexpected message {other:?}"),
+        }
+        write_message_async(&mut client, codec, &ClientRequest::Next)
+            .await
+            .unwrap();
+        let message: ServerMessage = read_message_async(&mut client, codec).await.unwrap();
+        assert!(matches!(message, ServerMessage::Info(_)));
+    }
+
+    #[tokio::test]
+    async fn sends_deltas_after_hello() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(None);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        let hello = ClientRequest::Hello {
+            encodings: vec![Encoding::Json],
+            compressions: vec![],
+            deltas: true,
+        };
+        write_message_async(&mut client, Encoding::Json, &hello)
+            .await
+            .unwrap();
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+
+        let mut seqs = vec![];
+        for request in [ClientRequest::Next, ClientRequest::Resync] {
+            match read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap()
+            {
+                ServerMessage::Snapshot { seq, info } => {
+                    assert_eq!(info.host_name, "box");
+                    seqs.push(seq);
+                }
+                ServerMessage::Delta { seq, changes } => {
+                    // nothing changed in between
+                    assert!(changes.is_empty());
+                    seqs.push(seq);
+                }
+                other => panic!("unexpected message {other:?}"),
+            }
+            write_message_async(&mut client, Encoding::Json, &request)
+                .await
+                .unwrap();
+        }
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::Snapshot { seq: 3, .. }));
+        assert_eq!(seqs, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_strip_sections_and_wait_for_the_interval() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(None);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        match read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap()
+        {
+            ServerMessage::Info(info) => assert_eq!(info.cores.len(), 1),
+            other => panic!("unexpected message {other:?}"),
+        }
+        let start = Instant::now();
+        let subscribe = ClientRequest::Subscribe {
+            sections: Sections {
+                cpus: false,
+                ..Default::default()
+            },
+            interval_ms: 200,
+            push: false,
+        };
+        write_message_async(&mut client, Encoding::Json, &subscribe)
+            .await
+            .unwrap();
+        match read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap()
+        {
+            ServerMessage::Info(info) => {
+                assert!(info.cores.is_empty());
+                assert_eq!(info.host_name, "box");
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn hubs_serve_the_selected_host() {
+        let hosts = Hosts::new(
+            [
+                ("web".to_string(), "web:8111".to_string()),
+                ("db".to_string(), "db:8111".to_string()),
+            ],
+            10,
+        );
+        for date in 1..=3 {
+            let info = ServerInfo {
+                host_name: "db.internal".to_string(),
+                date,
+                cores: vec![CoreInfo {
+                    name: "cpu0".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            hosts.update("db", info);
+        }
+        let mut shared = Arc::into_inner(shared(None)).unwrap();
+        shared.source = Source::Hub(Arc::new(hosts));
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let mut requests = vec![
+            ClientRequest::ListHosts,
+            ClientRequest::SelectHost {
+                name: "mail".to_string(),
+            },
+            ClientRequest::SelectHost {
+                name: "db".to_string(),
+            },
+            ClientRequest::History { from: 2, to: 10 },
+        ]
+        .into_iter();
+        let mut messages = vec![];
+        loop {
+            let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap();
+            if let ServerMessage::Info(_) = message {
+                let Some(request) = requests.next() else {
+                    messages.push(message);
+                    break;
+                };
+                write_message_async(&mut client, Encoding::Json, &request)
+                    .await
+                    .unwrap();
+            }
+            messages.push(message);
+        }
+
+        assert!(matches!(&messages[0], ServerMessage::Info(info) if info.host_name == "web"));
+        match &messages[1] {
+            ServerMessage::Hosts(hosts) => {
+                assert_eq!(hosts.len(), 2);
+                assert!(!hosts[0].connected);
+                assert!(hosts[1].connected);
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+        assert!(matches!(&messages[3], ServerMessage::Error(e) if e.contains("mail")));
+        assert!(matches!(&messages[4], ServerMessage::Info(info) if info.host_name == "web"));
+        assert!(
+            matches!(&messages[5], ServerMessage::Info(info) if info.host_name == "db.internal")
+        );
+        match &messages[6] {
+            ServerMessage::History(infos) => {
+                let dates: Vec<i64> = infos.iter().map(|info| info.date).collect();
+                assert_eq!(dates, vec![2, 3]);
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+        assert!(matches!(&messages[7], ServerMessage::Info(info) if info.date == 3));
+    }
+
+    #[tokio::test]
+    async fn exports_the_history_of_the_server() {
+        let shared = shared(None);
+        if let Source::Local(_, history) = &shared.source {
+            for date in 1..=3 {
+                let info = ServerInfo {
+                    date,
+                    ..Default::default()
+                };
+                history.write().unwrap().push(info);
+            }
+        }
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        let request = ClientRequest::Export {
+            from: 2,
+            to: 10,
+            format: ExportFormat::Csv,
+            columns: vec!["date".to_string()],
+        };
+        write_message_async(&mut client, Encoding::Json, &request)
+            .await
+            .unwrap();
+        match read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap()
+        {
+            ServerMessage::Export(csv) => assert_eq!(csv, "date\n2\n3\n"),
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn takes_an_inventory_of_the_server() {
+        let shared = shared(None);
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        write_message_async(&mut client, Encoding::Json, &ClientRequest::Inventory)
+            .await
+            .unwrap();
+        match read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap()
+        {
+            ServerMessage::Inventory(inventory) => assert!(inventory.cpu.logical_cores > 0),
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn hubs_store_the_reports_of_agents() {
+        let hosts = Arc::new(Hosts::new([], 10));
+        let mut shared = Arc::into_inner(shared(None)).unwrap();
+        shared.source = Source::Hub(hosts.clone());
+        shared.agent_token = Some("agents".to_string());
+        let shared = Arc::new(shared);
+
+        let mut replies = vec![];
+        for token in ["wrong", "agents"] {
+            let (mut agent, server) = tokio::io::duplex(64 * 1024);
+            let shared = shared.clone();
+            let (_sender, shutdown) = watch::channel(false);
+            let handle =
+                tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+            let _: ServerMessage = read_message_async(&mut agent, Encoding::Json)
+                .await
+                .unwrap();
+            let register = ClientRequest::Agent {
+                name: "nat".to_string(),
+                token: token.to_string(),
+            };
+            write_message_async(&mut agent, Encoding::Json, &register)
+                .await
+                .unwrap();
+            let reply: ServerMessage = read_message_async(&mut agent, Encoding::Json)
+                .await
+                .unwrap();
+            replies.push(reply);
+            if token == "wrong" {
+                assert!(handle.await.unwrap().is_err());
+                continue;
+            }
+
+            let infos = (1..=3)
+                .map(|date| ServerInfo {
+                    host_name: "nat.internal".to_string(),
+                    date,
+                    ..Default::default()
+                })
+                .collect();
+            write_message_async(&mut agent, Encoding::Json, &ClientRequest::Report(infos))
+                .await
+                .unwrap();
+            let reply: ServerMessage = read_message_async(&mut agent, Encoding::Json)
+                .await
+                .unwrap();
+            assert!(matches!(reply, ServerMessage::Ack));
+
+            drop(agent);
+            assert!(handle.await.unwrap().is_err());
+        }
+
+        assert!(matches!(&replies[0], ServerMessage::Error(e) if e.contains("token")));
+        assert!(matches!(replies[1], ServerMessage::Ack));
+        let listed = hosts.list();
+        assert_eq!(listed[0].name, "nat");
+        assert!(!listed[0].connected);
+        assert_eq!(listed[0].last_seen, 3);
+        assert_eq!(hosts.history(Some("nat"), 0, 10).len(), 3);
+    }
+
+    fn push(interval_ms: u64) -> ClientRequest {
+        ClientRequest::Subscribe {
+            sections: Sections::default(),
+            interval_ms,
+            push: true,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pushes_infos_and_drops_silent_clients() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(None);
+        let (_sender, shutdown) = watch::channel(false);
+        let handle = tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        let start = tokio::time::Instant::now();
+        write_message_async(&mut client, Encoding::Json, &push(1000))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap();
+            assert!(matches!(message, ServerMessage::Info(_)));
+        }
+        assert_eq!(start.elapsed().as_secs(), 3);
+
+        // the client never sends a heartbeat
+        let error = handle.await.unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= HEARTBEAT_TIMEOUT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn long_push_intervals_are_filled_with_heartbeats() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(None);
+        let (_sender, shutdown) = watch::channel(false);
+        tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        write_message_async(&mut client, Encoding::Json, &push(60_000))
+            .await
+            .unwrap();
+        for _ in 0..4 {
+            let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap();
+            assert!(matches!(message, ServerMessage::Heartbeat));
+            write_message_async(&mut client, Encoding::Json, &ClientRequest::Heartbeat)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn huge_intervals_are_shortened() {
+        assert_eq!(interval(1000), Duration::from_secs(1));
+        // adding u64::MAX ms to an Instant overflows where it counts nanoseconds in a u64, e.g. on macOS
+        assert_eq!(interval(u64::MAX), MAX_PUSH_INTERVAL);
+        assert!(Instant::now().checked_add(interval(u64::MAX)).is_some());
+    }
+
+    #[tokio::test]
+    async fn wrong_token_is_rejected() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let shared = shared(Some("secret"));
+        let (_sender, shutdown) = watch::channel(false);
+        let handle = tokio::spawn(async move { handle_client(server, &shared, shutdown).await });
+
+        let _: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        write_message_async(&mut client, Encoding::Json, &ClientRequest::Next)
+            .await
+            .unwrap();
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::Error(_)));
+        assert_eq!(
+            handle.await.unwrap().unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+
+    #[tokio::test]
+    async fn clients_past_the_limit_are_rejected() {
+        let address = spawn_server(shared(None), 1).await;
+
+        let mut first = TcpStream::connect(&address).await.unwrap();
+        let message: ServerMessage = read_message_async(&mut first, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::Info(_)));
+
+        let mut second = TcpStream::connect(&address).await.unwrap();
+        let message: ServerMessage = read_message_async(&mut second, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn clients_are_told_about_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (sender, shutdown) = watch::channel(false);
+        let server = tokio::spawn(serve(listener, shared(None), 8, shutdown));
+
+        let mut client = TcpStream::connect(&address).await.unwrap();
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::Info(_)));
+
+        sender.send(true).unwrap();
+        let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+            .await
+            .unwrap();
+        assert!(matches!(message, ServerMessage::ShuttingDown));
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    /// Resident memory of this process in KiB
+    fn resident_kib() -> u64 {
+        let status = fs::read_to_string("/proc/self/status").unwrap();
+        let line = status.lines().find(|l| l.starts_with("VmRSS:")).unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    /// User plus system cpu time of this process in clock ticks
+    fn cpu_ticks() -> u64 {
+        let stat = fs::read_to_string("/proc/self/stat").unwrap();
+        // fields after the parenthesised command name, utime and stime are the 14th and 15th field
+        let fields: Vec<&str> = stat
+            .rsplit_once(')')
+            .unwrap()
+            .1
+            .split_whitespace()
+            .collect();
+        fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+    }
+
+    /// Measures the server with 1000 connected clients that never ask for anything.
+    /// The clients live in the same process, so the numbers are an upper bound for the server.
+    ///
+    /// cargo test -p server_info_server_rs --release -- --ignored idle_subscribers --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn idle_subscribers() {
+        const CLIENTS: usize = 1000;
+        const IDLE: Duration = Duration::from_secs(5);
+
+        let address = spawn_server(shared(None), CLIENTS).await;
+        let memory_before = resident_kib();
+
+        let mut clients = Vec::with_capacity(CLIENTS);
+        for _ in 0..CLIENTS {
+            let mut client = TcpStream::connect(&address).await.unwrap();
+            let message: ServerMessage = read_message_async(&mut client, Encoding::Json)
+                .await
+                .unwrap();
+            assert!(matches!(message, ServerMessage::Info(_)));
+            clients.push(client);
+        }
+
+        let memory_connected = resident_kib();
+        let ticks_before = cpu_ticks();
+        let idle_start = Instant::now();
+        tokio::time::sleep(IDLE).await;
+        let cpu_percent =
+            (cpu_ticks() - ticks_before) as f64 / 100.0 / idle_start.elapsed().as_secs_f64()
+                * 100.0;
+        let memory_per_client = (memory_connected.saturating_sub(memory_before)) / CLIENTS as u64;
+
+        println!(
+            "{CLIENTS} idle subscribers: {} KiB resident ({memory_per_client} KiB per client), {cpu_percent:.2} % cpu while idle",
+            memory_connected
+        );
+        assert!(memory_per_client < 64, "{memory_per_client} KiB per client");
+        assert!(cpu_percent < 5.0, "{cpu_percent:.2} % cpu while idle");
+    }
+}
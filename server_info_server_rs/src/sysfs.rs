@@ -0,0 +1,17 @@
+use std::fs;
+use std::path::Path;
+
+/// Contents of a sysfs or procfs file without the trailing newline, none when it can not be read or is empty
+pub fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|contents| contents.trim().to_string())
+        .filter(|contents| !contents.is_empty())
+}
+
+/// Writes a file of a made up sysfs tree for tests, creating the directories it is in
+#[cfg(test)]
+pub fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
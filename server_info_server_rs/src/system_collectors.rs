@@ -0,0 +1,338 @@
+use crate::hwmon;
+use crate::proc_stat::ProcStat;
+use server_info_packets::cpu_info::CoreInfo;
+use server_info_packets::disk_info::DiskInfo;
+use server_info_packets::network_info::NetworkInterfaceInfo;
+use server_info_packets::sensor_info::{Sensors, Temperature};
+use server_info_server_rs::collector::{Collector, Section};
+use server_info_server_rs::interfaces::interfaces;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, System, SystemExt};
+
+/// sysinfo needs this long between two cpu refreshes to compute a usage
+const CPU_REFRESH_DELAY: Duration = Duration::from_millis(250);
+
+/// Name, kernel, os and host name, these rarely change so they are collected slowly
+pub struct HostCollector {
+    sys: System,
+}
+
+impl HostCollector {
+    pub fn new() -> Self {
+        Self { sys: System::new() }
+    }
+}
+
+impl Collector for HostCollector {
+    fn name(&self) -> &str {
+        "host"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        Ok(Section::Host {
+            system_name: self.sys.name().unwrap_or_default(),
+            kernel_version: self.sys.kernel_version().unwrap_or_default(),
+            os_version: self.sys.os_version().unwrap_or_default(),
+            host_name: self.sys.host_name().unwrap_or_default(),
+        })
+    }
+}
+
+pub struct CpuCollector {
+    sys: System,
+    last_refresh: Instant,
+    /// where the time went, on Linux
+    proc_stat: ProcStat,
+}
+
+impl CpuCollector {
+    pub fn new() -> Self {
+        let mut sys = System::new();
+        sys.refresh_cpu();
+        Self {
+            sys,
+            last_refresh: Instant::now(),
+            proc_stat: ProcStat::new(PathBuf::from("/proc/stat")),
+        }
+    }
+}
+
+impl Collector for CpuCollector {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        let since_refresh = self.last_refresh.elapsed();
+        if since_refresh < CPU_REFRESH_DELAY {
+            thread::sleep(CPU_REFRESH_DELAY - since_refresh);
+        }
+        self.sys.refresh_cpu();
+        self.last_refresh = Instant::now();
+
+        let total_cpus = self.sys.cpus().len();
+        let mut avg_cpu_usage = 0.0;
+
+        let mut times = self.proc_stat.read();
+        let mut cores = vec![];
+
+        for cpu in self.sys.cpus() {
+            avg_cpu_usage += cpu.cpu_usage();
+            cores.push(CoreInfo {
+                name: cpu.name().to_string(),
+                usage: cpu.cpu_usage(),
+                frequency: cpu.frequency(),
+                times: times.remove(cpu.name()),
+            });
+        }
+        avg_cpu_usage /= total_cpus as f32;
+
+        Ok(Section::Cpu {
+            total_cpus,
+            avg_cpu_usage,
+            cores,
+            cpu_times: times.remove("cpu"),
+        })
+    }
+}
+
+pub struct MemoryCollector {
+    sys: System,
+}
+
+impl MemoryCollector {
+    pub fn new() -> Self {
+        Self { sys: System::new() }
+    }
+}
+
+impl Collector for MemoryCollector {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        self.sys.refresh_memory();
+        Ok(Section::Memory {
+            total_ram: self.sys.total_memory(),
+            used_memory: self.sys.used_memory(),
+        })
+    }
+}
+
+pub struct DiskCollector {
+    sys: System,
+}
+
+impl DiskCollector {
+    pub fn new() -> Self {
+        Self { sys: System::new() }
+    }
+}
+
+impl Collector for DiskCollector {
+    fn name(&self) -> &str {
+        "disks"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        self.sys.refresh_disks_list();
+
+        let mut disks: Vec<DiskInfo> = vec![];
+
+        for disk in self.sys.disks() {
+            disks.push(DiskInfo {
+                name: disk.name().to_string_lossy().to_string(),
+                mount_point: disk.mount_point().display().to_string(),
+                file_system: String::from_utf8_lossy(disk.file_system()).to_string(),
+                total_space: disk.total_space(),
+                available_space: disk.available_space(),
+                is_removable: disk.is_removable(),
+            });
+        }
+
+        Ok(Section::Disks(disks))
+    }
+}
+
+/// Byte counts from sysinfo since the previous run, link state and error counters from /sys/class/net, addresses from getifaddrs
+pub struct NetworkCollector {
+    sys: System,
+    /// usually /sys/class/net
+    class_net: PathBuf,
+}
+
+impl NetworkCollector {
+    pub fn new() -> Self {
+        let mut sys = System::new();
+        sys.refresh_networks_list();
+        Self {
+            sys,
+            class_net: PathBuf::from("/sys/class/net"),
+        }
+    }
+}
+
+impl Collector for NetworkCollector {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        self.sys.refresh_networks_list();
+
+        let mut details = interfaces(&self.class_net);
+        let mut net_interfaces: Vec<NetworkInterfaceInfo> = vec![];
+
+        for (interface_name, data) in self.sys.networks() {
+            // only Linux has the details, elsewhere interfaces are just named
+            let interface = match details.iter().position(|d| d.name == *interface_name) {
+                Some(index) => details.swap_remove(index),
+                None => NetworkInterfaceInfo {
+                    name: interface_name.to_string(),
+                    ..Default::default()
+                },
+            };
+            net_interfaces.push(NetworkInterfaceInfo {
+                received: data.received(),
+                transmitted: data.transmitted(),
+                ..interface
+            });
+        }
+        net_interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Section::Network(net_interfaces))
+    }
+}
+
+/// Temperatures from sysinfo, fans and power from hwmon
+pub struct ComponentCollector {
+    /// the components are listed once, listing them again would reset their max
+    sys: System,
+    /// usually /sys/class/hwmon
+    hwmon_root: PathBuf,
+    /// highest temperature seen per label since the server started
+    maxes: HashMap<String, f32>,
+}
+
+impl ComponentCollector {
+    pub fn new() -> Self {
+        let mut sys = System::new();
+        sys.refresh_components_list();
+        Self {
+            sys,
+            hwmon_root: PathBuf::from("/sys/class/hwmon"),
+            maxes: HashMap::new(),
+        }
+    }
+
+    /// The reading of a sensor, none when it is not a number as sensors that fail to read report NaN
+    fn temperature(
+        &mut self,
+        label: &str,
+        current: f32,
+        critical: Option<f32>,
+    ) -> Option<Temperature> {
+        if !current.is_finite() {
+            return None;
+        }
+        let max = self.maxes.entry(label.to_string()).or_insert(current);
+        *max = max.max(current);
+        Some(Temperature {
+            label: label.to_string(),
+            current,
+            max: *max,
+            critical: critical.filter(|critical| critical.is_finite()),
+        })
+    }
+}
+
+impl Collector for ComponentCollector {
+    fn name(&self) -> &str {
+        "components"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        self.sys.refresh_components();
+
+        let mut temperatures = vec![];
+
+        let readings: Vec<(String, f32, Option<f32>)> = self
+            .sys
+            .components()
+            .iter()
+            .map(|component| {
+                (
+                    component.label().to_string(),
+                    component.temperature(),
+                    component.critical(),
+                )
+            })
+            .collect();
+        for (label, current, critical) in readings {
+            if let Some(temperature) = self.temperature(&label, current, critical) {
+                temperatures.push(temperature);
+            }
+        }
+        let (fans, power) = hwmon::read(&self.hwmon_root);
+
+        Ok(Section::Components(Sensors {
+            temperatures,
+            fans,
+            power,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_highest_temperature_and_skips_failed_readings() {
+        let mut collector = ComponentCollector {
+            sys: System::new(),
+            hwmon_root: PathBuf::new(),
+            maxes: HashMap::new(),
+        };
+        assert_eq!(
+            collector
+                .temperature("cpu", 50.0, Some(f32::NAN))
+                .unwrap()
+                .critical,
+            None
+        );
+        assert_eq!(collector.temperature("cpu", 70.0, None).unwrap().max, 70.0);
+        assert!(collector.temperature("cpu", f32::NAN, None).is_none());
+        let temperature = collector.temperature("cpu", 60.0, Some(100.0)).unwrap();
+        assert_eq!((temperature.current, temperature.max), (60.0, 70.0));
+        assert_eq!(collector.temperature("disk", 30.0, None).unwrap().max, 30.0);
+    }
+}
@@ -0,0 +1,179 @@
+use server_info_packets::service_info::ServiceStatus;
+use server_info_server_rs::collector::{Collector, Section};
+use std::process::Command;
+use std::time::Duration;
+
+/// The unit properties the collector asks systemd for
+const PROPERTIES: &str = "ActiveState,SubState,NRestarts,MemoryCurrent";
+
+/// Something that can answer `systemctl show` for a unit, stubbed out in tests
+pub trait UnitSource {
+    /// Returns the "Key=Value" lines systemd reports for the unit
+    fn show(&self, unit: &str) -> Result<String, String>;
+}
+
+/// Asks the local systemd by running `systemctl show`
+pub struct Systemctl;
+
+impl UnitSource for Systemctl {
+    fn show(&self, unit: &str) -> Result<String, String> {
+        let output = Command::new("systemctl")
+            .arg("show")
+            .arg(format!("--property={PROPERTIES}"))
+            .arg("--")
+            .arg(unit)
+            .output()
+            .map_err(|e| e.to_string())?;
+
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// Reports the state of every configured unit, units that can not be queried are reported as "unknown"
+pub fn collect_services(source: &dyn UnitSource, units: &[String]) -> Vec<ServiceStatus> {
+    let mut services: Vec<ServiceStatus> = vec![];
+
+    for unit in units {
+        match source.show(unit) {
+            Ok(output) => services.push(parse_show_output(unit, &output)),
+            Err(e) => {
+                println!("Unable to query unit {unit}: {e}");
+                services.push(ServiceStatus {
+                    name: unit.to_string(),
+                    active_state: "unknown".to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    services
+}
+
+/// Reports the state of the configured units
+pub struct ServiceCollector {
+    source: Box<dyn UnitSource + Send>,
+    units: Vec<String>,
+}
+
+impl ServiceCollector {
+    pub fn new(source: Box<dyn UnitSource + Send>, units: Vec<String>) -> Self {
+        Self { source, units }
+    }
+}
+
+impl Collector for ServiceCollector {
+    fn name(&self) -> &str {
+        "services"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn collect(&mut self) -> Result<Section, String> {
+        Ok(Section::Services(collect_services(
+            self.source.as_ref(),
+            &self.units,
+        )))
+    }
+}
+
+fn parse_show_output(unit: &str, output: &str) -> ServiceStatus {
+    let mut status = ServiceStatus {
+        name: unit.to_string(),
+        ..Default::default()
+    };
+
+    for line in output.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        match key {
+            "ActiveState" => status.active_state = value.to_string(),
+            "SubState" => status.sub_state = value.to_string(),
+            "NRestarts" => status.restarts = value.parse().unwrap_or_default(),
+            // "[not set]" or u64::MAX when memory accounting is disabled
+            "MemoryCurrent" => {
+                status.memory_usage = value.parse().ok().filter(|usage| *usage != u64::MAX)
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct StubSource(HashMap<&'static str, &'static str>);
+
+    impl UnitSource for StubSource {
+        fn show(&self, unit: &str) -> Result<String, String> {
+            self.0
+                .get(unit)
+                .map(|output| output.to_string())
+                .ok_or_else(|| "Unit not found.".to_string())
+        }
+    }
+
+    fn units(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn reports_unit_states() {
+        let source = StubSource(HashMap::from([
+            (
+                "nginx.service",
+                "ActiveState=active\nSubState=running\nNRestarts=0\nMemoryCurrent=7340032\n",
+            ),
+            (
+                "postgresql.service",
+                "ActiveState=failed\nSubState=failed\nNRestarts=3\nMemoryCurrent=[not set]\n",
+            ),
+            (
+                "worker.service",
+                "ActiveState=activating\nSubState=auto-restart\nNRestarts=12\nMemoryCurrent=18446744073709551615\n",
+            ),
+        ]));
+
+        let services = collect_services(
+            &source,
+            &units(&["nginx.service", "postgresql.service", "worker.service"]),
+        );
+
+        assert_eq!(
+            services[0],
+            ServiceStatus {
+                name: "nginx.service".to_string(),
+                active_state: "active".to_string(),
+                sub_state: "running".to_string(),
+                restarts: 0,
+                memory_usage: Some(7340032),
+            }
+        );
+        assert!(services[1].is_failed());
+        assert_eq!(services[1].restarts, 3);
+        assert_eq!(services[1].memory_usage, None);
+        assert!(services[2].is_restarting());
+        assert_eq!(services[2].restarts, 12);
+        assert_eq!(services[2].memory_usage, None);
+    }
+
+    #[test]
+    fn unknown_units_are_reported_as_unknown() {
+        let source = StubSource(HashMap::new());
+        let services = collect_services(&source, &units(&["missing.service"]));
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].active_state, "unknown");
+        assert!(!services[0].is_failed());
+    }
+}
@@ -0,0 +1,142 @@
+use server_info_client::{Client, ClientError, ClientOptions};
+use server_info_packets::server_info_packet::ServerInfo;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Samples kept for the sparklines
+pub const HISTORY_LENGTH: usize = 200;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// What is known about a host, updated by its polling thread.
+#[derive(Default)]
+pub struct HostState {
+    pub info: Option<ServerInfo>,
+    /// average cpu usage in whole percent, oldest first
+    pub cpu_history: VecDeque<u64>,
+    /// memory usage in whole percent, oldest first
+    pub memory_history: VecDeque<u64>,
+    pub error: Option<String>,
+    /// the server said it was shutting down, the error is the reason otherwise
+    pub stopped: bool,
+}
+
+impl HostState {
+    fn push(&mut self, info: ServerInfo) {
+        let memory_usage = (info.used_memory * 100)
+            .checked_div(info.total_ram)
+            .unwrap_or(0);
+        push_capped(&mut self.cpu_history, info.avg_cpu_usage.round() as u64);
+        push_capped(&mut self.memory_history, memory_usage);
+        self.info = Some(info);
+        self.error = None;
+        self.stopped = false;
+    }
+}
+
+fn push_capped(history: &mut VecDeque<u64>, value: u64) {
+    if history.len() == HISTORY_LENGTH {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+pub struct Host {
+    pub address: String,
+    pub state: Arc<Mutex<HostState>>,
+}
+
+impl Host {
+    /// Subscribes to the server at address on its own thread, reconnecting whenever the connection drops
+    pub fn spawn(address: String, options: ClientOptions, interval: Duration) -> Self {
+        let state = Arc::new(Mutex::new(HostState::default()));
+
+        {
+            let address = address.to_string();
+            let state = state.clone();
+            thread::spawn(move || loop {
+                let error = match Client::connect(&address, &options) {
+                    Ok(client) => {
+                        // the server pushes an info every interval, the subscription only ends with an error
+                        let mut infos = client.subscribe(interval);
+                        loop {
+                            match infos.next() {
+                                Some(Ok(info)) => {
+                                    state.lock().expect("Host lock poisoned.").push(info)
+                                }
+                                Some(Err(e)) => break e,
+                                None => unreachable!("Subscriptions end after their error."),
+                            }
+                        }
+                    }
+                    Err(e) => e,
+                };
+                let mut state = state.lock().expect("Host lock poisoned.");
+                state.stopped = matches!(error, ClientError::ServerStopped);
+                state.error = Some(error.to_string());
+                drop(state);
+                thread::sleep(RECONNECT_DELAY);
+            });
+        }
+
+        Self { address, state }
+    }
+}
+
+pub struct App {
+    pub hosts: Vec<Host>,
+    pub selected: usize,
+    pub should_quit: bool,
+    /// leaves loopback and virtual interfaces like veth and docker0 out of the network table
+    pub hide_virtual: bool,
+}
+
+impl App {
+    pub fn new(addresses: Vec<String>, options: ClientOptions, interval: Duration) -> Self {
+        Self {
+            hosts: addresses
+                .into_iter()
+                .map(|address| Host::spawn(address, options.clone(), interval))
+                .collect(),
+            selected: 0,
+            should_quit: false,
+            hide_virtual: false,
+        }
+    }
+
+    pub fn next_host(&mut self) {
+        self.selected = (self.selected + 1) % self.hosts.len();
+    }
+
+    pub fn previous_host(&mut self) {
+        self.selected = (self.selected + self.hosts.len() - 1) % self.hosts.len();
+    }
+
+    pub fn select_host(&mut self, index: usize) {
+        if index < self.hosts.len() {
+            self.selected = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_capped() {
+        let mut state = HostState::default();
+        for i in 0..HISTORY_LENGTH + 5 {
+            state.push(ServerInfo {
+                avg_cpu_usage: i as f32,
+                total_ram: 200,
+                used_memory: 50,
+                ..Default::default()
+            });
+        }
+        assert_eq!(state.cpu_history.len(), HISTORY_LENGTH);
+        assert_eq!(state.cpu_history.front(), Some(&5));
+        assert_eq!(state.memory_history.back(), Some(&25));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::app::App;
+use clap::Parser;
+use ratatui::crossterm::event;
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use server_info_client::{ClientOptions, Compression, Encoding, TlsOptions};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+mod app;
+mod ui;
+
+/// htop like terminal dashboard for one or more server_info_server_rs hosts.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Addresses of the servers, switch between them with tab or 1-9
+    #[arg(default_value = "localhost:8111")]
+    addresses: Vec<String>,
+
+    /// Seconds between two updates of a host
+    #[arg(short, long, default_value = "1", value_parser = parse_interval)]
+    interval: Duration,
+
+    /// Token to send when a server asks for one
+    #[arg(long)]
+    token: Option<String>,
+
+    /// Connect with tls, e.g. to a tls terminating proxy in front of the servers
+    #[arg(long)]
+    tls: bool,
+
+    /// Pem file with the certificates to trust for tls, instead of the usual web roots
+    #[arg(long, requires = "tls")]
+    tls_ca: Option<PathBuf>,
+
+    /// Encoding to ask the servers for: json, msgpack, cbor or bincode
+    #[arg(long, default_value_t = Encoding::Bincode)]
+    encoding: Encoding,
+
+    /// Compression to ask the servers for, for slow or metered links: none, deflate or zstd
+    #[arg(long, default_value_t = Compression::None)]
+    compression: Compression,
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let options = ClientOptions {
+        auth_token: args.token,
+        tls: args.tls.then_some(TlsOptions {
+            server_name: None,
+            ca_file: args.tls_ca,
+        }),
+        encodings: vec![args.encoding],
+        compressions: vec![args.compression],
+        ..Default::default()
+    };
+    let mut app = App::new(args.addresses, options, args.interval);
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => app.should_quit = true,
+                KeyCode::Tab | KeyCode::Right => app.next_host(),
+                KeyCode::BackTab | KeyCode::Left => app.previous_host(),
+                KeyCode::Char('v') => app.hide_virtual = !app.hide_virtual,
+                KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                    app.select_host(c as usize - '1' as usize)
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Seconds as a duration, a value Duration can not hold or that would never wait is rejected by clap
+fn parse_interval(seconds: &str) -> Result<Duration, String> {
+    let seconds: f32 = seconds.parse().map_err(|e| format!("{e}"))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err("expected a number of seconds greater than 0".to_string());
+    }
+    Duration::try_from_secs_f32(seconds).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_intervals_that_can_not_be_slept() {
+        assert_eq!(parse_interval("0.5"), Ok(Duration::from_millis(500)));
+        for interval in ["-1", "0", "nan", "inf", "1e30", "soon"] {
+            assert!(parse_interval(interval).is_err(), "{interval}");
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::app::{App, HostState};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Sparkline, Table, Tabs};
+use ratatui::Frame;
+use server_info_packets::network_info::InterfaceKind;
+
+const GB: f64 = 1000000000.0;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(6),
+            Constraint::Min(6),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+
+    let titles: Vec<Line> = app
+        .hosts
+        .iter()
+        .enumerate()
+        .map(|(i, host)| Line::from(format!("{} {}", i + 1, host.address)))
+        .collect();
+    let tabs = Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title("Hosts"))
+        .select(app.selected)
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Yellow),
+        );
+    frame.render_widget(tabs, chunks[0]);
+
+    let host = &app.hosts[app.selected];
+    let state = host.state.lock().expect("Host lock poisoned.");
+
+    draw_gauges(frame, &state, chunks[1]);
+    draw_history(frame, &state, chunks[2]);
+    draw_tables(frame, &state, app.hide_virtual, chunks[3]);
+
+    let status = match (&state.error, &state.info) {
+        (Some(_), _) if state.stopped => format!("{}: server stopped", host.address),
+        (Some(error), _) => format!("{}: {error}", host.address),
+        (None, Some(info)) => format!(
+            "{} - {} {} (kernel {})",
+            info.host_name, info.system_name, info.os_version, info.kernel_version
+        ),
+        (None, None) => format!("Connecting to {}", host.address),
+    };
+    let status_color = match &state.error {
+        None => Color::Reset,
+        Some(_) if state.stopped => Color::Yellow,
+        Some(_) => Color::Red,
+    };
+    let footer = Paragraph::new(format!(
+        "{status} | q: quit, tab/arrows/1-9: switch host, v: hide virtual interfaces"
+    ))
+    .style(Style::default().fg(status_color));
+    frame.render_widget(footer, chunks[4]);
+}
+
+fn draw_gauges(frame: &mut Frame, state: &HostState, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    let (cpu_usage, used_memory, total_ram) = match &state.info {
+        Some(info) => (info.avg_cpu_usage, info.used_memory, info.total_ram),
+        None => (0.0, 0, 0),
+    };
+
+    let cpu = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title("CPU"))
+        .gauge_style(Style::default().fg(usage_color(cpu_usage as f64)))
+        .ratio((cpu_usage as f64 / 100.0).clamp(0.0, 1.0))
+        .label(format!("{cpu_usage:.2} %"));
+    frame.render_widget(cpu, chunks[0]);
+
+    let memory_ratio = if total_ram == 0 {
+        0.0
+    } else {
+        used_memory as f64 / total_ram as f64
+    };
+    let memory = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title("RAM"))
+        .gauge_style(Style::default().fg(usage_color(memory_ratio * 100.0)))
+        .ratio(memory_ratio.clamp(0.0, 1.0))
+        .label(format!(
+            "{:.2} / {:.2} GB",
+            used_memory as f64 / GB,
+            total_ram as f64 / GB
+        ));
+    frame.render_widget(memory, chunks[1]);
+}
+
+fn draw_history(frame: &mut Frame, state: &HostState, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    // only the newest samples that fit are drawn
+    let fit = |history: &std::collections::VecDeque<u64>, width: u16| -> Vec<u64> {
+        let width = width.saturating_sub(2) as usize;
+        history
+            .iter()
+            .skip(history.len().saturating_sub(width))
+            .copied()
+            .collect()
+    };
+
+    let cpu_history = fit(&state.cpu_history, chunks[0].width);
+    let cpu = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("CPU history"))
+        .data(&cpu_history)
+        .max(100)
+        .style(Style::default().fg(Color::Cyan));
+    frame.render_widget(cpu, chunks[0]);
+
+    let memory_history = fit(&state.memory_history, chunks[1].width);
+    let memory = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("RAM history"))
+        .data(&memory_history)
+        .max(100)
+        .style(Style::default().fg(Color::Magenta));
+    frame.render_widget(memory, chunks[1]);
+}
+
+fn draw_tables(frame: &mut Frame, state: &HostState, hide_virtual: bool, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+    let (disks, interfaces) = match &state.info {
+        Some(info) => (info.disks.as_slice(), info.net_interfaces.as_slice()),
+        None => (&[][..], &[][..]),
+    };
+
+    let disk_rows = disks.iter().map(|disk| {
+        let usage = if disk.total_space == 0 {
+            0.0
+        } else {
+            disk.used_space() as f64 / disk.total_space as f64 * 100.0
+        };
+        Row::new(vec![
+            Cell::from(disk.mount_point.to_string()),
+            Cell::from(disk.file_system.to_string()),
+            Cell::from(format!("{:.2} GB", disk.used_space() as f64 / GB)),
+            Cell::from(format!("{:.2} GB", disk.total_space as f64 / GB)),
+            Cell::from(format!("{usage:.1} %")).style(Style::default().fg(usage_color(usage))),
+        ])
+    });
+    let disk_table = Table::new(
+        disk_rows,
+        [
+            Constraint::Percentage(35),
+            Constraint::Percentage(15),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(14),
+        ],
+    )
+    .header(Row::new(vec!["Mount", "FS", "Used", "Total", "Use"]).style(header_style))
+    .block(Block::default().borders(Borders::ALL).title("Disks"));
+    frame.render_widget(disk_table, chunks[0]);
+
+    let interface_rows = interfaces
+        .iter()
+        .filter(|interface| !hide_virtual || interface.kind == InterfaceKind::Physical)
+        .map(|interface| {
+            let (link, link_color) = match interface.up {
+                Some(true) => ("up", Color::Green),
+                Some(false) => ("down", Color::Red),
+                None => ("", Color::Reset),
+            };
+            let errors = interface.rx_errors + interface.tx_errors;
+            let errors_color = match errors {
+                0 => Color::Reset,
+                _ => Color::Yellow,
+            };
+            Row::new(vec![
+                Cell::from(interface.name.to_string()),
+                Cell::from(link).style(Style::default().fg(link_color)),
+                Cell::from(format!("{} B", interface.received)),
+                Cell::from(format!("{} B", interface.transmitted)),
+                Cell::from(errors.to_string()).style(Style::default().fg(errors_color)),
+            ])
+        });
+    let interface_table = Table::new(
+        interface_rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Percentage(12),
+            Constraint::Percentage(22),
+            Constraint::Percentage(22),
+            Constraint::Percentage(14),
+        ],
+    )
+    .header(Row::new(vec!["Interface", "Link", "RX", "TX", "Errors"]).style(header_style))
+    .block(Block::default().borders(Borders::ALL).title("Network"));
+    frame.render_widget(interface_table, chunks[1]);
+}
+
+fn usage_color(percent: f64) -> Color {
+    if percent >= 90.0 {
+        Color::Red
+    } else if percent >= 70.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}